@@ -0,0 +1,67 @@
+//! Runs a program and shows how often it wrote each byte, counted by a `StepHook`.
+//!
+//! ```text
+//! cargo run --example output_histogram -- program.b [input_file]
+//! ```
+
+use std::{env, fs, process::exit};
+
+use brainfuck::{parse, BFInterpreter, State, StepHook};
+
+/// Widest bar in the histogram
+const BAR_WIDTH: usize = 50;
+
+/// Counts every output byte and prints the counts once the program stops
+struct Histogram {
+	counts: [usize; 256],
+}
+
+impl StepHook for Histogram {
+	fn on_output(&mut self, byte: u8) {
+		self.counts[byte as usize] += 1;
+	}
+
+	fn on_state_change(&mut self, state: &State) {
+		if *state == State::Running {
+			return;
+		}
+		let total: usize = self.counts.iter().sum();
+		println!("{total} bytes written, stopped with {state:?}");
+		let max = self.counts.iter().copied().max().unwrap_or(0).max(1);
+		let mut bytes: Vec<u8> = (0..=255).filter(|&b| self.counts[b as usize] > 0).collect();
+		bytes.sort_by_key(|&b| std::cmp::Reverse(self.counts[b as usize]));
+		for byte in bytes {
+			let count = self.counts[byte as usize];
+			let name = format!("'{}'", byte.escape_ascii());
+			let bar = "#".repeat((count * BAR_WIDTH).div_ceil(max));
+			println!("{name:>6} {count:>8} {bar}");
+		}
+	}
+}
+
+fn main() {
+	let args: Vec<String> = env::args().skip(1).collect();
+	let Some(path) = args.first() else {
+		eprintln!("usage: output_histogram <source_file> [input_file]");
+		exit(1);
+	};
+	let read = |path: &str| {
+		fs::read(path).unwrap_or_else(|err| {
+			eprintln!("could not read {path}: {err}");
+			exit(1);
+		})
+	};
+	let source = String::from_utf8_lossy(&read(path)).into_owned();
+	let input = args.get(1).map(|path| read(path)).unwrap_or_default();
+	let program = parse(&source).unwrap_or_else(|errors| {
+		for err in errors {
+			eprintln!("{err}");
+		}
+		exit(1);
+	});
+	let mut interpreter = BFInterpreter::new(program, input);
+	interpreter.set_history_limit(0);
+	interpreter.track_writes = false;
+	interpreter.add_hook(Histogram { counts: [0; 256] });
+	interpreter.run();
+}
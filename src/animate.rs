@@ -0,0 +1,85 @@
+//! Stepping on a timer, redrawing the view after every step.
+
+use std::{
+	io::{stdout, Write},
+	mem,
+	sync::atomic,
+	time::{Duration, Instant},
+};
+
+use brainfuck::State;
+
+use crate::{
+	tui::{read_key, Key, Terminal},
+	Debugger,
+};
+
+/// Steps per second when `animate` is given no speed
+pub const DEFAULT_RATE: usize = 10;
+const MAX_RATE: usize = 1000;
+
+/// Steps `rate` times per second until a key is pressed or the program stops
+pub fn run(debugger: &mut Debugger, rate: usize) -> Result<(), String> {
+	let mut rate = rate.min(MAX_RATE);
+	let terminal = Terminal::enter("animate")?;
+	// the view already shows the output
+	let stream = mem::replace(&mut debugger.stream, false);
+	let start_steps = debugger.interpreter.steps;
+	loop {
+		debugger.interpreter.step(1);
+		print!("\x1b[H\x1b[J");
+		debugger.show();
+		if debugger.interpreter.state != State::Running {
+			break;
+		}
+		println!("animating at {rate} steps per second. + faster, - slower, any other key stops");
+		_ = stdout().flush();
+		match wait_for_key(debugger, Duration::from_secs(1) / rate as u32) {
+			None => (),
+			Some(Key::Char('+')) => rate = (rate * 2).min(MAX_RATE),
+			Some(Key::Char('-')) => rate = (rate / 2).max(1),
+			Some(_) => break,
+		}
+		if interrupted(debugger) {
+			break;
+		}
+	}
+	drop(terminal);
+	debugger.stream = stream;
+	println!(
+		"animated {} steps",
+		debugger.interpreter.steps - start_steps
+	);
+	Ok(())
+}
+
+/// Waits until `delay` is over, or returns early with the first key pressed
+fn wait_for_key(debugger: &Debugger, delay: Duration) -> Option<Key> {
+	let end = Instant::now() + delay;
+	loop {
+		let left = end.saturating_duration_since(Instant::now());
+		if left.is_zero() || interrupted(debugger) {
+			return None;
+		}
+		let mut poll = libc::pollfd {
+			fd: 0,
+			events: libc::POLLIN,
+			revents: 0,
+		};
+		// SAFETY: polls the one descriptor we pass, a Ctrl-C only makes it return early
+		let ready = unsafe { libc::poll(&mut poll, 1, left.as_millis().max(1) as i32) };
+		if ready > 0 {
+			if let Some(key) = read_key() {
+				return Some(key);
+			}
+		}
+	}
+}
+
+fn interrupted(debugger: &Debugger) -> bool {
+	debugger
+		.interpreter
+		.interrupt
+		.as_ref()
+		.is_some_and(|flag| flag.swap(false, atomic::Ordering::Relaxed))
+}
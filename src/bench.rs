@@ -0,0 +1,47 @@
+//! Timing the program from the start with nothing else attached, to measure the interpreter.
+
+use std::time::{Duration, Instant};
+
+use crate::{BFInterpreter, State};
+
+/// One timed run of the whole program
+#[derive(Debug)]
+pub struct BenchRun {
+	pub steps: usize,
+	pub time: Duration,
+	/// `EndOfProgram` unless something like the step limit stopped it
+	pub state: State,
+	pub output: Vec<u8>,
+}
+
+impl BFInterpreter {
+	/// Runs the program from the start on its input, with the same settings but none of the
+	/// watchers, breakpoints, history, tracing or streaming that slow it down. `!` is skipped. Leaves `self` as it was
+	pub fn bench_run(&self) -> BenchRun {
+		let mut run = BFInterpreter::new(self.program.clone(), self.input.clone());
+		run.eof_behavior = self.eof_behavior;
+		run.numeric_io = self.numeric_io;
+		run.cell_size = self.cell_size;
+		run.wrap = self.wrap;
+		run.tape_size = self.tape_size;
+		run.tape_mode = self.tape_mode;
+		run.max_steps = self.max_steps;
+		run.memory_limit = self.memory_limit;
+		// a `!` would end the run early
+		run.ignore_code_breaks = true;
+		run.interrupt = self.interrupt.clone();
+		run.transfer_loops = self.transfer_loops;
+		run.history_limit = 0;
+		run.track_writes = false;
+		run.initial_memory = self.initial_memory.clone();
+		run.reset();
+		let start = Instant::now();
+		run.run();
+		BenchRun {
+			steps: run.steps,
+			time: start.elapsed(),
+			state: run.state,
+			output: run.output,
+		}
+	}
+}
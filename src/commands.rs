@@ -0,0 +1,606 @@
+//! The list of debugger commands, used both for `help` and to check what was typed at the prompt.
+
+use crate::style::Paint;
+
+pub struct CommandInfo {
+	/// The first word of the command
+	pub name: &'static str,
+	pub aliases: &'static [&'static str],
+	pub usage: &'static str,
+	pub summary: &'static str,
+	/// Longer explanation with examples, shown by `help <name>`
+	pub details: &'static str,
+}
+
+const fn command(name: &'static str, usage: &'static str, summary: &'static str) -> CommandInfo {
+	CommandInfo {
+		name,
+		aliases: &[],
+		usage,
+		summary,
+		details: "",
+	}
+}
+
+impl CommandInfo {
+	const fn details(self, details: &'static str) -> Self {
+		Self { details, ..self }
+	}
+
+	const fn aliases(self, aliases: &'static [&'static str]) -> Self {
+		Self { aliases, ..self }
+	}
+}
+
+/// Every command, in the order they are listed by `help`. Several entries can share a name when a command has different forms
+pub const COMMANDS: &[CommandInfo] = &[
+	command("help", "help [command]", "List all commands, or explain one of them in more detail")
+		.details("Example: help break"),
+	command(
+		"step",
+		"step [n] | s [n]",
+		"Step the progam n times (this is the default behavior when pressing enter with no command)",
+	)
+	.details("Stops early if a breakpoint or watcher triggers.\nExample: step 100")
+	.aliases(&["s"]),
+	command(
+		"next",
+		"next | n",
+		"Step over the loop starting at the current instruction, or step once if not on a loop",
+	)
+	.aliases(&["n"]),
+	command(
+		"stepline",
+		"stepline [n] | sl [n]",
+		"Run until the current instruction is on another source line, or do that n times",
+	)
+	.details("Like stepping by source line in other debuggers, for programs with many instructions per line. A loop on one line runs until it exits onto another line. Breakpoints and watchers still stop it.")
+	.aliases(&["sl"]),
+	command(
+		"finish",
+		"finish | f",
+		"Run until the innermost loop containing the current instruction exits",
+	)
+	.aliases(&["f"]),
+	command(
+		"until",
+		"until [file:]<line>[:column]",
+		"Run until the instruction at the given source location is reached",
+	)
+	.details("Without a column, stops at the first instruction reached on that line.\nExample: until 12:4"),
+	command(
+		"goto",
+		"goto [file:]<line>:<column> | goto index <n>",
+		"Move to an instruction without executing anything in between",
+	)
+	.details("Warns when the jump goes into or out of a loop, since the loop was not entered by its `[`. Useful to run part of a program again after `set mem`.\nExample: goto 3:10"),
+	command("back", "back [n]", "Undo the last n executed instructions (default 1)").details(
+		"How far back you can go is limited by `set history`. A `run` without watchers or breakpoints is undone all at once.\nExample: back 20",
+	),
+	command(
+		"info",
+		"info",
+		"Show the source and input files, the state, how much input was read, breakpoints, watchers, labels, aliases and settings",
+	),
+	command(
+		"info",
+		"info breakpoints | watches | labels | aliases | settings",
+		"Show only one part of what `info` shows",
+	)
+	.details("Breakpoints are shown with how many times they have stopped the program.\nSettings are shown with the names used by `set`."),
+	command(
+		"info",
+		"info loops",
+		"List the loops the program is inside, with when they were entered and how many times they repeated",
+	),
+	command(
+		"where",
+		"where | stack | bt",
+		"Show the loops around the current instruction like a call stack, innermost first",
+	)
+	.details("Each line shows where the loop starts, the cell it was entered on with that cell's current value, and the iteration it is on")
+	.aliases(&["stack", "bt"]),
+	command(
+		"skip",
+		"skip [loop]",
+		"Move past the current instruction without executing it",
+	)
+	.details(
+		"Memory and the step count are left as they are. With `loop`, the whole loop starting at the current `[` is skipped.\nExample: skip loop",
+	),
+	command(
+		"reset",
+		"reset [input <file>]",
+		"Start the program over from step 0, keeping watchers, breakpoints, labels and settings",
+	)
+	.details("With input, the input is replaced by the contents of the file"),
+	command(
+		"reload",
+		"reload [keep-memory]",
+		"Read the source file again and start over, keeping watchers, breakpoints, labels and settings",
+	)
+	.details("Breakpoints move to the instruction at the same line and column of the new program, or are removed if there is none.\nWith keep-memory, the tape keeps its values. If the new source has errors, the old program stays loaded"),
+	command("run", "run | r | c", "Run the program until the end")
+		.details("Press Ctrl-C to stop a program that runs for too long. In a terminal, a status line shows the steps, speed, memory size and output length while it runs")
+		.aliases(&["r", "c"]),
+	command(
+		"continue",
+		"continue",
+		"Clear an error from moving the memory pointer off the tape, and run on from the instruction that caused it",
+	)
+	.details("The `<` or `>` that would have left the tape is not executed, so after moving the pointer with `set ptr` it is tried again. Use `skip` or `goto` to go past it instead, the error stays until continue clears it. Without an error, the same as run.\nExample: set ptr 5; continue"),
+	command(
+		"animate",
+		"animate [steps per second]",
+		"Step on a timer, redrawing after every step, until a key is pressed or the program stops",
+	)
+	.details("Defaults to 10 steps per second. While animating, + doubles the speed and - halves it.\nExample: animate 20"),
+	command(
+		"set",
+		"set stream on|off",
+		"When on, `run` prints output as soon as the program writes it (default on)",
+	)
+	.details("Control characters are escaped unless `set output raw` is chosen. Stepping never streams"),
+	command(
+		"watch",
+		"watch <cell> <value>",
+		"Create a breakpoint. Execution will stop when <cell> has <value>",
+	)
+	.details("`w` is short for `watch`. Values can be numbers, hex like 0x41 or characters like 'A', '\\n' or '\\0', here and in set mem, break if, break output and find mem.\nExample: watch 5 '\\n'")
+	.aliases(&["w"]),
+	command(
+		"watch",
+		"watch <cell> <==|!=|<|>><value>",
+		"Stop when the cell value compares true, e.g. watch 3 >100",
+	),
+	command("watch", "watch <cell>", "Stop whenever the cell changes")
+		.details("Examples:\n  watch 3        stop when cell 3 changes\n  watch 3 0      stop when cell 3 becomes 0\n  watch 3 !=10   stop when cell 3 is not 10"),
+	command(
+		"watch",
+		"watch ptr [==|!=|<|>]<cell>",
+		"Stop when the memory pointer moves and then compares true, e.g. watch ptr >100",
+	),
+	command(
+		"watch",
+		"watch ptr range <start> <end>",
+		"Stop when the memory pointer moves outside of the cells start to end",
+	)
+	.details("Catches a program running off the end of a buffer. Listed and removed like the other watchers.\nExample: watch ptr range 10 20"),
+	command(
+		"watch",
+		"watch outlen <n>",
+		"Stop after the . that makes the output n bytes long",
+	),
+	command(
+		"watch",
+		"watch outchar <byte> [k]",
+		"Stop after the . that writes the byte for the k-th time (default 1), e.g. watch outchar '\\n' 5",
+	)
+	.details("The byte is a number, 'c' or \\xNN. Stops right after the program prints its 5th line without knowing its memory layout, and shows the output offset and the . that wrote it. Listed and removed like the other watchers."),
+	command(
+		"lastwrite",
+		"lastwrite <cell>",
+		"Show the step and instruction that last changed a cell, with its old and new value",
+	)
+	.details("Stepping back forgets the writes that were undone, along with what the cell held before them.\nExample: lastwrite 7"),
+	command(
+		"set",
+		"set track-writes on|off",
+		"Remember the last write to every cell for `lastwrite` (default on). Turning it off makes `run` faster with `set history 0`",
+	),
+	command(
+		"record",
+		"record <cell>",
+		"Start keeping every write to a cell for `history`, up to the last 10000",
+	)
+	.details("Several cells can be recorded at once. Without a cell, lists the cells being recorded.\nExample: record 2"),
+	command(
+		"record",
+		"record stop <cell>",
+		"Stop recording a cell, keeping the writes collected so far",
+	),
+	command(
+		"record",
+		"record session <file> | record stop",
+		"Append every command that works to a file, to replay them later with `replay <file>` or -x <file>",
+	)
+	.details("The file starts with a header naming the source and input and a hash of the program, so replaying it against a changed program warns. Input typed at the program input prompt is recorded as `input type`, so the replay does not wait for it."),
+	command(
+		"replay",
+		"replay <file>",
+		"Run the debugger commands in a file, like -x does at startup",
+	),
+	command(
+		"history",
+		"history <cell> [n]",
+		"Show the recorded writes to a cell, most recent last, or only the last n",
+	)
+	.details("Example: history $sum 5"),
+	command("watches", "watches", "List memory watchers"),
+	command(
+		"unwatch",
+		"unwatch <id> | unwatch all",
+		"Remove a memory watcher, or all of them",
+	),
+	command(
+		"break",
+		"break [file:]<line>:<column> | break <file>:<line> | break <index>",
+		"Create a breakpoint at a source location or program index",
+	)
+	.details("With several source files, the file name picks the file, otherwise it is the first one. With a file name and no column the breakpoint is on the first instruction of the line. `b` is short for `break`\nExample: break main.b:3:7")
+	.aliases(&["b"]),
+	command(
+		"break",
+		"break <location> if <cell|index> <==|!=|<|>> <value>",
+		"Create a breakpoint that only stops when the current cell (or the cell at index) matches",
+	),
+	command("break", "break step <n>", "Stop when the step count reaches n").details(
+		"Examples:\n  break 3:10             stop before the instruction at line 3, column 10\n  break 42 if cell == 0  stop at program index 42 when the current cell is 0\n  break 3:10 if 5 > 100  stop there when cell 5 is above 100\n  break step 5000        stop after 5000 steps",
+	),
+	command(
+		"break",
+		"break loop <n>",
+		"Stop when a loop starts its body more than n times in a row",
+	)
+	.details("Useful for finding loops that never end because their counter cell got overwritten.\nExample: break loop 10000"),
+	command(
+		"break",
+		"break memsize <n>",
+		"Stop right after a > grows the tape past n cells",
+	)
+	.details("Shows where the pointer is and which > grew the tape. For a hard limit, use `set max-memory`.\nExample: break memsize 30000"),
+	command(
+		"break",
+		"break output <byte> | break output \"text\"",
+		"Stop right after the program writes a byte, or when the output ends with some text",
+	)
+	.details("The byte can be a number, hex like 0x0a, a character like 'X' or an escape like \\x0a. The stop shows at what offset in the output the match starts.\nExamples:\n  break output 'X'\n  break output \"ERROR\""),
+	command(
+		"break",
+		"break <any of the above> log",
+		"Make a logpoint, which prints a line when it triggers and keeps running instead of stopping",
+	)
+	.details("The line shows the step, the source location and the current cell with its value. Only the first 100 lines per logpoint are shown for each command, the rest are counted.\nExample: break 12:5 log"),
+	command(
+		"break",
+		"break <any of the above> once | ignore <n>",
+		"Delete the breakpoint after it stops the program once, or let it trigger n times before it stops",
+	)
+	.details("Ignored hits are only counted when the condition matched, so `ignore 10` on a conditional breakpoint stops the 11th time the condition is true. `info breakpoints` shows how many times each breakpoint triggered.\nExamples:\n  break 12:5 once\n  break 12:5 if cell == 0 ignore 36"),
+	command(
+		"watch",
+		"watch <cell> [condition] log",
+		"Make a watcher that prints a line when it triggers and keeps running instead of stopping",
+	)
+	.details("Example: watch 3 0 log"),
+	command("breakpoints", "breakpoints", "List breakpoints"),
+	command("delete", "delete <id>", "Remove a breakpoint"),
+	command(
+		"disable",
+		"disable <id> | disable watch <id> | disable code [location] | disable all",
+		"Keep a breakpoint, watcher or ! in the source from stopping the program, without deleting it",
+	)
+	.details("Disabled breakpoints and watchers stay in the lists, greyed out. `disable code` without a location disables every ! in the source.\nExamples:\n  disable 2\n  disable watch 1\n  disable code 14:3"),
+	command(
+		"enable",
+		"enable <id> | enable watch <id> | enable code [location] | enable all",
+		"Let a disabled breakpoint, watcher or ! stop the program again",
+	),
+	command(
+		"snapshot",
+		"snapshot <name>",
+		"Save the current program state under a name",
+	),
+	command(
+		"restore",
+		"restore <name>",
+		"Go back to a saved state. Breakpoints and watchers are kept as they are",
+	),
+	command("snapshots", "snapshots", "List saved states"),
+	command(
+		"diff",
+		"diff <name>",
+		"Show what changed since a snapshot: the cells as index: old -> new, the pointers, the steps and the output added",
+	)
+	.details("Answers what a loop actually did: take a snapshot before it, run it, then diff. Only the first 40 changed cells are listed.\nExample: snapshot before; finish; diff before"),
+	command(
+		"trace",
+		"trace on <file> [last <n>]",
+		"Write a line for every executed instruction to a file: step, index, line:column, instruction, memory pointer and cell value",
+	)
+	.details("With last n, only the latest n instructions are kept in the file.\nExample: trace on trace.txt last 1000"),
+	command("trace", "trace off", "Stop tracing"),
+	command(
+		"profile",
+		"profile on | profile off",
+		"Count how many times each instruction runs. Also enabled at startup with --profile",
+	),
+	command(
+		"profile",
+		"profile",
+		"Show the program colored by execution count, and the most executed locations",
+	),
+	command("profile", "profile reset", "Set all execution counts back to zero"),
+	command(
+		"coverage",
+		"coverage",
+		"Show the program with instructions that never ran dimmed, how many ran, and the lines never reached",
+	)
+	.details("Counts since the program was loaded or `coverage reset`, across `reset` and stepping back."),
+	command(
+		"match",
+		"match [[file:]line[:column]]",
+		"Show where the partner of the current bracket is, or of the bracket at a location",
+	)
+	.details("While on a bracket, its partner is also highlighted in magenta in the program."),
+	command(
+		"find",
+		"find mem <value> | find mem \"<text>\"",
+		"List the cells holding a value, or where a string of bytes starts, e.g. find mem \"Hello\\n\"",
+	),
+	command(
+		"find",
+		"find prog <instructions>",
+		"List the program index and line:column of every place an instruction sequence appears, e.g. find prog [->+<]",
+	),
+	command(
+		"code",
+		"code <code>",
+		"Add instructions to the end of the program, when started without a source file",
+	)
+	.details("The typed lines are the source of the program, so locations and the source view work as with a file. A program that ended goes on with the new instructions. Code from a `[` whose `]` is not typed yet waits until it is, and code with a `]` that closes nothing is rejected.\nExample: code ++++[>++++<-]"),
+	command(
+		"save",
+		"save program <file>",
+		"Write the code typed with `code` to a file",
+	),
+	command(
+		"eval",
+		"eval <code>",
+		"Run a snippet on the current memory, pointer and input, leaving the program where it was. Its output is shown separately",
+	)
+	.details("Watchers and breakpoints do not see the snippet, and it can not contain `!`.\nExample: eval .>.>.>.   print cells 0 to 3 and move the pointer 3 cells right"),
+	command(
+		"eval!",
+		"eval! <code>",
+		"Like eval, but also add the snippet's output to the program output",
+	),
+	command(
+		"stats",
+		"stats",
+		"Show instruction counts in the program and how often each kind ran, deepest loop nesting, and memory, input and output use",
+	)
+	.details("Execution counts are the same ones `coverage` uses, so they are kept across `reset` until `coverage reset`."),
+	command(
+		"coverage",
+		"coverage reset",
+		"Forget which instructions have run, without changing the program state",
+	),
+	command(
+		"load",
+		"load mem <file> [start]",
+		"Write the bytes of a file into memory from cell start (default 0), growing the tape as needed",
+	)
+	.details("Watchers see the new values. Useful with `set ptr` and `goto` to test one part of a program.\nExample: load mem state.bin 10"),
+	command(
+		"dump",
+		"dump mem <file> [start len] [--format raw|text]",
+		"Write memory cells to a file, all of them unless a range is given",
+	)
+	.details("Raw writes each cell as little endian bytes of the cell size, text writes one `index value` line per cell.\nExample: dump mem cells.txt 0 100 --format text"),
+	command("dump", "dump output <file>", "Write the output so far to a file, byte for byte"),
+	command(
+		"dump",
+		"dump state <file>",
+		"Write the pointers, steps, state, watchers and breakpoints to a file as text",
+	),
+	command(
+		"bench",
+		"bench [n]",
+		"Time n runs of the whole program from the start (default 1) and show the steps, times and speed",
+	)
+	.details("Runs with the same input and settings, but without watchers, breakpoints, history or tracing, and skips !. The current program is left as it is. With `expect`, every run's output is checked.\nExample: bench 5"),
+	command(
+		"emit",
+		"emit <c|rust> <file>",
+		"Translate the program into a standalone C or Rust file, like --emit",
+	)
+	.details("The translation starts from the beginning of the program with the current cell size, end of input behavior and tape settings. `!` and asserts become comments.\nExample: emit c program.c"),
+	command(
+		"save-session",
+		"save-session <file>",
+		"Save the program state, watchers and breakpoints. Continue later with --session <file>",
+	),
+	command("set", "set mem <index> <value>", "Write a value into a memory cell"),
+	command("set", "set ptr <index>", "Move the memory pointer"),
+	command("list", "list [start] [count]", "Show the whole program, or a range of it"),
+	command(
+		"set",
+		"set codewidth <n>",
+		"Set how many instructions are shown around the program pointer",
+	),
+	command("mem", "mem <start> [count]", "Show a range of memory")
+		.details("The start has to be a cell the tape already has, the range ends with the tape."),
+	command(
+		"label",
+		"label <index> <name>",
+		"Name a memory cell. The name is shown above the cell, and `$name` can be used wherever a cell index is expected",
+	)
+	.details("Labeling a cell again replaces its name. Labels are kept by `restore` and saved in sessions.\nExamples:\n  label 0 counter\n  watch $counter 0\n  set mem $counter 10"),
+	command("labels", "labels", "List labeled cells and their values"),
+	command("unlabel", "unlabel <name>", "Remove a label"),
+	command(
+		"set",
+		"set memwidth <n>",
+		"Set how many cells are shown around the memory pointer",
+	),
+	command(
+		"set",
+		"set memview dec|hex|ascii|mixed",
+		"Choose how memory cells are displayed",
+	),
+	command(
+		"set",
+		"set highlight-changes on|off",
+		"Highlight the cells that changed since the last prompt in yellow (default on)",
+	),
+	command(
+		"set",
+		"set breaks on|off",
+		"When off, ! in the source is skipped instead of stopping the program (default on)",
+	),
+	command(
+		"set",
+		"set rainbow on|off",
+		"Color brackets by how deeply they are nested (default on)",
+	),
+	command(
+		"set",
+		"set redraw on|off",
+		"Clear the screen before each command, so only its messages and the new view are shown (default off)",
+	)
+	.details("Only in a terminal. Errors and notices from the command are shown above the view"),
+	command(
+		"set",
+		"set prompt on|off",
+		"Show the state, step count and source location before each command, like [Running · step 1,520 · 14:3] > (default on)",
+	)
+	.details("Turn it off when piping commands in, to keep the output clean"),
+	command(
+		"output",
+		"output [raw|escaped|hex]",
+		"Show all of the output, optionally in a different format than the one set with `set output`",
+	),
+	command(
+		"set",
+		"set output raw|escaped|hex",
+		"Choose how output is displayed. Escaped (the default) shows control characters as \\x escapes",
+	),
+	command(
+		"set",
+		"set outputlimit <n>",
+		"Set how many bytes of output are shown after every step",
+	),
+	command(
+		"set",
+		"set history <n>",
+		"Set how many steps are remembered for `back`",
+	),
+	command(
+		"set",
+		"set wrap on|off",
+		"When off, stop before a + or - would make a cell wrap around, instead of wrapping (default on)",
+	)
+	.details("Use `skip` to go past the instruction that would have wrapped"),
+	command(
+		"set",
+		"set max-steps <n>|off",
+		"Stop running once the step count reaches n. Also set at startup with --max-steps <n>",
+	),
+	command(
+		"set",
+		"set max-memory <n>|off",
+		"Stop before a > would grow the tape past n cells. Also set at startup with --max-memory <n>",
+	),
+	command(
+		"set",
+		"set loop-detect on|off",
+		"Stop when the program gets back to exactly a state it was in before, since it would then loop forever (default off)",
+	)
+	.details("The state is the instruction, memory pointer, input position and all of memory. States are compared at exponentially growing distances, so an infinite loop is found within a few times its length.\nAlso set at startup with --loop-detect"),
+	command(
+		"expect",
+		"expect <file> | expect off",
+		"Compare the output against a file once the program ends, and show whether it matches",
+	)
+	.details("On a mismatch, the first differing byte is shown with the bytes around it in hex.\nAlso set at startup with --expect <file>"),
+	command(
+		"input",
+		"input",
+		"Show the input, with the consumed part dimmed and the next byte to be read highlighted",
+	),
+	command(
+		"input",
+		"input add <text>",
+		"Append to the input. `\\n`, `\\t` and `\\xNN` escapes are supported",
+	)
+	.details("Example: input add 3+4\\n"),
+	command(
+		"input",
+		"input type <text>",
+		"Give a program waiting for input a line, as if it was typed at the program input prompt",
+	)
+	.details("Recorded sessions use this for the input that was typed. Escapes work like with `input add`.\nExample: input type 42\\n"),
+	command(
+		"set",
+		"set input interactive|preloaded",
+		"When interactive, reading past the end of the input asks for more on the terminal",
+	)
+	.details("Entering an empty line at the input prompt gives the program an EOF instead"),
+	command(
+		"set",
+		"set eof 0|255|unchanged",
+		"Choose what a read does to the cell once the input is exhausted",
+	),
+	command(
+		"set",
+		"set numeric-io on|off",
+		"When on, , reads a decimal number from the input and . writes the cell as a decimal number and a space (default off)",
+	)
+	.details("Anything before a number that is not a digit is skipped, and numbers too big for a cell wrap around. Once no digits are left, reads get the eof value"),
+	command(
+		"alias",
+		"alias [<name> = <commands>]",
+		"Make a name run one or more `;` separated commands, or list the aliases",
+	)
+	.details("Words typed after the alias are added to the end of its last command. An alias can use other aliases, but not itself.\nExample: alias go = reset; watch 3 0; run"),
+	command("unalias", "unalias <name>", "Remove an alias"),
+	command("quit", "quit | q | exit", "Exit the debugger").aliases(&["q", "exit"]),
+];
+
+fn entries(name: &str) -> impl Iterator<Item = &'static CommandInfo> + '_ {
+	COMMANDS
+		.iter()
+		.filter(move |c| c.name == name || c.aliases.contains(&name))
+}
+
+pub fn is_command(name: &str) -> bool {
+	entries(name).next().is_some()
+}
+
+/// The command that a name or a built in alias like `s` stands for
+pub fn resolve(name: &str) -> Option<&'static str> {
+	entries(name).next().map(|c| c.name)
+}
+
+/// The accepted forms of a command, for error messages
+pub fn usage(name: &str) -> String {
+	let usages: Vec<_> = entries(name).map(|c| c.usage).collect();
+	match usages.as_slice() {
+		[usage] => format!("usage: {usage}"),
+		_ => format!("usage:\n  {}", usages.join("\n  ")),
+	}
+}
+
+pub fn help_text() -> String {
+	let mut out = String::from(
+		"Code breakpoints:\n    The ! character will be treated as a breakpoint, stopping execution\n\n[brackets] are optional parameters. Several commands can be given on one line, separated by ;\nCommand list:\n",
+	);
+	for c in COMMANDS {
+		out.push_str(&format!("- {}\n    {}\n", c.usage, c.summary));
+	}
+	out.push_str("Type `help <command>` for more about a command");
+	out
+}
+
+/// All forms of one command with their full explanations
+pub fn command_help(name: &str) -> Option<String> {
+	let mut out = String::new();
+	for c in entries(name) {
+		out.push_str(&format!("{}\n    {}\n", c.usage.bold(), c.summary));
+		for line in c.details.lines() {
+			out.push_str(&format!("    {line}\n"));
+		}
+	}
+	(!out.is_empty()).then_some(out)
+}
@@ -0,0 +1,15 @@
+//! Remembering which instructions have executed at all, for `coverage`.
+
+use crate::BFInterpreter;
+
+impl BFInterpreter {
+	/// Execution count of every instruction since the program was loaded or the coverage was reset
+	pub fn coverage(&self) -> &[u64] {
+		&self.coverage
+	}
+
+	/// Forgets which instructions have executed, without touching the program state
+	pub fn reset_coverage(&mut self) {
+		self.coverage.fill(0);
+	}
+}
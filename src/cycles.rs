@@ -0,0 +1,107 @@
+//! Noticing when the program comes back to exactly the state it was in before, which means it never ends.
+//! States are compared against one saved state that is replaced at exponentially growing distances, so
+//! a cycle is found within a few times its length while each step only costs a few comparisons.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::{BFInterpreter, State, StopReason, Tape};
+
+/// Steps before the first saved state is replaced, the distance doubles after that
+const FIRST_DISTANCE: usize = 64;
+
+#[derive(Debug, Default)]
+pub(crate) struct CycleCheck {
+	/// Sum of a hash of every non-zero cell, kept up to date as the program writes cells
+	memory_hash: u64,
+	saved: Option<SavedState>,
+	/// Steps after `saved` at which it is replaced
+	distance: usize,
+}
+
+#[derive(Debug)]
+struct SavedState {
+	step: usize,
+	program_ptr: usize,
+	mem_ptr: usize,
+	input_ptr: usize,
+	memory_hash: u64,
+	memory: Tape,
+}
+
+/// What one cell adds to the memory hash. Zero cells add nothing, so growing the tape changes nothing
+fn cell_hash(index: usize, value: u32) -> u64 {
+	if value == 0 {
+		return 0;
+	}
+	let mut hasher = DefaultHasher::new();
+	(index, value).hash(&mut hasher);
+	hasher.finish()
+}
+
+impl BFInterpreter {
+	/// Starts looking for cycles from the current state, since anything changed from outside
+	/// the program makes earlier states meaningless
+	pub(crate) fn forget_cycles(&mut self) {
+		if !self.loop_detect {
+			return;
+		}
+		let memory_hash = self
+			.memory
+			.iter()
+			.enumerate()
+			.fold(0u64, |hash, (index, value)| {
+				hash.wrapping_add(cell_hash(index, value))
+			});
+		self.cycle_check = CycleCheck {
+			memory_hash,
+			saved: None,
+			distance: FIRST_DISTANCE,
+		};
+	}
+
+	/// Called after the program changed the current cell from `old`
+	pub(crate) fn note_cell_write(&mut self, old: u32) {
+		let new = self.memory[self.mem_ptr];
+		let check = &mut self.cycle_check;
+		check.memory_hash = check
+			.memory_hash
+			.wrapping_sub(cell_hash(self.mem_ptr, old))
+			.wrapping_add(cell_hash(self.mem_ptr, new));
+	}
+
+	/// Stops with `InfiniteLoop` if the state after this step is the saved state
+	pub(crate) fn check_cycle(&mut self) {
+		let check = &self.cycle_check;
+		if let Some(saved) = &check.saved {
+			if saved.program_ptr == self.program_ptr
+				&& saved.mem_ptr == self.mem_ptr
+				&& saved.input_ptr == self.input_ptr
+				&& saved.memory_hash == check.memory_hash
+				&& saved.memory.len() == self.memory.len()
+				&& saved.memory.iter().eq(self.memory.iter())
+			{
+				self.state = State::InfiniteLoop;
+				self.stop_reason = Some(StopReason::Cycle {
+					first_step: saved.step,
+				});
+				return;
+			}
+			if self.steps - saved.step < check.distance {
+				return;
+			}
+		}
+		let check = &mut self.cycle_check;
+		if check.saved.is_some() {
+			check.distance *= 2;
+		}
+		let saved = SavedState {
+			step: self.steps,
+			program_ptr: self.program_ptr,
+			mem_ptr: self.mem_ptr,
+			input_ptr: self.input_ptr,
+			memory_hash: check.memory_hash,
+			memory: self.memory.clone(),
+		};
+		check.saved = Some(saved);
+	}
+}
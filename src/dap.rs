@@ -0,0 +1,438 @@
+//! A Debug Adapter Protocol server, so editors can use the interpreter as a debugger backend.
+//!
+//! Only what a single brainfuck program needs is supported: one thread, line breakpoints,
+//! stepping, cells as data breakpoints, and the loops around the current instruction as the stack.
+
+use std::{
+	fs,
+	io::{stdin, stdout, BufRead, BufReader, Write},
+	net::TcpListener,
+};
+
+use brainfuck::{optimize_clear_loops, parse, BFInterpreter, BreakTarget, State, WatchCondition};
+use serde_json::{json, Value};
+
+use crate::{
+	centered_window,
+	options::{Input, Options},
+	view::to_base64,
+};
+
+/// Number of cells listed around the memory pointer in the memory scope
+const VARIABLES_WINDOW: usize = 64;
+const MEMORY_SCOPE: u64 = 1;
+const POINTERS_SCOPE: u64 = 2;
+
+/// Serves one client on stdin/stdout, or on the TCP port from `--dap-port`
+pub fn serve(options: &Options) -> Result<(), String> {
+	match options.dap_port {
+		Some(port) => {
+			let listener = TcpListener::bind(("127.0.0.1", port))
+				.map_err(|err| format!("could not listen on port {port}: {err}"))?;
+			eprintln!("listening for a debug adapter client on port {port}");
+			let (stream, _) = listener
+				.accept()
+				.map_err(|err| format!("could not accept a connection: {err}"))?;
+			let reader = stream.try_clone().map_err(|err| err.to_string())?;
+			Server::new(options, stream).serve(BufReader::new(reader))
+		}
+		None => Server::new(options, stdout()).serve(stdin().lock()),
+	}
+}
+
+struct Server<'a, W: Write> {
+	options: &'a Options,
+	out: W,
+	/// Sequence number of the last message sent
+	seq: u64,
+	interpreter: Option<BFInterpreter>,
+	source_path: String,
+	stop_on_entry: bool,
+	launched: bool,
+	configured: bool,
+	/// Output bytes already sent to the client
+	output_sent: usize,
+	breakpoint_ids: Vec<usize>,
+	watcher_ids: Vec<usize>,
+}
+
+impl<'a, W: Write> Server<'a, W> {
+	fn new(options: &'a Options, out: W) -> Self {
+		Self {
+			options,
+			out,
+			seq: 0,
+			interpreter: None,
+			source_path: String::new(),
+			stop_on_entry: false,
+			launched: false,
+			configured: false,
+			output_sent: 0,
+			breakpoint_ids: Vec::new(),
+			watcher_ids: Vec::new(),
+		}
+	}
+
+	fn serve(mut self, mut input: impl BufRead) -> Result<(), String> {
+		while let Some(request) = read_message(&mut input)? {
+			let command = request["command"].as_str().unwrap_or_default().to_string();
+			let result = self.handle(&command, &request["arguments"]);
+			let mut response = json!({
+				"type": "response",
+				"request_seq": request["seq"],
+				"command": command,
+				"success": result.is_ok(),
+			});
+			match result {
+				Ok(body) => response["body"] = body,
+				Err(message) => response["message"] = message.into(),
+			}
+			self.send(response)?;
+			match command.as_str() {
+				"disconnect" => break,
+				"launch" if self.launched => {
+					self.send_event("initialized", json!({}))?;
+					self.start()?;
+				}
+				"configurationDone" => self.start()?,
+				"continue" | "next" | "stepIn" | "stepOut" | "stepBack" => self.report_stop()?,
+				_ => (),
+			}
+		}
+		Ok(())
+	}
+
+	fn handle(&mut self, command: &str, args: &Value) -> Result<Value, String> {
+		match command {
+			"initialize" => Ok(json!({
+				"supportsConfigurationDoneRequest": true,
+				"supportsReadMemoryRequest": true,
+				"supportsDataBreakpoints": true,
+				"supportsStepBack": true,
+			})),
+			"launch" => self.launch(args).map(|_| Value::Null),
+			"configurationDone" => {
+				self.configured = true;
+				Ok(Value::Null)
+			}
+			"disconnect" => Ok(Value::Null),
+			"threads" => Ok(json!({ "threads": [{ "id": 1, "name": "main" }] })),
+			"setBreakpoints" => self.set_breakpoints(args),
+			"dataBreakpointInfo" => Ok(self.data_breakpoint_info(args)),
+			"setDataBreakpoints" => self.set_data_breakpoints(args),
+			"continue" => {
+				self.interpreter()?.run();
+				Ok(json!({ "allThreadsContinued": true }))
+			}
+			"next" => {
+				self.interpreter()?.step_over();
+				Ok(Value::Null)
+			}
+			"stepIn" => {
+				self.interpreter()?.step_once();
+				Ok(Value::Null)
+			}
+			"stepOut" => {
+				self.interpreter()?.finish();
+				Ok(Value::Null)
+			}
+			"stepBack" => self.interpreter()?.back(1).map(|_| Value::Null),
+			"stackTrace" => self.stack_trace(),
+			"scopes" => Ok(json!({ "scopes": [
+				{ "name": "Memory", "variablesReference": MEMORY_SCOPE, "expensive": false },
+				{ "name": "Pointers", "variablesReference": POINTERS_SCOPE, "expensive": false },
+			] })),
+			"variables" => self.variables(args),
+			"readMemory" => self.read_memory(args),
+			_ => Err(format!("{command} is not supported")),
+		}
+	}
+
+	fn interpreter(&mut self) -> Result<&mut BFInterpreter, String> {
+		self.interpreter
+			.as_mut()
+			.ok_or_else(|| "no program has been launched".into())
+	}
+
+	/// Loads the program named by the `program` argument, or the source file given on the command line
+	fn launch(&mut self, args: &Value) -> Result<(), String> {
+		let path = args["program"]
+			.as_str()
+			.map(String::from)
+			.or_else(|| self.options.source_files.first().cloned())
+			.ok_or("launch needs a `program` argument")?;
+		let source =
+			fs::read_to_string(&path).map_err(|err| format!("could not read {path}: {err}"))?;
+		let mut program = parse(&source).map_err(|errors| {
+			let errors: Vec<_> = errors.iter().map(|err| err.to_string()).collect();
+			format!("Parser error: {}", errors.join(", "))
+		})?;
+		if self.options.optimize {
+			program = optimize_clear_loops(program);
+		}
+		let input = match (args["input"].as_str(), &self.options.input) {
+			(Some(text), _) => text.as_bytes().to_vec(),
+			(None, Input::File(path)) => {
+				fs::read(path).map_err(|err| format!("could not read {path}: {err}"))?
+			}
+			(None, Input::Inline(bytes)) => bytes.clone(),
+			(None, Input::None) => Vec::new(),
+		};
+		let mut interpreter = BFInterpreter::new(program, input);
+		interpreter.eof_behavior = self.options.eof_behavior;
+		interpreter.max_steps = self.options.max_steps;
+		interpreter.set_cell_size(self.options.cell_size);
+		interpreter.set_tape(self.options.tape_size, self.options.tape_mode)?;
+		self.interpreter = Some(interpreter);
+		self.source_path = fs::canonicalize(&path)
+			.map(|path| path.to_string_lossy().into_owned())
+			.unwrap_or(path);
+		self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+		self.launched = true;
+		Ok(())
+	}
+
+	/// Starts the program once it is both launched and configured
+	fn start(&mut self) -> Result<(), String> {
+		if !(self.launched && self.configured) {
+			return Ok(());
+		}
+		if self.stop_on_entry {
+			self.send_event("stopped", json!({ "reason": "entry", "threadId": 1 }))
+		} else {
+			self.interpreter()?.run();
+			self.report_stop()
+		}
+	}
+
+	/// Sends any new output, then tells the client why execution stopped
+	fn report_stop(&mut self) -> Result<(), String> {
+		let Some(interpreter) = &self.interpreter else {
+			return Ok(());
+		};
+		let state = interpreter.state;
+		// stepping back can take output away again
+		self.output_sent = self.output_sent.min(interpreter.output.len());
+		if interpreter.output.len() > self.output_sent {
+			let text =
+				String::from_utf8_lossy(&interpreter.output[self.output_sent..]).into_owned();
+			self.output_sent = interpreter.output.len();
+			self.send_event("output", json!({ "category": "stdout", "output": text }))?;
+		}
+		let reason = match state {
+			State::EndOfProgram => {
+				self.send_event("exited", json!({ "exitCode": 0 }))?;
+				return self.send_event("terminated", json!({}));
+			}
+			State::BreakPointHit => "breakpoint",
+			State::StoppedOnMemoryValue => "data breakpoint",
+			State::Running | State::LoopFinished | State::ReachedLocation => "step",
+			State::Interrupted => "pause",
+			_ => "exception",
+		};
+		self.send_event(
+			"stopped",
+			json!({
+				"reason": reason,
+				"description": format!("{state:?}"),
+				"text": format!("{state:?}"),
+				"threadId": 1,
+				"allThreadsStopped": true,
+			}),
+		)
+	}
+
+	fn set_breakpoints(&mut self, args: &Value) -> Result<Value, String> {
+		let interpreter = self
+			.interpreter
+			.as_mut()
+			.ok_or("no program has been launched")?;
+		for id in self.breakpoint_ids.drain(..) {
+			_ = interpreter.delete_breakpoint(id);
+		}
+		let mut results = Vec::new();
+		for breakpoint in args["breakpoints"].as_array().into_iter().flatten() {
+			let line = breakpoint["line"].as_u64().unwrap_or(0) as usize;
+			let column = breakpoint["column"].as_u64().map(|c| c as usize);
+			// the first instruction on the line, at or after the column if there is one
+			let index = interpreter.program.iter().position(|c| {
+				c.line_number == line && column.is_none_or(|column| c.column >= column)
+			});
+			let Some(index) = index else {
+				results
+					.push(json!({ "verified": false, "message": "no instruction on this line" }));
+				continue;
+			};
+			match interpreter.add_breakpoint(BreakTarget::Index(index), None) {
+				Ok(id) => {
+					self.breakpoint_ids.push(id);
+					let c = &interpreter.program[index];
+					results.push(json!({
+						"id": id,
+						"verified": true,
+						"line": c.line_number,
+						"column": c.column,
+					}));
+				}
+				Err(message) => results.push(json!({ "verified": false, "message": message })),
+			}
+		}
+		Ok(json!({ "breakpoints": results }))
+	}
+
+	fn data_breakpoint_info(&self, args: &Value) -> Value {
+		let name = args["name"].as_str().unwrap_or_default();
+		match (args["variablesReference"].as_u64(), name.parse::<usize>()) {
+			(Some(MEMORY_SCOPE), Ok(index)) => json!({
+				"dataId": index.to_string(),
+				"description": format!("cell {index}"),
+				"accessTypes": ["write"],
+			}),
+			_ => json!({ "dataId": null, "description": "only memory cells can be watched" }),
+		}
+	}
+
+	fn set_data_breakpoints(&mut self, args: &Value) -> Result<Value, String> {
+		let interpreter = self
+			.interpreter
+			.as_mut()
+			.ok_or("no program has been launched")?;
+		for id in self.watcher_ids.drain(..) {
+			_ = interpreter.remove_watch(id);
+		}
+		let mut results = Vec::new();
+		for breakpoint in args["breakpoints"].as_array().into_iter().flatten() {
+			let Some(index) = breakpoint["dataId"].as_str().and_then(|id| id.parse().ok()) else {
+				results.push(json!({ "verified": false }));
+				continue;
+			};
+			let last = interpreter.memory.get(index).unwrap_or(0);
+			let id = interpreter.add_watch(index, WatchCondition::Changed { last })?;
+			self.watcher_ids.push(id);
+			results.push(json!({ "id": id, "verified": true }));
+		}
+		Ok(json!({ "breakpoints": results }))
+	}
+
+	/// The current instruction, then the start of every loop around it from the inside out
+	fn stack_trace(&mut self) -> Result<Value, String> {
+		let interpreter = self
+			.interpreter
+			.as_ref()
+			.ok_or("no program has been launched")?;
+		let frame = |id: usize, name: String, index: usize| {
+			let c = &interpreter.program[index];
+			json!({
+				"id": id,
+				"name": name,
+				"source": { "path": self.source_path },
+				"line": c.line_number.max(1),
+				"column": c.column.max(1),
+			})
+		};
+		let mut frames = Vec::new();
+		let mut index = interpreter.program_ptr;
+		for activation in interpreter.loop_stack.iter().rev() {
+			let start = &interpreter.program[activation.start];
+			let name = format!(
+				"loop at {}:{}, iteration {}",
+				start.line_number, start.column, activation.iterations
+			);
+			frames.push(frame(frames.len(), name, index));
+			index = activation.start;
+		}
+		frames.push(frame(frames.len(), "program".into(), index));
+		Ok(json!({ "stackFrames": frames, "totalFrames": frames.len() }))
+	}
+
+	fn variables(&mut self, args: &Value) -> Result<Value, String> {
+		let interpreter = self
+			.interpreter
+			.as_ref()
+			.ok_or("no program has been launched")?;
+		let variable = |name: String, value: String| json!({ "name": name, "value": value, "variablesReference": 0 });
+		let variables: Vec<_> = match args["variablesReference"].as_u64() {
+			Some(MEMORY_SCOPE) => {
+				let (start, end) = centered_window(
+					interpreter.mem_ptr,
+					VARIABLES_WINDOW,
+					interpreter.memory.len(),
+				);
+				(start..end)
+					.map(|i| variable(i.to_string(), interpreter.memory[i].to_string()))
+					.collect()
+			}
+			Some(POINTERS_SCOPE) => vec![
+				variable("memory pointer".into(), interpreter.mem_ptr.to_string()),
+				variable(
+					"program pointer".into(),
+					interpreter.program_ptr.to_string(),
+				),
+				variable("steps".into(), interpreter.steps.to_string()),
+			],
+			_ => Vec::new(),
+		};
+		Ok(json!({ "variables": variables }))
+	}
+
+	/// The tape as bytes, one per cell. Wider cells are cut down to their lowest byte like output is
+	fn read_memory(&mut self, args: &Value) -> Result<Value, String> {
+		let interpreter = self
+			.interpreter
+			.as_ref()
+			.ok_or("no program has been launched")?;
+		let base: i64 = args["memoryReference"]
+			.as_str()
+			.and_then(|r| r.parse().ok())
+			.unwrap_or(0);
+		let start = (base + args["offset"].as_i64().unwrap_or(0)).max(0) as usize;
+		let count = args["count"].as_u64().unwrap_or(0) as usize;
+		let bytes: Vec<u8> = interpreter
+			.memory
+			.range(start, start.saturating_add(count))
+			.map(|cell| cell as u8)
+			.collect();
+		Ok(json!({
+			"address": start.to_string(),
+			"data": to_base64(&bytes),
+			"unreadableBytes": count - bytes.len(),
+		}))
+	}
+
+	fn send_event(&mut self, event: &str, body: Value) -> Result<(), String> {
+		self.send(json!({ "type": "event", "event": event, "body": body }))
+	}
+
+	fn send(&mut self, mut message: Value) -> Result<(), String> {
+		self.seq += 1;
+		message["seq"] = self.seq.into();
+		let body = message.to_string();
+		write!(self.out, "Content-Length: {}\r\n\r\n{body}", body.len())
+			.and_then(|_| self.out.flush())
+			.map_err(|err| format!("could not send to the client: {err}"))
+	}
+}
+
+/// Reads one message, or `None` once the client has closed the connection
+fn read_message(input: &mut impl BufRead) -> Result<Option<Value>, String> {
+	let mut length = None;
+	loop {
+		let mut line = String::new();
+		if input.read_line(&mut line).map_err(|err| err.to_string())? == 0 {
+			return Ok(None);
+		}
+		let line = line.trim_end();
+		if line.is_empty() {
+			break;
+		}
+		if let Some(value) = line.strip_prefix("Content-Length:") {
+			length = value.trim().parse().ok();
+		}
+	}
+	let length: usize = length.ok_or("message without a Content-Length header")?;
+	let mut body = vec![0; length];
+	input.read_exact(&mut body).map_err(|err| err.to_string())?;
+	serde_json::from_slice(&body)
+		.map(Some)
+		.map_err(|err| format!("invalid message: {err}"))
+}
@@ -0,0 +1,40 @@
+//! Comparing a snapshot with the current state, to see what the steps since then did.
+
+use crate::{BFInterpreter, Snapshot};
+
+/// Each pair is the value in the snapshot and the value now
+#[derive(Debug)]
+pub struct SnapshotDiff {
+	/// Index and both values of every cell that differs. Cells that only exist on one side are 0 on the other
+	pub cells: Vec<(usize, u32, u32)>,
+	pub mem_ptr: (usize, usize),
+	pub program_ptr: (usize, usize),
+	pub steps: (usize, usize),
+	pub output_len: (usize, usize),
+	/// The bytes written since the snapshot, if the output then is the start of the output now
+	pub new_output: Option<Vec<u8>>,
+}
+
+impl BFInterpreter {
+	pub fn diff(&self, snapshot: &Snapshot) -> SnapshotDiff {
+		let (old, new) = (&snapshot.memory, &self.memory);
+		let cells = (0..old.len().max(new.len()))
+			.filter_map(|index| {
+				let before = old.get(index).unwrap_or(0);
+				let now = new.get(index).unwrap_or(0);
+				(before != now).then_some((index, before, now))
+			})
+			.collect();
+		SnapshotDiff {
+			cells,
+			mem_ptr: (snapshot.mem_ptr, self.mem_ptr),
+			program_ptr: (snapshot.program_ptr, self.program_ptr),
+			steps: (snapshot.steps, self.steps),
+			output_len: (snapshot.output.len(), self.output.len()),
+			new_output: self
+				.output
+				.strip_prefix(snapshot.output.as_slice())
+				.map(<[u8]>::to_vec),
+		}
+	}
+}
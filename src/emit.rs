@@ -0,0 +1,334 @@
+//! Translating the program into a standalone C or Rust file, to build a native binary of it.
+
+use crate::{
+	optimize::{FastProgram, Op, Transfer},
+	BFInterpreter, Command, EofBehavior, TapeMode,
+};
+
+/// Cells on the emitted tape when the interpreter would grow it without a limit
+pub const EMIT_TAPE_SIZE: usize = 1 << 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+	C,
+	Rust,
+}
+
+impl Language {
+	pub fn parse(text: &str) -> Option<Self> {
+		match text {
+			"c" => Some(Self::C),
+			"rust" => Some(Self::Rust),
+			_ => None,
+		}
+	}
+}
+
+/// The body of `main` as it is emitted, indented by loop depth
+struct Emitter {
+	language: Language,
+	body: String,
+	depth: usize,
+	/// Largest cell value, added amounts are masked to it
+	mask: u32,
+	size: usize,
+	/// Whether the pointer wraps around the tape instead of stopping the program
+	wrap_tape: bool,
+	/// Whether the current cell is passed to reads, to keep it at the end of the input
+	keep_on_eof: bool,
+	uses_read: bool,
+	uses_off_tape: bool,
+}
+
+impl BFInterpreter {
+	/// The program as a standalone C or Rust file with the same cell size, tape and end of input behavior,
+	/// always starting from the beginning. Repeated instructions are folded, and transfer loops become
+	/// multiplications when those are on. `!` and asserts are left as comments, and moving off the tape
+	/// exits with code 2 like `--run`
+	pub fn emit(&self, language: Language) -> Result<String, String> {
+		if self.numeric_io {
+			return Err("numeric io can not be emitted, only byte io".into());
+		}
+		let size = self
+			.tape_size
+			.or(self.memory_limit)
+			.unwrap_or(EMIT_TAPE_SIZE);
+		if self.initial_memory.len() > size {
+			return Err(format!(
+				"the initial memory does not fit on the emitted tape of {size} cells"
+			));
+		}
+		let wrap_tape = self.tape_size.is_some() && self.tape_mode == TapeMode::Wrap;
+		// transfers reach several cells at once, which is only checked for a tape that does not wrap
+		let fast = FastProgram::compile(&self.program, self.transfer_loops && !wrap_tape);
+		let mut emitter = Emitter {
+			language,
+			body: String::new(),
+			depth: 1,
+			mask: self.cell_size.max(),
+			size,
+			wrap_tape,
+			keep_on_eof: self.eof_behavior == EofBehavior::Unchanged,
+			uses_read: false,
+			uses_off_tape: false,
+		};
+		for op in &fast.ops {
+			match op.op {
+				Op::Add(n) => emitter.add(n),
+				Op::Move(n) => emitter.move_by(n),
+				Op::Clear => emitter.line("tape[p] = 0;"),
+				Op::JumpIfZero(_) => {
+					emitter.line(match language {
+						Language::C => "while (tape[p]) {",
+						Language::Rust => "while tape[p] != 0 {",
+					});
+					emitter.depth += 1;
+				}
+				Op::JumpIfNotZero(_) => {
+					emitter.depth -= 1;
+					emitter.line("}");
+				}
+				Op::Transfer(t) => emitter.transfer(&fast.transfers[t]),
+				Op::Single => match self.program[op.source_index].command {
+					Command::Read => emitter.read(),
+					Command::Write => emitter.line(match language {
+						Language::C => "putchar(tape[p]);",
+						Language::Rust => "_ = out.write_all(&[tape[p] as u8]);",
+					}),
+					Command::Break => emitter.line("// !"),
+					Command::Assert(value) => emitter.line(&format!("// {{={value}}}")),
+					_ => (),
+				},
+			}
+		}
+		let eof_value = match self.eof_behavior {
+			EofBehavior::Zero => "0",
+			EofBehavior::Max => match language {
+				Language::C => "(cell)-1",
+				Language::Rust => "Cell::MAX",
+			},
+			EofBehavior::Unchanged => "old",
+		};
+		let initial: Vec<_> = self.initial_memory.iter().map(u32::to_string).collect();
+		Ok(match language {
+			Language::C => emitter.finish_c(eof_value, &initial),
+			Language::Rust => emitter.finish_rust(eof_value, &initial),
+		})
+	}
+}
+
+impl Emitter {
+	fn line(&mut self, text: &str) {
+		for _ in 0..self.depth {
+			self.body.push('\t');
+		}
+		self.body.push_str(text);
+		self.body.push('\n');
+	}
+
+	fn add(&mut self, n: u32) {
+		let n = n & self.mask;
+		// anything past half way reads better as taking away
+		let (operation, amount) = match n > self.mask / 2 {
+			false => ("add", n),
+			true => ("sub", self.mask - n + 1),
+		};
+		let line = match (self.language, operation) {
+			(Language::C, "add") => format!("tape[p] += {amount}u;"),
+			(Language::C, _) => format!("tape[p] -= {amount}u;"),
+			(Language::Rust, _) => format!("tape[p] = tape[p].wrapping_{operation}({amount});"),
+		};
+		self.line(&line);
+	}
+
+	fn move_by(&mut self, n: isize) {
+		let distance = n.unsigned_abs();
+		if self.wrap_tape {
+			let step = match n > 0 {
+				true => distance % self.size,
+				false => (self.size - distance % self.size) % self.size,
+			};
+			self.line(&format!("p = (p + {step}) % TAPE_SIZE;"));
+			return;
+		}
+		self.uses_off_tape = true;
+		let lines = match (self.language, n > 0) {
+			(Language::C, true) => [
+				format!("p += {distance};"),
+				"if (p >= TAPE_SIZE) off_tape();".into(),
+			],
+			(Language::C, false) => [
+				format!("if (p < {distance}) off_tape();"),
+				format!("p -= {distance};"),
+			],
+			(Language::Rust, true) => [
+				format!("p += {distance};"),
+				"if p >= TAPE_SIZE { off_tape(&mut out) }".into(),
+			],
+			(Language::Rust, false) => [
+				format!("if p < {distance} {{ off_tape(&mut out) }}"),
+				format!("p -= {distance};"),
+			],
+		};
+		for line in lines {
+			self.line(&line);
+		}
+	}
+
+	/// A loop like `[->+>++<<]`, as one multiplication per target cell
+	fn transfer(&mut self, transfer: &Transfer) {
+		let mut checks = Vec::new();
+		if transfer.min_offset < 0 {
+			checks.push(format!("p < {}", transfer.min_offset.unsigned_abs()));
+		}
+		if transfer.max_offset > 0 {
+			checks.push(format!("p + {} >= TAPE_SIZE", transfer.max_offset));
+		}
+		match self.language {
+			Language::C => self.line("if (tape[p]) {"),
+			Language::Rust => self.line("if tape[p] != 0 {"),
+		}
+		self.depth += 1;
+		if !checks.is_empty() {
+			self.uses_off_tape = true;
+			let checks = checks.join(" || ");
+			match self.language {
+				Language::C => self.line(&format!("if ({checks}) off_tape();")),
+				Language::Rust => self.line(&format!("if {checks} {{ off_tape(&mut out) }}")),
+			}
+		}
+		for &(offset, amount) in &transfer.targets {
+			let cell = match offset < 0 {
+				true => format!("tape[p - {}]", offset.unsigned_abs()),
+				false => format!("tape[p + {offset}]"),
+			};
+			let amount = amount & self.mask;
+			let line = match (self.language, amount) {
+				(Language::C, 1) => format!("{cell} += tape[p];"),
+				(Language::C, _) if amount == self.mask => format!("{cell} -= tape[p];"),
+				(Language::Rust, 1) => format!("{cell} = {cell}.wrapping_add(tape[p]);"),
+				(Language::Rust, _) if amount == self.mask => {
+					format!("{cell} = {cell}.wrapping_sub(tape[p]);")
+				}
+				(Language::C, _) => format!("{cell} += tape[p] * {amount}u;"),
+				(Language::Rust, _) => {
+					format!("{cell} = {cell}.wrapping_add(tape[p].wrapping_mul({amount}));")
+				}
+			};
+			self.line(&line);
+		}
+		self.line("tape[p] = 0;");
+		self.depth -= 1;
+		self.line("}");
+	}
+
+	fn read(&mut self) {
+		self.uses_read = true;
+		let line = match (self.language, self.keep_on_eof) {
+			(Language::C, false) => "tape[p] = read_cell();",
+			(Language::C, true) => "tape[p] = read_cell(tape[p]);",
+			(Language::Rust, false) => "tape[p] = read_cell(&mut out);",
+			(Language::Rust, true) => "tape[p] = read_cell(tape[p], &mut out);",
+		};
+		self.line(line);
+	}
+
+	fn finish_c(self, eof_value: &str, initial: &[String]) -> String {
+		let bits = self.mask.count_ones();
+		let mut out = format!(
+			"// Translated from brainfuck by `brainfuck --emit c`\n\
+			 #include <stdint.h>\n#include <stdio.h>\n#include <stdlib.h>\n\n\
+			 #define TAPE_SIZE {}\n\ntypedef uint{bits}_t cell;\n\n",
+			self.size
+		);
+		match initial.is_empty() {
+			true => out.push_str("static cell tape[TAPE_SIZE];\n"),
+			false => out.push_str(&format!(
+				"static cell tape[TAPE_SIZE] = {{{}}};\n",
+				initial.join(", ")
+			)),
+		}
+		if self.uses_off_tape {
+			out.push_str(
+				"\nstatic void off_tape(void) {\n\
+				 \tfflush(stdout);\n\
+				 \tfputs(\"error: memory pointer moved off the tape\\n\", stderr);\n\
+				 \texit(2);\n}\n",
+			);
+		}
+		if self.uses_read {
+			let parameter = match self.keep_on_eof {
+				true => "cell old",
+				false => "void",
+			};
+			out.push_str(&format!(
+				"\nstatic cell read_cell({parameter}) {{\n\
+				 \tfflush(stdout);\n\
+				 \tint c = getchar();\n\
+				 \treturn c == EOF ? {eof_value} : (cell)c;\n}}\n"
+			));
+		}
+		out.push_str("\nint main(void) {\n\tsize_t p = 0;\n");
+		out.push_str(&self.body);
+		out.push_str("\treturn 0;\n}\n");
+		out
+	}
+
+	fn finish_rust(self, eof_value: &str, initial: &[String]) -> String {
+		let bits = self.mask.count_ones();
+		let traits = match self.uses_read {
+			true => "Read, Write",
+			false => "Write",
+		};
+		// `exit` is only used to stop when moving off the tape
+		let exit = match self.uses_off_tape {
+			true => "use std::process::exit;\n",
+			false => "",
+		};
+		let mut out = format!(
+			"// Translated from brainfuck by `brainfuck --emit rust`\n\
+			 // the pointer and tape are often left unused at the end of a program\n\
+			 #![allow(unused)]\n\n\
+			 use std::io::{{self, {traits}}};\n{exit}\n\
+			 const TAPE_SIZE: usize = {};\n\ntype Cell = u{bits};\n",
+			self.size
+		);
+		if self.uses_off_tape {
+			out.push_str(
+				"\nfn off_tape(out: &mut impl Write) -> ! {\n\
+				 \t_ = out.flush();\n\
+				 \teprintln!(\"error: memory pointer moved off the tape\");\n\
+				 \texit(2);\n}\n",
+			);
+		}
+		if self.uses_read {
+			let old = match self.keep_on_eof {
+				true => "old: Cell, ",
+				false => "",
+			};
+			out.push_str(&format!(
+				"\nfn read_cell({old}out: &mut impl Write) -> Cell {{\n\
+				 \t_ = out.flush();\n\
+				 \tlet mut byte = [0];\n\
+				 \tmatch io::stdin().read(&mut byte) {{\n\
+				 \t\tOk(1) => byte[0] as Cell,\n\
+				 \t\t_ => {eof_value},\n\
+				 \t}}\n}}\n"
+			));
+		}
+		out.push_str("\nfn main() {\n\tlet mut tape: Vec<Cell> = vec![0; TAPE_SIZE];\n");
+		if !initial.is_empty() {
+			out.push_str(&format!(
+				"\ttape[..{}].copy_from_slice(&[{}]);\n",
+				initial.len(),
+				initial.join(", ")
+			));
+		}
+		out.push_str(
+			"\tlet mut p: usize = 0;\n\tlet mut out = io::BufWriter::new(io::stdout().lock());\n",
+		);
+		out.push_str(&self.body);
+		out.push_str("\t_ = out.flush();\n}\n");
+		out
+	}
+}
@@ -0,0 +1,48 @@
+//! Running a snippet of code against the current memory without disturbing the program.
+
+use std::mem;
+
+use crate::{BFInterpreter, DebugCommand, State};
+
+impl BFInterpreter {
+	/// Runs `snippet` to its end on the current memory, pointer and input, leaving the program where it was.
+	/// Watchers, breakpoints, history and the other debugging aids do not see it.
+	/// Returns what the snippet wrote, which is only added to `output` when `keep_output` is set
+	pub fn eval(
+		&mut self,
+		snippet: Vec<DebugCommand>,
+		keep_output: bool,
+	) -> Result<Vec<u8>, String> {
+		let mut eval = BFInterpreter::new(snippet, mem::take(&mut self.input));
+		eval.memory = mem::take(&mut self.memory);
+		eval.mem_ptr = self.mem_ptr;
+		eval.input_ptr = self.input_ptr;
+		eval.eof_behavior = self.eof_behavior;
+		eval.cell_size = self.cell_size;
+		eval.wrap = self.wrap;
+		eval.tape_size = self.tape_size;
+		eval.tape_mode = self.tape_mode;
+		eval.interrupt = self.interrupt.clone();
+		eval.transfer_loops = self.transfer_loops;
+		eval.history_limit = 0;
+		eval.track_writes = false;
+		eval.run();
+
+		self.memory = eval.memory;
+		self.mem_ptr = eval.mem_ptr;
+		self.input = eval.input;
+		self.input_ptr = eval.input_ptr;
+		self.max_memory = self.max_memory.max(self.memory.len());
+		self.reset_change_watchers();
+		if keep_output {
+			self.output.extend_from_slice(&eval.output);
+		}
+		match eval.state {
+			State::EndOfProgram => Ok(eval.output),
+			state => Err(format!(
+				"eval stopped with {state:?} after {} steps",
+				eval.steps
+			)),
+		}
+	}
+}
@@ -0,0 +1,68 @@
+//! Checking the program output against the contents of an expected output file.
+
+/// Bytes shown on each side of the first difference
+const CONTEXT: usize = 8;
+
+/// Describes how `actual` differs from `expected`, or `None` when they are the same
+pub fn compare_output(expected: &[u8], actual: &[u8]) -> Option<String> {
+	if expected == actual {
+		return None;
+	}
+	let trimmed =
+		|bytes: &[u8]| bytes.len() - bytes.iter().rev().take_while(|&&b| b == b'\n').count();
+	let (expected_end, actual_end) = (trimmed(expected), trimmed(actual));
+	if expected[..expected_end] == actual[..actual_end] {
+		return Some(format!(
+			"only trailing newlines differ: expected {}, got {}",
+			expected.len() - expected_end,
+			actual.len() - actual_end
+		));
+	}
+	let offset = expected
+		.iter()
+		.zip(actual)
+		.position(|(e, a)| e != a)
+		.unwrap_or(expected.len().min(actual.len()));
+	let mut out = match (expected.get(offset), actual.get(offset)) {
+		(Some(&e), Some(&a)) => format!(
+			"first difference at byte {offset}: expected {}, got {}",
+			describe_byte(e),
+			describe_byte(a)
+		),
+		(Some(_), None) => format!(
+			"output ended after {} bytes, expected {}",
+			actual.len(),
+			expected.len()
+		),
+		_ => format!(
+			"output has {} more bytes than the expected {}",
+			actual.len() - expected.len(),
+			expected.len()
+		),
+	};
+	let start = offset.saturating_sub(CONTEXT);
+	out.push_str(&format!("\nexpected: {}", hex_window(expected, start)));
+	out.push_str(&format!("\nactual:   {}", hex_window(actual, start)));
+	Some(out)
+}
+
+fn describe_byte(byte: u8) -> String {
+	if byte.is_ascii_graphic() || byte == b' ' {
+		format!("0x{byte:02x} '{}'", byte as char)
+	} else {
+		format!("0x{byte:02x}")
+	}
+}
+
+/// Hex of the bytes around the first difference, starting at `start`
+fn hex_window(bytes: &[u8], start: usize) -> String {
+	let window = bytes.iter().skip(start).take(CONTEXT * 2 + 1);
+	let hex: Vec<_> = window.map(|b| format!("{b:02x}")).collect();
+	let before = if start > 0 { "… " } else { "" };
+	let after = if start + CONTEXT * 2 + 1 < bytes.len() {
+		" …"
+	} else {
+		""
+	};
+	format!("{before}{}{after}", hex.join(" "))
+}
@@ -0,0 +1,84 @@
+//! Observers registered by programs that use the library, to build their own analyses on the interpreter.
+
+use std::{fmt::Debug, mem};
+
+use crate::{BFInterpreter, Command, State};
+
+/// One executed instruction, given to `StepHook::on_step`
+#[derive(Debug, Clone, Copy)]
+pub struct StepEvent {
+	/// Steps run so far, including this one
+	pub step: usize,
+	/// Program index of the instruction
+	pub index: usize,
+	pub command: Command,
+	/// The memory pointer after the instruction
+	pub mem_ptr: usize,
+}
+
+/// Called by the interpreter as the program runs, every method does nothing unless implemented.
+/// While any hook is registered, `run` goes one instruction at a time instead of taking its fast path
+pub trait StepHook {
+	/// After every executed instruction, once its output and memory writes were reported
+	fn on_step(&mut self, _event: &StepEvent) {}
+	/// For every byte the program writes
+	fn on_output(&mut self, _byte: u8) {}
+	/// When an instruction changes the value of a cell
+	fn on_memory_write(&mut self, _index: usize, _old: u32, _new: u32) {}
+	/// When trying to run an instruction changed the state, mostly because the program stopped
+	fn on_state_change(&mut self, _state: &State) {}
+}
+
+#[derive(Default)]
+pub(crate) struct Hooks(pub(crate) Vec<Box<dyn StepHook>>);
+
+impl Debug for Hooks {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "Hooks({})", self.0.len())
+	}
+}
+
+impl BFInterpreter {
+	/// Registers a hook, called from now on as the program runs
+	pub fn add_hook(&mut self, hook: impl StepHook + 'static) {
+		self.hooks.0.push(Box::new(hook));
+	}
+
+	/// Removes every hook and gives them back
+	pub fn take_hooks(&mut self) -> Vec<Box<dyn StepHook>> {
+		mem::take(&mut self.hooks.0)
+	}
+
+	/// Runs one instruction and tells the hooks what it did, by comparing the state before and after
+	pub(crate) fn step_with_hooks(&mut self) {
+		let (state, steps) = (self.state, self.steps);
+		let (index, mem_ptr) = (self.program_ptr, self.mem_ptr);
+		let old_cell = self.memory[mem_ptr];
+		let old_output_len = self.output.len();
+		self.step_instruction();
+		let hooks = &mut self.hooks.0;
+		if self.steps != steps {
+			// only `+`, `-`, `,` and clears write, and they write the cell they are on
+			let new_cell = self.memory[mem_ptr];
+			for hook in hooks.iter_mut() {
+				for &byte in &self.output[old_output_len..] {
+					hook.on_output(byte);
+				}
+				if new_cell != old_cell {
+					hook.on_memory_write(mem_ptr, old_cell, new_cell);
+				}
+				hook.on_step(&StepEvent {
+					step: self.steps,
+					index,
+					command: self.program[index].command,
+					mem_ptr: self.mem_ptr,
+				});
+			}
+		}
+		if self.state != state {
+			for hook in hooks.iter_mut() {
+				hook.on_state_change(&self.state);
+			}
+		}
+	}
+}
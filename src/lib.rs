@@ -0,0 +1,2174 @@
+//! A brainfuck interpreter that can be stepped, rewound and inspected, used by the debugger binary.
+
+use std::{
+	collections::{BTreeMap, BTreeSet, VecDeque},
+	fmt::{Debug, Display},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+};
+
+pub use optimize::optimize_clear_loops;
+use optimize::FastProgram;
+use serde::{Deserialize, Serialize};
+use trace::Trace;
+
+pub use bench::BenchRun;
+use cycles::CycleCheck;
+pub use diff::SnapshotDiff;
+pub use emit::Language;
+use hooks::Hooks;
+pub use hooks::{StepEvent, StepHook};
+pub use logs::{LogMessage, LogSource, Logs, LOG_LIMIT};
+pub use loops::LoopActivation;
+use loops::LoopChange;
+use tape::Chunks;
+pub use tape::Tape;
+use writes::Recording;
+pub use writes::WriteRecord;
+
+mod bench;
+mod coverage;
+mod cycles;
+mod diff;
+mod emit;
+mod eval;
+mod hooks;
+mod logs;
+mod loops;
+mod optimize;
+mod profile;
+mod tape;
+mod trace;
+mod writes;
+
+const DEFAULT_HISTORY_LIMIT: usize = 1_000_000;
+/// How many steps run between checks of the interrupt flag
+const INTERRUPT_CHECK_INTERVAL: usize = 4096;
+
+#[derive(Debug)]
+pub struct BFInterpreter {
+	pub memory: Tape,
+	pub mem_ptr: usize,
+	pub program: Vec<DebugCommand>,
+	pub program_ptr: usize,
+	pub output: Vec<u8>,
+	pub input: Vec<u8>,
+	pub input_ptr: usize,
+	/// The step of the first `,` that found no input left and read the EOF value instead
+	pub input_exhausted_at: Option<usize>,
+	pub state: State,
+	pub steps: usize,
+	pub watchers: Vec<MemoryWatcher>,
+	/// What caused the current stop, cleared once execution continues
+	pub stop_reason: Option<StopReason>,
+	next_watcher_id: usize,
+	history: VecDeque<UndoRecord>,
+	history_limit: usize,
+	/// When set, reading past the end of the input stops with `WaitingForInput` instead of reading EOF
+	pub interactive_input: bool,
+	/// When set, `,` reads a decimal number from the input and `.` writes the cell as a decimal number and a space
+	pub numeric_io: bool,
+	send_eof: bool,
+	pub eof_behavior: EofBehavior,
+	pub breakpoints: Vec<Breakpoint>,
+	next_breakpoint_id: usize,
+	/// The step count and program index where the breakpoints were last checked
+	breakpoints_checked_at: Option<(usize, usize)>,
+	fast_program: Option<FastProgram>,
+	trace: Option<Trace>,
+	/// Execution count per instruction, when profiling
+	profile: Option<Vec<u64>>,
+	/// Execution count per instruction, always kept
+	coverage: Vec<u64>,
+	/// When this gets set, running stops with `Interrupted` and clears it again
+	pub interrupt: Option<Arc<AtomicBool>>,
+	/// Stop with `StepLimitReached` once this many steps have run
+	pub max_steps: Option<usize>,
+	/// Stop with `MemoryLimitReached` instead of growing the tape past this many cells
+	pub memory_limit: Option<usize>,
+	/// Stop with `InfiniteLoop` when the program gets back to a state it was in earlier in the same run
+	pub loop_detect: bool,
+	cycle_check: CycleCheck,
+	cell_size: CellSize,
+	/// When false, `+` on the largest value and `-` on 0 stop instead of wrapping around
+	pub wrap: bool,
+	/// A fixed number of cells, instead of growing the tape when moving right
+	tape_size: Option<usize>,
+	tape_mode: TapeMode,
+	/// The last step where the memory pointer wrapped around a circular tape
+	pub last_wrap_step: Option<usize>,
+	/// The most cells the tape has had since the program started
+	max_memory: usize,
+	/// Written to the start of the tape whenever the program starts over
+	initial_memory: Vec<u32>,
+	/// The loops the program pointer is inside, innermost last
+	pub loop_stack: Vec<LoopActivation>,
+	/// Gets every output byte as soon as it is written, in addition to `output`
+	pub output_stream: Option<OutputStream>,
+	/// Called every few thousand steps while running
+	pub progress: Option<ProgressReport>,
+	/// Whether `run` does loops like `[->+<]` in one go when it can run fast
+	pub transfer_loops: bool,
+	/// Whether the last write to every cell is remembered, see `last_write`
+	pub track_writes: bool,
+	last_writes: Chunks<Option<WriteRecord>>,
+	recordings: BTreeMap<usize, Recording>,
+	logs: Logs,
+	disabled_code_breaks: BTreeSet<usize>,
+	/// Whether every `!` in the source is skipped instead of stopping
+	pub ignore_code_breaks: bool,
+	skipped_code_breaks: usize,
+	hooks: Hooks,
+}
+
+pub struct OutputStream(pub Box<dyn FnMut(u8)>);
+
+impl Debug for OutputStream {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "OutputStream")
+	}
+}
+
+pub struct ProgressReport(pub Box<dyn FnMut(Progress)>);
+
+impl Debug for ProgressReport {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "ProgressReport")
+	}
+}
+
+/// How far a run has come, given to `progress`
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+	pub steps: usize,
+	pub memory_len: usize,
+	pub output_len: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Breakpoint {
+	pub id: usize,
+	pub target: BreakTarget,
+	pub condition: Option<CellCondition>,
+	/// How many times it has stopped the program, or logged for a logpoint
+	#[serde(default)]
+	pub hits: usize,
+	/// Log instead of stopping
+	#[serde(default)]
+	pub log: bool,
+	/// Delete the breakpoint once it has stopped the program
+	#[serde(default)]
+	pub once: bool,
+	/// How many more times it triggers without stopping, counting only when the condition matched
+	#[serde(default)]
+	pub ignore: usize,
+	/// Kept in the list, but never triggers
+	#[serde(default)]
+	pub disabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BreakTarget {
+	/// Stop before the instruction at this program index
+	Index(usize),
+	/// Stop when this many steps have been executed
+	Step(usize),
+	/// Stop when a loop starts its body more than this many times in a row
+	Loop(usize),
+	/// Stop right after a write that makes the output end with these bytes
+	Output(Vec<u8>),
+	/// Stop right after the tape grows past this many cells
+	MemorySize(usize),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CellCondition {
+	/// `None` means the cell under the memory pointer
+	pub cell: Option<usize>,
+	pub comparison: Comparison,
+	pub value: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Comparison {
+	Equal,
+	NotEqual,
+	Less,
+	Greater,
+}
+
+/// Everything needed to reverse one executed instruction, or a whole `run` through the folded program
+#[derive(Debug)]
+enum UndoRecord {
+	Step(StepRecord),
+	/// Undone all at once, since the folded program does not record its instructions
+	Run(Box<RunRecord>),
+}
+
+#[derive(Debug)]
+struct StepRecord {
+	program_ptr: usize,
+	mem_ptr: usize,
+	cell: u32,
+	memory_len: usize,
+	input_ptr: usize,
+	output_len: usize,
+	loop_change: LoopChange,
+	max_memory: usize,
+	last_wrap_step: Option<usize>,
+}
+
+/// The state before a `run` through the folded program
+#[derive(Debug)]
+struct RunRecord {
+	snapshot: Snapshot,
+	input_exhausted_at: Option<usize>,
+	max_memory: usize,
+	last_wrap_step: Option<usize>,
+}
+
+/// A copy of the program state, without breakpoints, watchers or other settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+	memory: Tape,
+	mem_ptr: usize,
+	program_ptr: usize,
+	output: Vec<u8>,
+	input_ptr: usize,
+	send_eof: bool,
+	loop_stack: Vec<LoopActivation>,
+	pub steps: usize,
+	pub state: State,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryWatcher {
+	pub id: usize,
+	pub index: usize,
+	pub condition: WatchCondition,
+	/// Log instead of stopping
+	#[serde(default)]
+	pub log: bool,
+	/// Kept in the list, but never triggers
+	#[serde(default)]
+	pub disabled: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum WatchCondition {
+	Changed {
+		last: u32,
+	},
+	Compare(Comparison, u32),
+	/// The memory pointer compares true after it moves, the watched cell is not used
+	Pointer(Comparison, usize),
+	/// The memory pointer moves outside of `start..=end`
+	PointerOutside(usize, usize),
+	/// The output grows to this many bytes
+	OutputLength(usize),
+	/// The byte is written for the `count`th time
+	OutputByte(u8, usize),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum State {
+	#[default]
+	Running,
+	TooFarLeft,
+	TooFarRight,
+	EndOfProgram,
+	StoppedOnMemoryValue,
+	BreakPointHit,
+	LoopFinished,
+	ReachedLocation,
+	WaitingForInput,
+	StoppedAtStep,
+	Interrupted,
+	StepLimitReached,
+	/// A `>` would grow the tape past `memory_limit`, and was not executed
+	MemoryLimitReached,
+	/// The program got back to an earlier state, so it would repeat forever
+	InfiniteLoop,
+	/// A `{=N}` found another value in the current cell
+	AssertionFailed,
+	CellOverflow,
+	CellUnderflow,
+	RunawayLoop,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum StopReason {
+	Watcher {
+		watcher: MemoryWatcher,
+		value: u32,
+	},
+	Breakpoint(Breakpoint),
+	/// A `!` in the source, at this program index
+	CodeBreakpoint(usize),
+	/// The `{=N}` at this program index found `actual` in the cell instead
+	Assertion {
+		index: usize,
+		actual: u32,
+	},
+	/// The state at this step came back, `InfiniteLoop` stops at the second time
+	Cycle {
+		first_step: usize,
+	},
+	/// The `<` or `>` at this program index would have moved the memory pointer off the tape,
+	/// so it was not executed
+	OffTape(usize),
+	/// Not a stop: `skip` moved past the instructions from program index `from` without executing them
+	Skipped {
+		from: usize,
+		to: usize,
+	},
+}
+
+/// Number of bits in a memory cell. Cells are stored as `u32` either way
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CellSize {
+	#[default]
+	Bits8,
+	Bits16,
+	Bits32,
+}
+
+/// What happens when the memory pointer moves off either end of a fixed size tape
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TapeMode {
+	/// Stop with `TooFarLeft` or `TooFarRight`
+	#[default]
+	Error,
+	/// Continue from the other end
+	Wrap,
+}
+
+/// What `,` does to the current cell when there is no input left
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EofBehavior {
+	#[default]
+	Zero,
+	Max,
+	Unchanged,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DebugCommand {
+	pub command: Command,
+	/// Index of the source file it is in, in the order the files were given
+	pub file: usize,
+	pub line_number: usize,
+	pub column: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Command {
+	Inc,
+	Dec,
+	Right,
+	Left,
+	Read,
+	Write,
+	BeginLoop(usize),
+	EndLoop(usize),
+	/// Set the current cell to 0, replacing a `[-]` or `[+]` loop
+	Clear,
+	Break,
+	/// A `{=N}` comment, stopping with `AssertionFailed` unless the current cell is N
+	Assert(u32),
+	End,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+	pub kind: ParseErrorKind,
+	pub file: usize,
+	pub line_number: usize,
+	pub column: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParseErrorKind {
+	/// A `]` with no `[` before it
+	UnmatchedClose,
+	/// A `[` that is never closed
+	UnclosedOpen,
+	/// Only a warning: a character that is not a command but may have been meant as one
+	Suspicious(char),
+}
+
+impl BFInterpreter {
+	pub fn new(program: Vec<DebugCommand>, input: Vec<u8>) -> Self {
+		let coverage = vec![0; program.len()];
+		Self {
+			memory: Tape::new(1),
+			mem_ptr: 0,
+			program,
+			program_ptr: 0,
+			output: Vec::new(),
+			input,
+			input_ptr: 0,
+			input_exhausted_at: None,
+			state: State::Running,
+			steps: 0,
+			watchers: Vec::new(),
+			stop_reason: None,
+			next_watcher_id: 1,
+			history: VecDeque::new(),
+			history_limit: DEFAULT_HISTORY_LIMIT,
+			interactive_input: false,
+			numeric_io: false,
+			send_eof: false,
+			eof_behavior: EofBehavior::Zero,
+			breakpoints: Vec::new(),
+			next_breakpoint_id: 1,
+			breakpoints_checked_at: None,
+			fast_program: None,
+			trace: None,
+			profile: None,
+			coverage,
+			interrupt: None,
+			max_steps: None,
+			memory_limit: None,
+			loop_detect: false,
+			cycle_check: CycleCheck::default(),
+			cell_size: CellSize::default(),
+			wrap: true,
+			tape_size: None,
+			tape_mode: TapeMode::default(),
+			last_wrap_step: None,
+			max_memory: 1,
+			initial_memory: Vec::new(),
+			loop_stack: Vec::new(),
+			output_stream: None,
+			transfer_loops: true,
+			progress: None,
+			track_writes: true,
+			last_writes: Chunks::default(),
+			recordings: BTreeMap::new(),
+			logs: Logs::default(),
+			disabled_code_breaks: BTreeSet::new(),
+			ignore_code_breaks: false,
+			skipped_code_breaks: 0,
+			hooks: Hooks::default(),
+		}
+	}
+
+	/// Adds a watcher and returns its id
+	pub fn add_watch(&mut self, index: usize, condition: WatchCondition) -> Result<usize, String> {
+		match condition {
+			WatchCondition::Compare(_, value) => self.check_cell_value(value)?,
+			WatchCondition::PointerOutside(start, end) if start > end => {
+				return Err(format!("the range {start} to {end} is empty"));
+			}
+			WatchCondition::OutputLength(0) | WatchCondition::OutputByte(_, 0) => {
+				return Err("the output count must be at least 1".into());
+			}
+			_ => (),
+		}
+		let id = self.next_watcher_id;
+		self.next_watcher_id += 1;
+		self.watchers.push(MemoryWatcher {
+			id,
+			index,
+			condition,
+			log: false,
+			disabled: false,
+		});
+		Ok(id)
+	}
+
+	pub fn remove_watch(&mut self, id: usize) -> Result<(), String> {
+		let count = self.watchers.len();
+		self.watchers.retain(|w| w.id != id);
+		if self.watchers.len() == count {
+			return Err(format!("no watcher with id {id}"));
+		}
+		Ok(())
+	}
+
+	/// Appends to the input buffer, or makes the next read see EOF if `bytes` is an empty line
+	pub fn provide_input(&mut self, bytes: &[u8]) {
+		if bytes.trim_ascii().is_empty() {
+			self.send_eof = true;
+		} else {
+			self.input.extend_from_slice(bytes);
+		}
+	}
+
+	/// Appends to the input buffer, letting a program that waits for input continue
+	pub fn append_input(&mut self, bytes: &[u8]) {
+		self.input.extend_from_slice(bytes);
+		if self.state == State::WaitingForInput {
+			self.state = State::Running;
+		}
+	}
+
+	/// Index of the first real instruction at `line:column` of a source file
+	pub fn instruction_at(&self, file: usize, line: usize, column: usize) -> Option<usize> {
+		self.program[..self.program.len() - 1]
+			.iter()
+			.position(|c| c.is_at(file, line, Some(column)))
+	}
+
+	/// Adds a breakpoint and returns its id
+	pub fn add_breakpoint(
+		&mut self,
+		target: BreakTarget,
+		condition: Option<CellCondition>,
+	) -> Result<usize, String> {
+		if let Some(condition) = condition {
+			self.check_cell_value(condition.value)?;
+		}
+		if target == BreakTarget::Output(Vec::new()) {
+			return Err("the output to break on can not be empty".into());
+		}
+		if let BreakTarget::Step(step) = target {
+			if step <= self.steps {
+				return Err(format!(
+					"step {step} has already been reached (currently at step {})",
+					self.steps
+				));
+			}
+		}
+		let id = self.next_breakpoint_id;
+		self.next_breakpoint_id += 1;
+		self.breakpoints.push(Breakpoint {
+			id,
+			target,
+			condition,
+			hits: 0,
+			log: false,
+			once: false,
+			ignore: 0,
+			disabled: false,
+		});
+		Ok(id)
+	}
+
+	/// Replaces all watchers and breakpoints, e.g. when loading a saved session
+	pub fn set_debug_points(&mut self, watchers: Vec<MemoryWatcher>, breakpoints: Vec<Breakpoint>) {
+		self.next_watcher_id = watchers.iter().map(|w| w.id + 1).max().unwrap_or(1);
+		self.next_breakpoint_id = breakpoints.iter().map(|b| b.id + 1).max().unwrap_or(1);
+		self.watchers = watchers;
+		self.breakpoints = breakpoints;
+	}
+
+	pub fn breakpoint_mut(&mut self, id: usize) -> Result<&mut Breakpoint, String> {
+		self.breakpoints
+			.iter_mut()
+			.find(|b| b.id == id)
+			.ok_or(format!("no breakpoint with id {id}"))
+	}
+
+	pub fn watcher_mut(&mut self, id: usize) -> Result<&mut MemoryWatcher, String> {
+		self.watchers
+			.iter_mut()
+			.find(|w| w.id == id)
+			.ok_or(format!("no watcher with id {id}"))
+	}
+
+	pub fn delete_breakpoint(&mut self, id: usize) -> Result<(), String> {
+		let count = self.breakpoints.len();
+		self.breakpoints.retain(|b| b.id != id);
+		if self.breakpoints.len() == count {
+			return Err(format!("no breakpoint with id {id}"));
+		}
+		Ok(())
+	}
+
+	pub fn has_breakpoint(&self, index: usize) -> bool {
+		self.breakpoints
+			.iter()
+			.any(|b| !b.disabled && b.target == BreakTarget::Index(index))
+	}
+
+	/// Makes a `!` in the source do nothing, or stop again
+	pub fn set_code_break_enabled(&mut self, index: usize, enabled: bool) -> Result<(), String> {
+		if !matches!(
+			self.program.get(index).map(|c| &c.command),
+			Some(Command::Break)
+		) {
+			return Err(format!("there is no ! at program index {index}"));
+		}
+		if enabled {
+			self.disabled_code_breaks.remove(&index);
+		} else {
+			self.disabled_code_breaks.insert(index);
+		}
+		Ok(())
+	}
+
+	/// How many times a `!` was skipped since the program started, because it was disabled or ignored
+	pub fn skipped_code_breaks(&self) -> usize {
+		self.skipped_code_breaks
+	}
+
+	/// Program indices of the `!`s that were disabled
+	pub fn disabled_code_breaks(&self) -> impl Iterator<Item = usize> + '_ {
+		self.disabled_code_breaks.iter().copied()
+	}
+
+	/// The state a breakpoint stops with, if it triggers right now. `wrote` is whether the last step wrote output,
+	/// and `old_memory_len` is the tape length before it
+	fn breakpoint_state(
+		&self,
+		breakpoint: &Breakpoint,
+		wrote: bool,
+		old_memory_len: usize,
+	) -> Option<State> {
+		if breakpoint.disabled || !breakpoint.condition.is_none_or(|c| c.matches(self)) {
+			return None;
+		}
+		match &breakpoint.target {
+			&BreakTarget::Index(index) if index == self.program_ptr => Some(State::BreakPointHit),
+			&BreakTarget::Step(step) if step == self.steps => Some(State::StoppedAtStep),
+			&BreakTarget::Loop(limit)
+				if self.loop_stack.last().is_some_and(|l| {
+					l.iterations == limit + 1 && self.program_ptr == l.start + 1
+				}) =>
+			{
+				Some(State::RunawayLoop)
+			}
+			BreakTarget::Output(text) if wrote && self.output.ends_with(text) => {
+				Some(State::BreakPointHit)
+			}
+			&BreakTarget::MemorySize(limit)
+				if old_memory_len <= limit && self.memory.len() > limit =>
+			{
+				Some(State::BreakPointHit)
+			}
+			_ => None,
+		}
+	}
+
+	/// Stops on the first breakpoint that triggers, after every logpoint that triggers has logged
+	fn check_breakpoints(&mut self, wrote: bool, old_memory_len: usize) {
+		self.breakpoints_checked_at = Some((self.steps, self.program_ptr));
+		// one-shot breakpoints that are done, there is rarely more than one
+		let mut spent = Vec::new();
+		for i in 0..self.breakpoints.len() {
+			let Some(state) = self.breakpoint_state(&self.breakpoints[i], wrote, old_memory_len)
+			else {
+				continue;
+			};
+			let breakpoint = &mut self.breakpoints[i];
+			breakpoint.hits += 1;
+			if breakpoint.ignore > 0 {
+				breakpoint.ignore -= 1;
+				continue;
+			}
+			if breakpoint.log {
+				let (source, once) = (LogSource::Breakpoint(breakpoint.id), breakpoint.once);
+				self.log(source, self.mem_ptr);
+				if once {
+					spent.push(i);
+				}
+			} else if self.state == State::Running {
+				self.stop_reason = Some(StopReason::Breakpoint(breakpoint.clone()));
+				self.state = state;
+				if breakpoint.once {
+					spent.push(i);
+				}
+			}
+		}
+		for i in spent.into_iter().rev() {
+			self.breakpoints.remove(i);
+		}
+	}
+
+	pub fn step_once(&mut self) {
+		self.resume();
+		self.forget_cycles();
+		self.step_internal();
+	}
+
+	pub fn step(&mut self, num: usize) {
+		self.resume();
+		self.forget_cycles();
+		for _ in 0..num {
+			self.step_internal();
+			if self.state != State::Running {
+				break;
+			}
+		}
+	}
+
+	/// Runs through the whole loop if the current instruction starts one, otherwise steps once
+	pub fn step_over(&mut self) {
+		let Command::BeginLoop(end_of_loop) = self.program[self.program_ptr].command else {
+			self.step_once();
+			return;
+		};
+		self.resume();
+		if self.state != State::Running {
+			return;
+		}
+		self.run_until(|s| s.program_ptr == end_of_loop + 1);
+	}
+
+	/// Runs until the current instruction is on another source line, `num` times.
+	/// A loop within one line is run until it exits onto another line
+	pub fn step_line(&mut self, num: usize) {
+		self.resume();
+		for _ in 0..num {
+			let c = self.program[self.program_ptr];
+			self.run_until(|s| {
+				let now = &s.program[s.program_ptr];
+				(now.file, now.line_number) != (c.file, c.line_number)
+			});
+			if self.state != State::Running {
+				break;
+			}
+		}
+	}
+
+	/// Runs until the innermost loop around the current instruction exits
+	pub fn finish(&mut self) {
+		let Some(loop_end) = self.enclosing_loop_end() else {
+			self.run();
+			return;
+		};
+		self.resume();
+		self.run_until(|s| s.program_ptr == loop_end + 1);
+		if self.state == State::Running && self.program_ptr == loop_end + 1 {
+			self.state = State::LoopFinished;
+		}
+	}
+
+	/// Index of the `]` closing the innermost loop the program pointer is inside
+	fn enclosing_loop_end(&self) -> Option<usize> {
+		self.program[..self.program_ptr]
+			.iter()
+			.rev()
+			.find_map(|c| match c.command {
+				Command::BeginLoop(end) if end >= self.program_ptr => Some(end),
+				_ => None,
+			})
+	}
+
+	/// Runs until an instruction at `line` (and `column` if given) of a source file is reached
+	pub fn run_until_location(
+		&mut self,
+		file: usize,
+		line: usize,
+		column: Option<usize>,
+	) -> Result<(), String> {
+		let at_location = |s: &Self| s.program[s.program_ptr].is_at(file, line, column);
+		let exists = self.program[..self.program.len() - 1]
+			.iter()
+			.any(|c| c.is_at(file, line, column));
+		if !exists {
+			return Err("no instruction at that location".into());
+		}
+		self.resume();
+		self.run_until(at_location);
+		if self.state == State::Running && at_location(self) {
+			self.state = State::ReachedLocation;
+		}
+		Ok(())
+	}
+
+	fn run_until(&mut self, done: impl Fn(&Self) -> bool) {
+		self.forget_cycles();
+		self.step_internal();
+		while self.state == State::Running && !done(self) {
+			self.step_internal();
+		}
+	}
+
+	/// Undoes the last `num` executed instructions
+	pub fn back(&mut self, num: usize) -> Result<(), String> {
+		for _ in 0..num {
+			let record = match self.history.pop_back() {
+				Some(UndoRecord::Step(record)) => record,
+				Some(UndoRecord::Run(run)) => {
+					self.undo_run(*run);
+					continue;
+				}
+				None => return Err("no more history to step back through".into()),
+			};
+			self.program_ptr = record.program_ptr;
+			self.mem_ptr = record.mem_ptr;
+			self.memory.truncate(record.memory_len);
+			self.memory[record.mem_ptr] = record.cell;
+			self.input_ptr = record.input_ptr;
+			self.output.truncate(record.output_len);
+			self.undo_loop_change(record.loop_change);
+			self.max_memory = record.max_memory;
+			self.last_wrap_step = record.last_wrap_step;
+			self.steps -= 1;
+			self.state = State::Running;
+			self.stop_reason = None;
+		}
+		if self
+			.input_exhausted_at
+			.is_some_and(|step| step > self.steps)
+		{
+			self.input_exhausted_at = None;
+		}
+		self.forget_later_writes();
+		self.reset_change_watchers();
+		Ok(())
+	}
+
+	pub fn set_cell(&mut self, index: usize, value: u32) -> Result<(), String> {
+		self.check_cell_value(value)?;
+		self.check_tape_index(index)?;
+		self.grow_memory(index);
+		self.write_cell(index, value);
+		Ok(())
+	}
+
+	/// Writes `cells` into the tape from `start`, growing it as needed
+	pub fn load_memory(&mut self, start: usize, cells: &[u32]) -> Result<(), String> {
+		if cells.is_empty() {
+			return Ok(());
+		}
+		for &value in cells {
+			self.check_cell_value(value)?;
+		}
+		let last = start
+			.checked_add(cells.len() - 1)
+			.ok_or("the cells do not fit on the tape")?;
+		self.check_tape_index(last)?;
+		self.grow_memory(last);
+		for (offset, &value) in cells.iter().enumerate() {
+			self.write_cell(start + offset, value);
+		}
+		Ok(())
+	}
+
+	/// Sets the cells the tape starts with, now and every time the program starts over
+	pub fn set_initial_memory(&mut self, cells: Vec<u32>) -> Result<(), String> {
+		self.load_memory(0, &cells)?;
+		self.max_memory = self.max_memory.max(self.memory.len());
+		self.initial_memory = cells;
+		Ok(())
+	}
+
+	pub fn cell_size(&self) -> CellSize {
+		self.cell_size
+	}
+
+	/// Changes how many bits a cell has, cutting off the high bits of existing values when shrinking
+	pub fn set_cell_size(&mut self, size: CellSize) {
+		self.cell_size = size;
+		self.memory.map_written(|cell| cell & size.max());
+		self.fast_program = None;
+	}
+
+	fn check_cell_value(&self, value: u32) -> Result<(), String> {
+		if value > self.cell_size.max() {
+			return Err(format!(
+				"{value} does not fit in a {} bit cell",
+				self.cell_size.bits()
+			));
+		}
+		Ok(())
+	}
+
+	pub fn set_mem_ptr(&mut self, index: usize) -> Result<(), String> {
+		self.check_tape_index(index)?;
+		self.grow_memory(index);
+		self.mem_ptr = index;
+		Ok(())
+	}
+
+	pub fn tape_size(&self) -> Option<usize> {
+		self.tape_size
+	}
+
+	/// The most cells the tape has had since the program started
+	pub fn max_memory_len(&self) -> usize {
+		self.max_memory.max(self.memory.len())
+	}
+
+	pub fn tape_mode(&self) -> TapeMode {
+		self.tape_mode
+	}
+
+	/// Gives the tape a fixed number of cells, or lets it grow again with `None`
+	pub fn set_tape(&mut self, size: Option<usize>, mode: TapeMode) -> Result<(), String> {
+		if let Some(size) = size {
+			if size == 0 {
+				return Err("the tape needs at least one cell".into());
+			}
+			if self.mem_ptr >= size {
+				return Err(format!(
+					"the memory pointer is at cell {}, outside a tape of {size} cells",
+					self.mem_ptr
+				));
+			}
+			self.memory.resize(size);
+		}
+		self.tape_size = size;
+		self.tape_mode = mode;
+		Ok(())
+	}
+
+	fn check_tape_index(&self, index: usize) -> Result<(), String> {
+		match self.tape_size {
+			Some(size) if index >= size => {
+				Err(format!("cell {index} is outside the tape of {size} cells"))
+			}
+			_ => Ok(()),
+		}
+	}
+
+	/// Extends the tape with zeros so that `index` is valid
+	fn grow_memory(&mut self, index: usize) {
+		if index >= self.memory.len() {
+			self.memory.resize(index + 1);
+		}
+	}
+
+	pub fn history_limit(&self) -> usize {
+		self.history_limit
+	}
+
+	/// Sets how many executed instructions are remembered for `back`
+	pub fn set_history_limit(&mut self, limit: usize) {
+		self.history_limit = limit;
+		while self.history.len() > limit {
+			self.history.pop_front();
+		}
+	}
+
+	pub fn snapshot(&self) -> Snapshot {
+		Snapshot {
+			memory: self.memory.clone(),
+			mem_ptr: self.mem_ptr,
+			program_ptr: self.program_ptr,
+			output: self.output.clone(),
+			input_ptr: self.input_ptr,
+			send_eof: self.send_eof,
+			loop_stack: self.loop_stack.clone(),
+			steps: self.steps,
+			state: self.state,
+		}
+	}
+
+	/// Puts the program back into the state of `snapshot`, discarding the undo history
+	pub fn restore(&mut self, snapshot: &Snapshot) {
+		self.memory = snapshot.memory.clone();
+		self.mem_ptr = snapshot.mem_ptr;
+		self.program_ptr = snapshot.program_ptr;
+		self.output = snapshot.output.clone();
+		self.input_ptr = snapshot.input_ptr.min(self.input.len());
+		self.send_eof = snapshot.send_eof;
+		self.loop_stack = snapshot.loop_stack.clone();
+		self.steps = snapshot.steps;
+		self.state = snapshot.state;
+		self.stop_reason = None;
+		if self
+			.input_exhausted_at
+			.is_some_and(|step| step > self.steps)
+		{
+			self.input_exhausted_at = None;
+		}
+		self.history.clear();
+		self.breakpoints_checked_at = None;
+		self.clear_writes();
+		self.reset_change_watchers();
+	}
+
+	/// Replaces the program and starts over from the beginning, keeping the input, settings, watchers and breakpoints.
+	/// Breakpoints on program indices are left as they are, for the caller to move to the new program
+	pub fn load_program(&mut self, program: Vec<DebugCommand>, keep_memory: bool) {
+		self.program = program;
+		self.fast_program = None;
+		if let Some(counts) = &mut self.profile {
+			*counts = vec![0; self.program.len()];
+		}
+		self.coverage = vec![0; self.program.len()];
+		self.disabled_code_breaks.clear();
+		self.restart(keep_memory);
+	}
+
+	/// Adds instructions at the end of the program, before its `End`. Every bracket in them has to be
+	/// closed, and their loop targets are counted from their first instruction. A program that ended
+	/// continues with the first new instruction
+	pub fn append_program(&mut self, code: Vec<DebugCommand>) {
+		let end = self.program.len() - 1;
+		let added = code.len();
+		let offset = |target: usize| target + end;
+		self.program.splice(
+			end..end,
+			code.into_iter().map(|c| DebugCommand {
+				command: match c.command {
+					Command::BeginLoop(target) => Command::BeginLoop(offset(target)),
+					Command::EndLoop(target) => Command::EndLoop(offset(target)),
+					command => command,
+				},
+				..c
+			}),
+		);
+		self.fast_program = None;
+		if let Some(counts) = &mut self.profile {
+			counts.splice(end..end, vec![0; added]);
+		}
+		self.coverage.splice(end..end, vec![0; added]);
+		if added > 0 && self.state == State::EndOfProgram && self.program_ptr == end {
+			self.state = State::Running;
+		}
+	}
+
+	/// Starts the program over from step 0 with empty memory, keeping the input, settings, watchers and breakpoints
+	pub fn reset(&mut self) {
+		self.restart(false);
+	}
+
+	fn restart(&mut self, keep_memory: bool) {
+		if !keep_memory {
+			self.memory = Tape::new(self.tape_size.unwrap_or(1));
+			self.memory
+				.resize(self.memory.len().max(self.initial_memory.len()));
+			for (index, &value) in self.initial_memory.iter().enumerate() {
+				self.memory[index] = value;
+			}
+		}
+		self.mem_ptr = 0;
+		self.program_ptr = 0;
+		self.output.clear();
+		self.input_ptr = 0;
+		self.input_exhausted_at = None;
+		self.send_eof = false;
+		self.state = State::Running;
+		self.steps = 0;
+		self.stop_reason = None;
+		self.history.clear();
+		self.breakpoints_checked_at = None;
+		self.loop_stack.clear();
+		self.last_wrap_step = None;
+		self.skipped_code_breaks = 0;
+		self.max_memory = self.memory.len();
+		self.clear_writes();
+		self.reset_change_watchers();
+	}
+
+	/// Makes change watchers compare against the current memory, not what was there before
+	pub(crate) fn reset_change_watchers(&mut self) {
+		for watcher in &mut self.watchers {
+			if let WatchCondition::Changed { last } = &mut watcher.condition {
+				*last = self.memory.get(watcher.index).unwrap_or(0);
+			}
+		}
+	}
+
+	pub fn run(&mut self) {
+		self.resume();
+		self.forget_cycles();
+		// nothing has stopped on the next instruction yet if it was never stepped onto, like the first one
+		if self.state == State::Running
+			&& !self.breakpoints.is_empty()
+			&& self.breakpoints_checked_at != Some((self.steps, self.program_ptr))
+		{
+			self.check_breakpoints(false, self.memory.len());
+			if self.state != State::Running {
+				return;
+			}
+		}
+		if self.can_run_fast() {
+			self.run_fast();
+			return;
+		}
+		while self.state == State::Running {
+			self.step_internal();
+		}
+	}
+
+	/// Moves past the current instruction without executing it. Memory and the step count are left as they are
+	pub fn skip(&mut self) -> Result<(), String> {
+		if self.program_ptr + 1 >= self.program.len() {
+			return Err("can't skip past the end of the program".into());
+		}
+		self.skip_to(self.program_ptr + 1);
+		Ok(())
+	}
+
+	/// Moves past the whole loop starting at the current instruction without executing any of it
+	pub fn skip_loop(&mut self) -> Result<(), String> {
+		let Command::BeginLoop(end_of_loop) = self.program[self.program_ptr].command else {
+			return Err("the current instruction does not start a loop".into());
+		};
+		self.skip_to(end_of_loop + 1);
+		Ok(())
+	}
+
+	/// Moves the program pointer to `index` without executing anything in between.
+	/// Loop activations for loops that don't contain `index` are dropped
+	pub fn goto(&mut self, index: usize) -> Result<(), String> {
+		if index + 1 >= self.program.len() {
+			return Err(format!("there is no instruction at index {index}"));
+		}
+		let enclosing = self.enclosing_loops(index);
+		self.loop_stack.retain(|l| enclosing.contains(&l.start));
+		self.program_ptr = index;
+		self.clear_pause();
+		self.stop_reason = None;
+		Ok(())
+	}
+
+	/// Program indices of the `[` of every loop around `index`, outermost first
+	pub fn enclosing_loops(&self, index: usize) -> Vec<usize> {
+		self.program[..index]
+			.iter()
+			.enumerate()
+			.filter_map(|(start, c)| match c.command {
+				Command::BeginLoop(end) if end >= index => Some(start),
+				_ => None,
+			})
+			.collect()
+	}
+
+	fn skip_to(&mut self, index: usize) {
+		let from = self.program_ptr;
+		self.program_ptr = index;
+		self.clear_pause();
+		self.stop_reason = Some(StopReason::Skipped { from, to: index });
+	}
+
+	/// Goes back to `Running` after moving the program pointer by hand, except from moving off the
+	/// tape, which only `clear_error` clears
+	fn clear_pause(&mut self) {
+		if !matches!(self.state, State::TooFarLeft | State::TooFarRight) {
+			self.state = State::Running;
+		}
+	}
+
+	/// Clears a `TooFarLeft` or `TooFarRight` error, so the program can go on. The `<` or `>` that
+	/// caused it is tried again unless it was skipped or the program pointer moved with `goto`, so the
+	/// memory pointer should be moved with `set_mem_ptr` first. Does nothing in any other state
+	pub fn clear_error(&mut self) {
+		if matches!(self.state, State::TooFarLeft | State::TooFarRight) {
+			self.state = State::Running;
+			self.stop_reason = None;
+		}
+	}
+
+	/// Continues after a breakpoint or other pause, unless the program can't go any further
+	fn resume(&mut self) {
+		if !matches!(
+			self.state,
+			State::EndOfProgram | State::TooFarLeft | State::TooFarRight
+		) {
+			self.state = State::Running;
+		}
+	}
+
+	/// Whether nothing needs to see each instruction as it executes
+	fn can_run_fast(&self) -> bool {
+		self.watchers.is_empty()
+			&& self.breakpoints.is_empty()
+			&& !self.interactive_input
+			&& !self.numeric_io
+			&& !self.loop_detect
+			&& self.trace.is_none()
+			&& self.profile.is_none()
+			&& self.wrap
+			&& !self.is_recording()
+			&& self.hooks.0.is_empty()
+	}
+
+	fn step_internal(&mut self) {
+		if self.hooks.0.is_empty() {
+			self.step_instruction();
+		} else {
+			self.step_with_hooks();
+		}
+	}
+
+	fn step_instruction(&mut self) {
+		if self.state == State::Running {
+			self.stop_reason = None;
+		}
+		if self.program_ptr + 1 == self.program.len() {
+			self.state = State::EndOfProgram;
+		}
+		if self.state == State::Running && self.max_steps.is_some_and(|max| self.steps >= max) {
+			self.state = State::StepLimitReached;
+		}
+		if self.state != State::Running {
+			return;
+		}
+		let command = self.program[self.program_ptr].command;
+		if let Command::Read = command {
+			if self.input_exhausted() && self.interactive_input && !self.send_eof {
+				self.state = State::WaitingForInput;
+				return;
+			}
+			self.send_eof = false;
+		}
+		if matches!(command, Command::Right)
+			&& self.tape_size.is_none()
+			&& self.mem_ptr + 1 >= self.memory.len()
+			&& self
+				.memory_limit
+				.is_some_and(|limit| self.memory.len() >= limit)
+		{
+			self.state = State::MemoryLimitReached;
+			return;
+		}
+		// moving off the tape is refused before anything changes, so the instruction can be tried
+		// again once the pointer was moved, or skipped
+		let wraps = self.tape_size.is_some() && self.tape_mode == TapeMode::Wrap;
+		match command {
+			Command::Left if self.mem_ptr == 0 && !wraps => self.state = State::TooFarLeft,
+			Command::Right
+				if !wraps && self.tape_size.is_some_and(|size| self.mem_ptr + 1 == size) =>
+			{
+				self.state = State::TooFarRight
+			}
+			_ => (),
+		}
+		if self.state != State::Running {
+			self.stop_reason = Some(StopReason::OffTape(self.program_ptr));
+			return;
+		}
+		if !self.wrap {
+			let cell = self.memory[self.mem_ptr];
+			match command {
+				Command::Inc if cell == self.cell_size.max() => self.state = State::CellOverflow,
+				Command::Dec if cell == 0 => self.state = State::CellUnderflow,
+				_ => (),
+			}
+			if self.state != State::Running {
+				return;
+			}
+		}
+		let index = self.program_ptr;
+		let old_cell = self.memory[self.mem_ptr];
+		let old_memory_len = self.memory.len();
+		let old_output_len = self.output.len();
+		self.record_history(old_cell);
+		if matches!(command, Command::Read)
+			&& self.input_exhausted_at.is_none()
+			&& self.input_exhausted()
+		{
+			self.input_exhausted_at = Some(self.steps + 1);
+		}
+		match command {
+			Command::Inc => {
+				self.write_cell(
+					self.mem_ptr,
+					old_cell.wrapping_add(1) & self.cell_size.max(),
+				);
+			}
+			Command::Dec => {
+				self.write_cell(
+					self.mem_ptr,
+					old_cell.wrapping_sub(1) & self.cell_size.max(),
+				);
+			}
+			Command::Right => match self.tape_size {
+				Some(size) if self.mem_ptr + 1 == size => {
+					self.mem_ptr = 0;
+					self.last_wrap_step = Some(self.steps + 1);
+				}
+				Some(_) => self.mem_ptr += 1,
+				None => {
+					self.mem_ptr += 1;
+					if self.mem_ptr >= self.memory.len() {
+						self.memory.push(0);
+						self.max_memory = self.max_memory.max(self.memory.len());
+						self.update_watchers(self.mem_ptr);
+					}
+				}
+			},
+			Command::Left => match (self.mem_ptr, self.tape_size) {
+				(0, Some(size)) => {
+					self.mem_ptr = size - 1;
+					self.last_wrap_step = Some(self.steps + 1);
+				}
+				_ => self.mem_ptr -= 1,
+			},
+			Command::Read if self.numeric_io => {
+				let value = self
+					.read_number()
+					.unwrap_or_else(|| self.eof_value(old_cell));
+				self.write_cell(self.mem_ptr, value);
+			}
+			Command::Read => {
+				let value = match self.input.get(self.input_ptr) {
+					Some(&byte) => {
+						self.input_ptr += 1;
+						byte as u32
+					}
+					None => self.eof_value(old_cell),
+				};
+				self.write_cell(self.mem_ptr, value);
+			}
+			Command::Write if self.numeric_io => {
+				for byte in format!("{old_cell} ").into_bytes() {
+					self.output.push(byte);
+					if let Some(OutputStream(stream)) = &mut self.output_stream {
+						stream(byte);
+					}
+				}
+			}
+			// wider cells are written as their lowest byte
+			Command::Write => {
+				let byte = old_cell as u8;
+				self.output.push(byte);
+				if let Some(OutputStream(stream)) = &mut self.output_stream {
+					stream(byte);
+				}
+			}
+			Command::Clear => self.write_cell(self.mem_ptr, 0),
+			Command::BeginLoop(end_of_loop) => {
+				if old_cell == 0 {
+					self.program_ptr = end_of_loop;
+				} else {
+					let change = self.enter_loop(index);
+					self.record_loop_change(change);
+				}
+			}
+			Command::EndLoop(start_of_loop) => {
+				let change = if old_cell != 0 {
+					self.program_ptr = start_of_loop;
+					self.repeat_loop(start_of_loop)
+				} else {
+					self.exit_loop(start_of_loop)
+				};
+				self.record_loop_change(change);
+			}
+			Command::Break
+				if self.ignore_code_breaks || self.disabled_code_breaks.contains(&index) =>
+			{
+				self.skipped_code_breaks += 1;
+			}
+			Command::Break => {
+				self.state = State::BreakPointHit;
+				self.stop_reason = Some(StopReason::CodeBreakpoint(index));
+			}
+			Command::Assert(expected) if old_cell != expected => {
+				self.state = State::AssertionFailed;
+				self.stop_reason = Some(StopReason::Assertion {
+					index,
+					actual: old_cell,
+				});
+			}
+			Command::Assert(_) => (),
+			Command::End => (),
+		}
+		if self.loop_detect
+			&& matches!(
+				command,
+				Command::Inc | Command::Dec | Command::Read | Command::Clear
+			) {
+			self.note_cell_write(old_cell);
+		}
+		if matches!(command, Command::Right | Command::Left)
+			&& self.state == State::Running
+			&& !self.watchers.is_empty()
+		{
+			self.update_pointer_watchers();
+		}
+		if matches!(command, Command::Write)
+			&& self.state == State::Running
+			&& !self.watchers.is_empty()
+		{
+			self.update_output_watchers(old_output_len);
+		}
+
+		self.program_ptr += 1;
+		self.steps += 1;
+		if (self.track_writes || self.is_recording())
+			&& matches!(
+				command,
+				Command::Inc | Command::Dec | Command::Read | Command::Clear
+			) {
+			self.record_write(index, old_cell);
+		}
+		self.trace_step(index);
+		self.coverage[index] += 1;
+		if let Some(counts) = &mut self.profile {
+			counts[index] += 1;
+		}
+		if self.state == State::Running && !self.breakpoints.is_empty() {
+			self.check_breakpoints(matches!(command, Command::Write), old_memory_len);
+		}
+		if self.state == State::Running && self.loop_detect {
+			self.check_cycle();
+		}
+		if self.state == State::Running && self.max_steps.is_some_and(|max| self.steps >= max) {
+			self.state = State::StepLimitReached;
+		}
+		if self.steps.is_multiple_of(INTERRUPT_CHECK_INTERVAL) && self.state == State::Running {
+			self.check_interrupt();
+		}
+	}
+
+	/// Stops with `Interrupted` if the interrupt flag was set, and reports the progress so far
+	pub(crate) fn check_interrupt(&mut self) {
+		if let Some(flag) = &self.interrupt {
+			if flag.swap(false, Ordering::Relaxed) {
+				self.state = State::Interrupted;
+			}
+		}
+		if let Some(ProgressReport(report)) = &mut self.progress {
+			report(Progress {
+				steps: self.steps,
+				memory_len: self.memory.len(),
+				output_len: self.output.len(),
+			});
+		}
+	}
+
+	/// What `,` stores in a cell holding `old_cell` when there is no input left
+	fn eof_value(&self, old_cell: u32) -> u32 {
+		match self.eof_behavior {
+			EofBehavior::Zero => 0,
+			EofBehavior::Max => self.cell_size.max(),
+			EofBehavior::Unchanged => old_cell,
+		}
+	}
+
+	/// Stores a value in a cell that is on the tape and checks the watchers on it. Writes by the
+	/// program and from outside it all go through here, only `run_fast` skips it since it never
+	/// runs with watchers
+	fn write_cell(&mut self, index: usize, value: u32) {
+		self.memory[index] = value;
+		self.update_watchers(index);
+	}
+
+	/// Whether the next `,` would read EOF
+	fn input_exhausted(&self) -> bool {
+		let rest = &self.input[self.input_ptr..];
+		if self.numeric_io {
+			!rest.iter().any(u8::is_ascii_digit)
+		} else {
+			rest.is_empty()
+		}
+	}
+
+	/// Reads the next decimal number in the input for `numeric_io`, skipping anything before it that is not a digit.
+	/// Numbers too big for a cell wrap around
+	fn read_number(&mut self) -> Option<u32> {
+		let input = &self.input[self.input_ptr..];
+		let Some(start) = input.iter().position(u8::is_ascii_digit) else {
+			self.input_ptr = self.input.len();
+			return None;
+		};
+		let len = input[start..]
+			.iter()
+			.take_while(|b| b.is_ascii_digit())
+			.count();
+		let modulus = self.cell_size.max() as u64 + 1;
+		let value = input[start..start + len].iter().fold(0, |value, &digit| {
+			(value * 10 + (digit - b'0') as u64) % modulus
+		});
+		self.input_ptr += start + len;
+		Some(value as u32)
+	}
+
+	fn record_history(&mut self, cell: u32) {
+		if self.history_limit == 0 {
+			return;
+		}
+		self.push_history(UndoRecord::Step(StepRecord {
+			program_ptr: self.program_ptr,
+			mem_ptr: self.mem_ptr,
+			cell,
+			memory_len: self.memory.len(),
+			input_ptr: self.input_ptr,
+			output_len: self.output.len(),
+			loop_change: LoopChange::None,
+			max_memory: self.max_memory,
+			last_wrap_step: self.last_wrap_step,
+		}));
+	}
+
+	fn push_history(&mut self, record: UndoRecord) {
+		if self.history.len() >= self.history_limit {
+			self.history.pop_front();
+		}
+		self.history.push_back(record);
+	}
+
+	/// Remembers how the instruction that was just recorded changed the loop stack
+	fn record_loop_change(&mut self, change: LoopChange) {
+		if let Some(UndoRecord::Step(record)) =
+			self.history.back_mut().filter(|_| self.history_limit > 0)
+		{
+			record.loop_change = change;
+		}
+	}
+
+	/// Checks the watchers on a cell that was just written or allocated
+	fn update_watchers(&mut self, index: usize) {
+		if self.watchers.is_empty() {
+			return;
+		}
+		let value = self.memory[index];
+		for i in 0..self.watchers.len() {
+			let watcher = &self.watchers[i];
+			if watcher.index != index
+				|| watcher.condition.is_pointer()
+				|| watcher.condition.is_output()
+			{
+				continue;
+			}
+			// the old value is still followed, so enabling it again does not trigger on old changes
+			let fired = !watcher.disabled
+				&& match watcher.condition {
+					WatchCondition::Changed { last } => last != value,
+					WatchCondition::Compare(comparison, target) => {
+						comparison.compare(value, target)
+					}
+					_ => false,
+				};
+			if fired && watcher.log {
+				self.log(LogSource::Watcher(watcher.id), index);
+			} else if fired {
+				self.state = State::StoppedOnMemoryValue;
+				self.stop_reason = Some(StopReason::Watcher {
+					watcher: watcher.clone(),
+					value,
+				});
+			}
+			if let WatchCondition::Changed { last } = &mut self.watchers[i].condition {
+				*last = value;
+			}
+		}
+	}
+
+	/// Checks the pointer watchers after the memory pointer moved
+	fn update_pointer_watchers(&mut self) {
+		let ptr = self.mem_ptr;
+		for i in 0..self.watchers.len() {
+			let watcher = &self.watchers[i];
+			let fired = !watcher.disabled
+				&& match watcher.condition {
+					WatchCondition::Pointer(comparison, target) => comparison.compare(ptr, target),
+					WatchCondition::PointerOutside(start, end) => !(start..=end).contains(&ptr),
+					_ => false,
+				};
+			if fired && watcher.log {
+				self.log(LogSource::Watcher(watcher.id), ptr);
+			} else if fired {
+				self.state = State::StoppedOnMemoryValue;
+				self.stop_reason = Some(StopReason::Watcher {
+					watcher: watcher.clone(),
+					value: ptr as u32,
+				});
+			}
+		}
+	}
+
+	/// Checks the output watchers after a `.` wrote the output from `old_len` on
+	fn update_output_watchers(&mut self, old_len: usize) {
+		for i in 0..self.watchers.len() {
+			let watcher = &self.watchers[i];
+			if watcher.disabled {
+				continue;
+			}
+			let written = &self.output[old_len..];
+			// the offset of the byte that made the watcher trigger
+			let offset = match watcher.condition {
+				WatchCondition::OutputLength(len) => {
+					(old_len < len && len <= self.output.len()).then(|| len - 1)
+				}
+				WatchCondition::OutputByte(byte, count) if written.contains(&byte) => {
+					let before = self.output[..old_len]
+						.iter()
+						.filter(|&&b| b == byte)
+						.count();
+					// which of the bytes written now is the `count`th one
+					count.checked_sub(before + 1).and_then(|n| {
+						written
+							.iter()
+							.enumerate()
+							.filter(|&(_, &b)| b == byte)
+							.nth(n)
+							.map(|(i, _)| old_len + i)
+					})
+				}
+				_ => None,
+			};
+			let Some(offset) = offset else {
+				continue;
+			};
+			if watcher.log {
+				self.log(LogSource::Watcher(watcher.id), self.mem_ptr);
+			} else {
+				self.state = State::StoppedOnMemoryValue;
+				self.stop_reason = Some(StopReason::Watcher {
+					watcher: watcher.clone(),
+					value: offset as u32,
+				});
+			}
+		}
+	}
+}
+
+impl Snapshot {
+	/// Approximate number of bytes used by the snapshot
+	pub fn size(&self) -> usize {
+		std::mem::size_of::<Self>() + self.memory.heap_size() + self.output.len()
+	}
+}
+
+impl Command {
+	/// Program index of the other bracket, if this is a `[` or `]`
+	pub fn matching_bracket(&self) -> Option<usize> {
+		match *self {
+			Command::BeginLoop(partner) | Command::EndLoop(partner) => Some(partner),
+			_ => None,
+		}
+	}
+}
+
+impl DebugCommand {
+	pub fn is_at(&self, file: usize, line: usize, column: Option<usize>) -> bool {
+		self.file == file && self.line_number == line && column.is_none_or(|c| self.column == c)
+	}
+}
+
+/// A hash of the instruction sequence, used to tell whether saved program positions still make sense
+pub fn program_hash(program: &[DebugCommand]) -> u64 {
+	// FNV-1a, so the value stays the same between builds
+	let mut hash: u64 = 0xcbf29ce484222325;
+	for c in program {
+		for byte in format!("{:?}", c.command).bytes() {
+			hash ^= byte as u64;
+			hash = hash.wrapping_mul(0x100000001b3);
+		}
+	}
+	hash
+}
+
+/// Parses the program, or returns every bracket error in the order they appear.
+/// Lines and columns are counted from 1
+pub fn parse(source_text: &str) -> Result<Vec<DebugCommand>, Vec<ParseError>> {
+	parse_files(&[source_text])
+}
+
+/// Parses several source files in order into one program. Brackets can be matched across files
+pub fn parse_files(sources: &[&str]) -> Result<Vec<DebugCommand>, Vec<ParseError>> {
+	let (mut out, loop_starts, mut errors) = parse_commands(sources);
+	let (line_number, column) = end_location(sources.last().unwrap_or(&""));
+	out.push(DebugCommand {
+		command: Command::End,
+		file: sources.len().saturating_sub(1),
+		line_number,
+		column,
+	});
+	for loop_start_index in loop_starts {
+		let loop_start = &out[loop_start_index];
+		errors.push(ParseError {
+			kind: ParseErrorKind::UnclosedOpen,
+			file: loop_start.file,
+			line_number: loop_start.line_number,
+			column: loop_start.column,
+		});
+	}
+	if errors.is_empty() {
+		Ok(out)
+	} else {
+		errors.sort_by_key(|e| (e.file, e.line_number, e.column));
+		Err(errors)
+	}
+}
+
+/// Line and column just after the last character of a source file, where the `End` of the program is
+pub fn end_location(source_text: &str) -> (usize, usize) {
+	let last_line = source_text.lines().last().unwrap_or("");
+	(
+		source_text.lines().count().max(1),
+		last_line.chars().count() + 1,
+	)
+}
+
+/// Whether a parsed program has nothing but its `End`, because the source only has comments
+pub fn is_empty_program(program: &[DebugCommand]) -> bool {
+	program.iter().all(|c| matches!(c.command, Command::End))
+}
+
+/// Code typed in pieces, as parsed by `parse_partial`
+#[derive(Debug, Clone)]
+pub struct PartialProgram {
+	/// The instructions up to the first `[` that is still open, without an `End`
+	pub program: Vec<DebugCommand>,
+	/// Line and column of that `[`, from where the text has to be parsed again once more is typed
+	pub open: Option<(usize, usize)>,
+}
+
+/// Parses code that is typed in pieces, like in the scratchpad. A `[` may stay open for a later
+/// piece to close, only a `]` that closes nothing is an error
+pub fn parse_partial(source_text: &str) -> Result<PartialProgram, Vec<ParseError>> {
+	let (mut program, loop_starts, errors) = parse_commands(&[source_text]);
+	if !errors.is_empty() {
+		return Err(errors);
+	}
+	let open = loop_starts.first().map(|&index| {
+		let loop_start = &program[index];
+		(loop_start.line_number, loop_start.column)
+	});
+	if let Some(&index) = loop_starts.first() {
+		program.truncate(index);
+	}
+	Ok(PartialProgram { program, open })
+}
+
+/// The instructions of every file without an `End`, the indices of brackets left open and the
+/// errors for brackets closing nothing
+fn parse_commands(sources: &[&str]) -> (Vec<DebugCommand>, Vec<usize>, Vec<ParseError>) {
+	let mut out: Vec<DebugCommand> = Vec::new();
+	let mut loop_starts = Vec::new();
+	let mut errors = Vec::new();
+	let lines = sources.iter().enumerate().flat_map(|(file, text)| {
+		text.lines()
+			.enumerate()
+			.map(move |(num, line)| (file, num + 1, line))
+	});
+	for (file, line_number, line) in lines {
+		let chars: Vec<char> = line.chars().collect();
+		let mut skip_to = 0;
+		for (i, &char) in chars.iter().enumerate() {
+			if i < skip_to {
+				continue;
+			}
+			let column = i + 1;
+			let cmd = match char {
+				'+' => Command::Inc,
+				'-' => Command::Dec,
+				'>' => Command::Right,
+				'<' => Command::Left,
+				',' => Command::Read,
+				'.' => Command::Write,
+				'[' => {
+					loop_starts.push(out.len());
+					Command::BeginLoop(usize::MAX)
+				}
+				']' => {
+					let Some(last_loop_start) = loop_starts.pop() else {
+						errors.push(ParseError {
+							kind: ParseErrorKind::UnmatchedClose,
+							file,
+							line_number,
+							column,
+						});
+						continue;
+					};
+					out[last_loop_start].command = Command::BeginLoop(out.len());
+
+					Command::EndLoop(last_loop_start)
+				}
+				'!' => Command::Break,
+				'{' => match assert_directive(&chars[i..]) {
+					Some((value, len)) => {
+						skip_to = i + len;
+						Command::Assert(value)
+					}
+					None => continue,
+				},
+				_ => continue,
+			};
+			out.push(DebugCommand {
+				command: cmd,
+				file,
+				line_number,
+				column,
+			});
+		}
+	}
+	(out, loop_starts, errors)
+}
+
+/// The value and length of a `{=N}` assert at the start of `chars`
+fn assert_directive(chars: &[char]) -> Option<(u32, usize)> {
+	let digits = chars
+		.get(2..)?
+		.iter()
+		.take_while(|c| c.is_ascii_digit())
+		.count();
+	if !chars.starts_with(&['{', '=']) || digits == 0 || chars.get(2 + digits) != Some(&'}') {
+		return None;
+	}
+	let value = chars[2..2 + digits]
+		.iter()
+		.collect::<String>()
+		.parse()
+		.ok()?;
+	Some((value, digits + 3))
+}
+
+/// Removes the `{=N}` asserts from a program, for `--no-asserts`
+pub fn strip_asserts(program: Vec<DebugCommand>) -> Vec<DebugCommand> {
+	let mut out: Vec<_> = program
+		.into_iter()
+		.filter(|c| !matches!(c.command, Command::Assert(_)))
+		.collect();
+	link_brackets(&mut out);
+	out
+}
+
+/// Points the brackets of a program that had instructions removed at their partners again
+pub(crate) fn link_brackets(program: &mut [DebugCommand]) {
+	let mut loop_starts = Vec::new();
+	for index in 0..program.len() {
+		match program[index].command {
+			Command::BeginLoop(_) => loop_starts.push(index),
+			Command::EndLoop(_) => {
+				let start = loop_starts.pop().unwrap();
+				program[start].command = Command::BeginLoop(index);
+				program[index].command = Command::EndLoop(start);
+			}
+			_ => (),
+		}
+	}
+}
+
+/// Characters that `parse` ignores as comments but that may be mistyped commands, like `;`, `}`,
+/// digits or look-alikes from other alphabets. Letters, whitespace, `{=N}` asserts and `@index:name`
+/// label annotations are taken to be comments. The warnings are given the file index `file`
+pub fn strict_warnings(source_text: &str, file: usize) -> Vec<ParseError> {
+	let mut warnings = Vec::new();
+	for (line_number, line) in source_text.lines().enumerate() {
+		let chars: Vec<char> = line.chars().collect();
+		let mut skip_to = 0;
+		for (i, &c) in chars.iter().enumerate() {
+			if let Some((_, len)) = assert_directive(&chars[i..]) {
+				skip_to = i + len;
+			}
+			if c == '@' && i >= skip_to {
+				let digits = chars[i + 1..]
+					.iter()
+					.take_while(|c| c.is_ascii_digit())
+					.count();
+				if digits > 0 && chars.get(i + 1 + digits) == Some(&':') {
+					let name = chars[i + 2 + digits..]
+						.iter()
+						.take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+						.count();
+					skip_to = i + 2 + digits + name;
+				}
+			}
+			if i < skip_to || c.is_whitespace() || c.is_alphabetic() || "+-<>,.[]!".contains(c) {
+				continue;
+			}
+			warnings.push(ParseError {
+				kind: ParseErrorKind::Suspicious(c),
+				file,
+				line_number: line_number + 1,
+				column: i + 1,
+			});
+		}
+	}
+	warnings
+}
+
+/// Finds cell name annotations of the form `@index:name` in the source, e.g. `@0:counter`.
+/// Names only contain letters, digits and `_`, so an annotation never contains an instruction
+pub fn parse_labels(source_text: &str) -> Vec<(usize, String)> {
+	let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_';
+	let mut labels = Vec::new();
+	for annotation in source_text.split('@').skip(1) {
+		let Some((index, rest)) = annotation.split_once(':') else {
+			continue;
+		};
+		let name = &rest[..rest.find(|c| !is_name(c)).unwrap_or(rest.len())];
+		let starts_well = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+		if let (Ok(index), true) = (index.parse(), starts_well) {
+			labels.push((index, name.to_string()));
+		}
+	}
+	labels
+}
+
+impl Display for ParseError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.kind {
+			ParseErrorKind::UnmatchedClose => write!(
+				f,
+				"no opening bracket for closing bracket at {}:{}",
+				self.line_number, self.column
+			),
+			ParseErrorKind::UnclosedOpen => write!(
+				f,
+				"no matching closing bracket for open bracket at {}:{}",
+				self.line_number, self.column
+			),
+			ParseErrorKind::Suspicious(c) => write!(
+				f,
+				"{c:?} at {}:{} is not a command and is ignored",
+				self.line_number, self.column
+			),
+		}
+	}
+}
+
+/// Parses a cell value typed as a number like `65`, hex like `0x41` or a character like `'A'`.
+/// Characters can be the escapes `'\n'`, `'\t'`, `'\r'`, `'\0'`, `'\\'`, `'\''` and `'\xNN'`
+pub fn parse_value(text: &str) -> Result<u32, String> {
+	if let Some(quoted) = text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+		let value = match quoted.strip_prefix('\\') {
+			Some("n") => '\n' as u32,
+			Some("t") => '\t' as u32,
+			Some("r") => '\r' as u32,
+			Some("0") => 0,
+			Some("\\") => '\\' as u32,
+			Some("'") => '\'' as u32,
+			Some(hex) if hex.len() == 3 && hex.starts_with('x') => {
+				u32::from_str_radix(&hex[1..], 16)
+					.map_err(|_| format!("\\x in {text} must be followed by two hex digits"))?
+			}
+			Some(_) => return Err(format!("unknown escape in {text}")),
+			None => {
+				let mut chars = quoted.chars();
+				match (chars.next(), chars.next()) {
+					(Some(c), None) => c as u32,
+					(None, _) => return Err("'' has no character in it".into()),
+					(Some(_), Some(_)) => {
+						return Err(format!("{text} is more than one character"));
+					}
+				}
+			}
+		};
+		return Ok(value);
+	}
+	let (digits, radix) = match text.strip_prefix("0x") {
+		Some(hex) => (hex, 16),
+		None => (text, 10),
+	};
+	if digits.is_empty()
+		|| digits.starts_with(['_', ','])
+		|| digits.ends_with(['_', ','])
+		|| !digits
+			.chars()
+			.all(|c| c.is_digit(radix) || c == '_' || c == ',')
+	{
+		return Err(format!(
+			"'{text}' is not a number, a 0x hex number or a 'c' character"
+		));
+	}
+	u32::from_str_radix(&digits.replace(['_', ','], ""), radix)
+		.map_err(|_| format!("{text} is too large for a cell"))
+}
+
+/// A value with the character it stands for when that is printable ASCII or a common escape,
+/// like `65 ('A')`
+fn value_text(value: u32) -> String {
+	match u8::try_from(value) {
+		Ok(0) => "0 ('\\0')".into(),
+		Ok(byte @ (b' '..=b'~' | b'\n' | b'\t' | b'\r')) => {
+			format!("{value} ('{}')", byte.escape_ascii())
+		}
+		_ => value.to_string(),
+	}
+}
+
+impl CellCondition {
+	pub fn parse(cell: &str, comparison: &str, value: &str) -> Result<Self, String> {
+		let cell = match cell {
+			"cell" => None,
+			index => Some(
+				index
+					.parse()
+					.map_err(|_| format!("'{index}' is not 'cell' or a valid cell index"))?,
+			),
+		};
+		let comparison = Comparison::parse(comparison)
+			.ok_or_else(|| format!("'{comparison}' is not one of ==, !=, < or >"))?;
+		let value = parse_value(value)?;
+		Ok(Self {
+			cell,
+			comparison,
+			value,
+		})
+	}
+
+	pub fn matches(&self, interpreter: &BFInterpreter) -> bool {
+		let index = self.cell.unwrap_or(interpreter.mem_ptr);
+		let cell = interpreter.memory.get(index).unwrap_or(0);
+		self.comparison.compare(cell, self.value)
+	}
+}
+
+impl Display for CellCondition {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.cell {
+			Some(index) => write!(f, "{index}")?,
+			None => write!(f, "cell")?,
+		}
+		write!(f, " {} {}", self.comparison, value_text(self.value))
+	}
+}
+
+impl Display for MemoryWatcher {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		if self.condition.is_pointer() {
+			write!(f, "#{} ptr {}", self.id, self.condition)?;
+		} else if self.condition.is_output() {
+			write!(f, "#{} {}", self.id, self.condition)?;
+		} else {
+			write!(f, "#{} cell {} {}", self.id, self.index, self.condition)?;
+		}
+		if self.log {
+			write!(f, " (log)")?;
+		}
+		Ok(())
+	}
+}
+
+impl WatchCondition {
+	/// Parses `value` or a comparison directly followed by a value, like `>100` or `=='\n'`,
+	/// with values written like `parse_value` takes them
+	pub fn parse(text: &str) -> Result<Self, String> {
+		let split = text
+			.find(|c| !matches!(c, '=' | '!' | '<' | '>'))
+			.ok_or("a value is missing after the comparison")?;
+		let (comparison, value) = text.split_at(split);
+		let comparison = match comparison {
+			"" => Comparison::Equal,
+			comparison => Comparison::parse(comparison)
+				.ok_or_else(|| format!("'{comparison}' is not one of ==, !=, < or >"))?,
+		};
+		Ok(Self::Compare(comparison, parse_value(value)?))
+	}
+
+	/// Whether this watches the memory pointer instead of a cell
+	pub fn is_pointer(&self) -> bool {
+		matches!(self, Self::Pointer(..) | Self::PointerOutside(..))
+	}
+
+	/// Whether this watches the output instead of a cell
+	pub fn is_output(&self) -> bool {
+		matches!(self, Self::OutputLength(_) | Self::OutputByte(..))
+	}
+}
+
+impl Display for WatchCondition {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			WatchCondition::Changed { .. } => write!(f, "changes"),
+			WatchCondition::Compare(comparison, value) => {
+				write!(f, "{comparison}{}", value_text(*value))
+			}
+			WatchCondition::Pointer(comparison, index) => write!(f, "{comparison}{index}"),
+			WatchCondition::PointerOutside(start, end) => write!(f, "leaves {start} to {end}"),
+			WatchCondition::OutputLength(len) => write!(f, "outlen {len}"),
+			WatchCondition::OutputByte(byte, count) => {
+				write!(f, "outchar '{}' {count}", byte.escape_ascii())
+			}
+		}
+	}
+}
+
+impl Comparison {
+	pub fn parse(text: &str) -> Option<Self> {
+		match text {
+			"==" => Some(Self::Equal),
+			"!=" => Some(Self::NotEqual),
+			"<" => Some(Self::Less),
+			">" => Some(Self::Greater),
+			_ => None,
+		}
+	}
+
+	pub fn compare<T: PartialOrd>(self, left: T, right: T) -> bool {
+		match self {
+			Comparison::Equal => left == right,
+			Comparison::NotEqual => left != right,
+			Comparison::Less => left < right,
+			Comparison::Greater => left > right,
+		}
+	}
+}
+
+impl Display for Comparison {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Comparison::Equal => write!(f, "=="),
+			Comparison::NotEqual => write!(f, "!="),
+			Comparison::Less => write!(f, "<"),
+			Comparison::Greater => write!(f, ">"),
+		}
+	}
+}
+
+impl CellSize {
+	pub fn parse(text: &str) -> Option<Self> {
+		match text {
+			"8" => Some(Self::Bits8),
+			"16" => Some(Self::Bits16),
+			"32" => Some(Self::Bits32),
+			_ => None,
+		}
+	}
+
+	pub fn bits(self) -> u32 {
+		match self {
+			CellSize::Bits8 => 8,
+			CellSize::Bits16 => 16,
+			CellSize::Bits32 => 32,
+		}
+	}
+
+	/// The largest value a cell can hold, also used as the mask for wrapping
+	pub fn max(self) -> u32 {
+		u32::MAX >> (32 - self.bits())
+	}
+}
+
+impl TapeMode {
+	pub fn parse(text: &str) -> Option<Self> {
+		match text {
+			"error" => Some(Self::Error),
+			"wrap" => Some(Self::Wrap),
+			_ => None,
+		}
+	}
+}
+
+impl EofBehavior {
+	pub fn parse(text: &str) -> Option<Self> {
+		match text {
+			"0" => Some(Self::Zero),
+			"255" => Some(Self::Max),
+			"unchanged" => Some(Self::Unchanged),
+			_ => None,
+		}
+	}
+}
+
+impl Display for EofBehavior {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			EofBehavior::Zero => write!(f, "0"),
+			EofBehavior::Max => write!(f, "255"),
+			EofBehavior::Unchanged => write!(f, "unchanged"),
+		}
+	}
+}
+
+impl Display for Command {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Command::Clear => return write!(f, "[-]"),
+			Command::Assert(value) => return write!(f, "{{={value}}}"),
+			_ => (),
+		}
+		write!(
+			f,
+			"{}",
+			match self {
+				Command::Inc => '+',
+				Command::Dec => '-',
+				Command::Right => '>',
+				Command::Left => '<',
+				Command::Read => ',',
+				Command::Write => '.',
+				Command::BeginLoop(_) => '[',
+				Command::EndLoop(_) => ']',
+				Command::Clear | Command::Assert(_) => unreachable!(),
+				Command::Break => '!',
+				Command::End => ' ',
+			}
+		)
+	}
+}
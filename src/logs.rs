@@ -0,0 +1,62 @@
+//! Logpoints: breakpoints and watchers that note when they trigger instead of stopping the program.
+
+use std::{collections::BTreeMap, fmt::Display, mem};
+
+use crate::BFInterpreter;
+
+/// Messages kept per logpoint until they are taken, later ones are only counted
+pub const LOG_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogSource {
+	Breakpoint(usize),
+	Watcher(usize),
+}
+
+impl Display for LogSource {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			LogSource::Breakpoint(id) => write!(f, "breakpoint #{id}"),
+			LogSource::Watcher(id) => write!(f, "watcher #{id}"),
+		}
+	}
+}
+
+/// One time a logpoint triggered
+#[derive(Debug, Clone, Copy)]
+pub struct LogMessage {
+	pub source: LogSource,
+	pub step: usize,
+	pub program_ptr: usize,
+	/// The watched cell, or the current cell for a breakpoint
+	pub cell: usize,
+	pub value: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct Logs {
+	pub messages: Vec<LogMessage>,
+	/// How many times each logpoint triggered, including the messages that were not kept
+	pub counts: BTreeMap<LogSource, usize>,
+}
+
+impl BFInterpreter {
+	/// What the logpoints noted since the last time this was called
+	pub fn take_logs(&mut self) -> Logs {
+		mem::take(&mut self.logs)
+	}
+
+	pub(crate) fn log(&mut self, source: LogSource, cell: usize) {
+		let count = self.logs.counts.entry(source).or_default();
+		*count += 1;
+		if *count <= LOG_LIMIT {
+			self.logs.messages.push(LogMessage {
+				source,
+				step: self.steps,
+				program_ptr: self.program_ptr,
+				cell,
+				value: self.memory[cell],
+			});
+		}
+	}
+}
@@ -0,0 +1,72 @@
+//! Keeping track of which loops are running and how many times they have repeated.
+
+use serde::{Deserialize, Serialize};
+
+use crate::BFInterpreter;
+
+/// One run of a loop, from entering it with a nonzero cell until it exits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoopActivation {
+	/// Program index of the `[`
+	pub start: usize,
+	/// The step count when the loop was entered
+	pub entry_step: usize,
+	/// How many times the body has started in this run of the loop
+	pub iterations: usize,
+	/// The cell the loop was tested on when it was entered
+	pub cell: usize,
+}
+
+/// How one instruction changed the loop stack, so it can be undone
+#[derive(Debug, Default)]
+pub(crate) enum LoopChange {
+	#[default]
+	None,
+	Entered,
+	Repeated,
+	Exited(Box<LoopActivation>),
+}
+
+impl BFInterpreter {
+	/// Called when the `[` at `start` enters its body
+	pub(crate) fn enter_loop(&mut self, start: usize) -> LoopChange {
+		self.loop_stack.push(LoopActivation {
+			start,
+			entry_step: self.steps,
+			iterations: 1,
+			cell: self.mem_ptr,
+		});
+		LoopChange::Entered
+	}
+
+	/// Called when the `]` of the loop starting at `start` jumps back
+	pub(crate) fn repeat_loop(&mut self, start: usize) -> LoopChange {
+		match self.loop_stack.last_mut() {
+			Some(activation) if activation.start == start => {
+				activation.iterations += 1;
+				LoopChange::Repeated
+			}
+			// entered in some way that skipped the `[`, like `skip`
+			_ => self.enter_loop(start),
+		}
+	}
+
+	/// Called when the `]` of the loop starting at `start` falls through
+	pub(crate) fn exit_loop(&mut self, start: usize) -> LoopChange {
+		match self.loop_stack.last() {
+			Some(activation) if activation.start == start => {
+				LoopChange::Exited(Box::new(self.loop_stack.pop().unwrap()))
+			}
+			_ => LoopChange::None,
+		}
+	}
+
+	pub(crate) fn undo_loop_change(&mut self, change: LoopChange) {
+		match change {
+			LoopChange::None => (),
+			LoopChange::Entered => _ = self.loop_stack.pop(),
+			LoopChange::Repeated => self.loop_stack.last_mut().unwrap().iterations -= 1,
+			LoopChange::Exited(activation) => self.loop_stack.push(*activation),
+		}
+	}
+}
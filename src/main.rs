@@ -1,343 +1,3301 @@
-use std::{env, fmt::Display, fs, io::stdin, process::exit};
-
-use owo_colors::OwoColorize;
-
-const HELP_TEXT:&str = r#"HELP:
-Code breakpoints:
-    The ! character will be treated as a breakpoint, stopping execution
-
-[brackets] are optional parameters
-Command list:
-- step [n]
-    Step the progam n times (this is the default behavior when pressing enter with no command)
-- run
-    Run the program until the end
-- watch <cell> <value>
-    Create a breakpoint. Execution will stop when <cell> has <value>
-- quit | q | exit
-    Exit the debugger
-"#;
-
-#[derive(Debug)]
-struct BFInterpreter {
-	memory: Vec<u8>,
-	mem_ptr: usize,
-	program: Vec<DebugCommand>,
-	program_ptr: usize,
-	output: Vec<u8>,
-	input: Vec<u8>,
-	input_ptr: usize,
-	state: State,
-	steps: usize,
-	watchers: Vec<MemoryWatcher>,
-}
-
-#[derive(Debug)]
-struct MemoryWatcher {
-	index: usize,
-	value: u8,
-}
-
-#[derive(Debug, Default, PartialEq)]
-enum State {
-	#[default]
-	Running,
-	TooFarLeft,
-	EndOfProgram,
-	StoppedOnMemoryValue,
-	BreakPointHit,
+use std::{
+	cell::Cell,
+	cmp::Ordering,
+	collections::{BTreeMap, BTreeSet},
+	env,
+	fs::{self, File, OpenOptions},
+	io::{self, stdin, stdout, BufRead, IsTerminal, Read, Write},
+	ops::ControlFlow,
+	path::PathBuf,
+	process::exit,
+	rc::Rc,
+	sync::{
+		atomic::{self, AtomicBool},
+		Arc,
+	},
+	time::{Duration, Instant},
+};
+
+use brainfuck::{
+	end_location, is_empty_program, optimize_clear_loops, parse_files, parse_labels, parse_partial,
+	parse_value, program_hash, strict_warnings, strip_asserts, BFInterpreter, BreakTarget,
+	Breakpoint, CellCondition, CellSize, Command, Comparison, DebugCommand, EofBehavior, Language,
+	OutputStream, ParseError, ParseErrorKind, PartialProgram, ProgressReport, Snapshot, State,
+	StopReason, Tape, TapeMode, WatchCondition, LOG_LIMIT,
+};
+use expect::compare_output;
+use options::{Input, Options, Strict};
+use owo_colors::AnsiColors;
+use rustyline::{error::ReadlineError, DefaultEditor};
+use session::Session;
+use style::{ColorChoice, Paint};
+use view::{Expect, View};
+
+#[cfg(unix)]
+mod animate;
+mod commands;
+mod dap;
+mod expect;
+mod options;
+mod session;
+mod style;
+#[cfg(unix)]
+mod tui;
+mod view;
+
+const DEFAULT_MEM_WIDTH: usize = 32;
+const DEFAULT_CODE_WIDTH: usize = 80;
+const DEFAULT_OUTPUT_LIMIT: usize = 256;
+/// Matches listed by `find` before the rest are only counted
+const FIND_LIMIT: usize = 20;
+/// Changed cells listed by `diff` before the rest are only counted
+const DIFF_LIMIT: usize = 40;
+/// How often the progress line of a long `run` is updated
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+/// Commands that take at least this long show how long they took and how fast they ran
+const TIMING_THRESHOLD: Duration = Duration::from_millis(100);
+/// Command history file, in the home directory
+const HISTORY_FILE: &str = ".bf-debugger-history";
+/// Starts the line of a recorded session that `replay` checks against the loaded program
+const PROGRAM_HASH_HEADER: &str = "# program hash: ";
+
+/// The interpreter together with the state of the user interface around it
+struct Debugger {
+	interpreter: BFInterpreter,
+	/// The files the program was parsed from, shown around the current instruction
+	sources: Vec<SourceFile>,
+	program_text: ProgramText,
+	/// Where the program input came from, shown by `info`
+	input_source: String,
+	/// Whether clear loops were optimized when loading the program
+	optimize: bool,
+	/// Whether `{=N}` asserts are kept when loading the program, off with `--no-asserts`
+	asserts: bool,
+	/// Number of cells shown around the memory pointer
+	mem_width: usize,
+	mem_view: MemView,
+	/// Number of instructions shown around the program pointer
+	code_width: usize,
+	output_view: OutputView,
+	/// Number of output bytes shown after every step
+	output_limit: usize,
+	snapshots: BTreeMap<String, Snapshot>,
+	/// Whether cells that changed since the last prompt are highlighted
+	highlight_changes: bool,
+	/// Whether brackets are colored by how deeply they are nested
+	rainbow: bool,
+	/// Whether the screen is cleared before each command, so its messages and the view replace the last ones
+	redraw: bool,
+	/// Whether the command prompt shows the state, steps and source location
+	prompt: bool,
+	/// `--strict`, also checked when reloading
+	strict: Strict,
+	/// The memory as it was when the prompt was last shown
+	shown_memory: Tape,
+	/// The step count when the prompt was last shown
+	shown_steps: usize,
+	/// How long the last command took, and how many steps it ran
+	command_time: Option<(Duration, usize)>,
+	/// Names given to memory cells, usable as `$name` wherever a cell index is expected
+	labels: BTreeMap<usize, String>,
+	/// Names defined with `alias`, and the commands they run
+	aliases: BTreeMap<String, String>,
+	/// Whether `run` prints output as it is written instead of only at the next prompt
+	stream: bool,
+	/// A file and its contents that the output is compared against once the program ends
+	expected_output: Option<(String, Vec<u8>)>,
+	/// Show the state as a line of JSON instead of rendering it, for other programs to read
+	json: bool,
+	/// The file that `record session` appends each command to
+	session_recording: Option<(String, File)>,
+	/// Set when started without a source file, for code typed with `code`
+	scratchpad: Option<Scratchpad>,
+}
+
+/// The code typed with `code`, kept as the text of the only source file
+#[derive(Default)]
+struct Scratchpad {
+	/// Line and column of the first `[` still waiting for its `]`. The code from there on
+	/// is only added to the program once every bracket in it is closed
+	open: Option<(usize, usize)>,
+}
+
+/// One of the files the program was parsed from
+#[derive(Clone)]
+struct SourceFile {
+	/// The path as it was given, shown in locations when there are several files
+	name: String,
+	/// Absolute path, for reloading and saving sessions
+	path: String,
+	text: String,
+}
+
+impl SourceFile {
+	fn read(name: &str) -> Result<Self, String> {
+		let text = fs::read_to_string(name).map_err(|err| format!("{name}: {err}"))?;
+		let path = fs::canonicalize(name)
+			.map(|path| path.to_string_lossy().into_owned())
+			.unwrap_or(name.into());
+		Ok(Self {
+			name: name.into(),
+			path,
+			text,
+		})
+	}
+
+	/// Code typed at the prompt, parsed like a file of its own
+	fn snippet(text: String) -> Self {
+		Self {
+			name: String::new(),
+			path: String::new(),
+			text,
+		}
+	}
+
+	/// The empty file that code typed with `code` is added to
+	fn scratchpad() -> Self {
+		Self {
+			name: "scratchpad".into(),
+			path: String::new(),
+			text: String::new(),
+		}
+	}
+
+	/// The line with this 1-based number
+	fn line(&self, number: usize) -> Option<&str> {
+		self.text.lines().nth(number.checked_sub(1)?)
+	}
+}
+
+/// `line:column` of an instruction, after its file name when the program has several files
+fn location(c: &DebugCommand, sources: &[SourceFile]) -> String {
+	match sources.len() {
+		1 => format!("{}:{}", c.line_number, c.column),
+		_ => format!("{}:{}:{}", sources[c.file].name, c.line_number, c.column),
+	}
+}
+
+/// The `@index:name` annotations of every file
+fn source_labels(sources: &[SourceFile]) -> Vec<(usize, String)> {
+	sources
+		.iter()
+		.flat_map(|file| parse_labels(&file.text))
+		.collect()
+}
+
+/// The program as plain text, made once so showing it only has to color a few instructions
+struct ProgramText {
+	text: String,
+	/// Where each instruction starts in `text`, followed by the end of the text
+	offsets: Vec<usize>,
+	/// Number of loops open before each instruction
+	depths: Vec<usize>,
+}
+
+/// The words that can end a `break` command. Watchers only take `log`
+#[derive(Debug, Default, Clone, Copy)]
+struct BreakModifiers {
+	log: bool,
+	once: bool,
+	ignore: usize,
 }
 
-#[derive(Debug)]
-struct DebugCommand {
-	command: Command,
-	line_number: usize,
-	column: usize,
+impl ProgramText {
+	fn new(program: &[DebugCommand]) -> Self {
+		let mut text = String::new();
+		let mut offsets = Vec::with_capacity(program.len() + 1);
+		let mut depths = Vec::with_capacity(program.len());
+		let mut depth = 0usize;
+		for c in program {
+			offsets.push(text.len());
+			depths.push(depth);
+			match c.command {
+				Command::BeginLoop(_) => depth += 1,
+				Command::EndLoop(_) => depth = depth.saturating_sub(1),
+				_ => (),
+			}
+			text.push_str(&c.command.to_string());
+		}
+		offsets.push(text.len());
+		Self {
+			text,
+			offsets,
+			depths,
+		}
+	}
+
+	/// The text of the instructions in `start..end`
+	fn range(&self, start: usize, end: usize) -> &str {
+		&self.text[self.offsets[start]..self.offsets[end]]
+	}
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Command {
-	Inc,
+/// How memory cells are rendered
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum MemView {
+	#[default]
 	Dec,
-	Right,
-	Left,
-	Read,
-	Write,
-	BeginLoop(usize),
-	EndLoop(usize),
-	Break,
-	End,
+	Hex,
+	Ascii,
+	Mixed,
+}
+
+/// How the program output is rendered
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum OutputView {
+	/// Written to the terminal as is
+	Raw,
+	/// Control characters shown as escapes, so they can't mess up the display
+	#[default]
+	Escaped,
+	Hex,
 }
 
 fn main() {
-	let args: Vec<_> = env::args().collect();
-	if args.len() <= 1 {
-		println!("usage: brainfuck <source_file> <input_file>");
+	let options = Options::parse(env::args().skip(1)).unwrap_or_else(|err| {
+		style::init(ColorChoice::Auto);
+		println!("{}", err.red());
+		println!("{}\nsee --help for all options", options::USAGE);
+		exit(1);
+	});
+	style::init(options.color);
+	if options.help {
+		println!("{}\n\n{}", options::USAGE, options::HELP);
 		exit(0);
 	}
-	let filename = &args[1];
-	let source = fs::read_to_string(filename).unwrap_or_else(|err| {
-		println!("Error reading file: {err}");
-		exit(1);
+	if options.version {
+		println!("brainfuck {}", env!("CARGO_PKG_VERSION"));
+		exit(0);
+	}
+	if options.dap {
+		if let Err(err) = dap::serve(&options) {
+			eprintln!("{err}");
+			exit(1);
+		}
+		exit(0);
+	}
+	let session = options.session_file.as_ref().map(|path| {
+		Session::load(path).unwrap_or_else(|err| {
+			println!("{}", err.red());
+			exit(1);
+		})
 	});
-	let input_data = args
-		.get(2)
-		.map(|path| {
-			fs::read(path).unwrap_or_else(|err| {
-				println!("Error reading file: {err}");
-				exit(1);
+	let (mut optimize, mut eof_behavior, mut numeric_io) =
+		(options.optimize, options.eof_behavior, options.numeric_io);
+	let (mut cell_size, mut tape_size, mut tape_mode) =
+		(options.cell_size, options.tape_size, options.tape_mode);
+	if let Some(session) = &session {
+		optimize = session.optimize;
+		eof_behavior = session.eof_behavior;
+		numeric_io = session.numeric_io;
+		cell_size = session.cell_size;
+		(tape_size, tape_mode) = (session.tape_size, session.tape_mode);
+	}
+	let filenames = match &session {
+		Some(session) => session.source_paths.clone(),
+		None => options.source_files.clone(),
+	};
+	let scratchpad = filenames.is_empty();
+	if scratchpad && (options.run_only || options.bench.is_some() || options.emit.is_some()) {
+		eprintln!("{}", "no source file given".red());
+		eprintln!("{}\nsee --help for all options", options::USAGE);
+		exit(1);
+	}
+	let sources: Vec<_> = match scratchpad {
+		true => vec![SourceFile::scratchpad()],
+		false => filenames
+			.iter()
+			.map(|name| {
+				SourceFile::read(name).unwrap_or_else(|err| {
+					println!("Error reading file: {err}");
+					exit(1);
+				})
 			})
-		})
-		.unwrap_or_default();
+			.collect(),
+	};
+	let input_source = match (&options.session_file, &options.input) {
+		(Some(path), _) => format!("session {path}"),
+		(None, Input::File(path)) => path.clone(),
+		(None, Input::Inline(bytes)) => format!("-i ({} bytes)", bytes.len()),
+		(None, Input::None) => "none".into(),
+	};
+	let input_data = match (&session, options.input) {
+		(Some(session), _) => session.input.clone(),
+		(None, Input::File(path)) => fs::read(path).unwrap_or_else(|err| {
+			println!("Error reading file: {err}");
+			exit(1);
+		}),
+		(None, Input::Inline(bytes)) => bytes,
+		// `--run` reads piped input like any other interpreter
+		(None, Input::None) if options.run_only && !stdin().is_terminal() => {
+			let mut bytes = Vec::new();
+			stdin()
+				.lock()
+				.read_to_end(&mut bytes)
+				.unwrap_or_else(|err| {
+					println!("Error reading stdin: {err}");
+					exit(1);
+				});
+			bytes
+		}
+		(None, Input::None) => Vec::new(),
+	};
 
-	let program = parse(&source);
+	let mut program = parse_sources(&sources).unwrap_or_else(|errors| {
+		println!("{errors}");
+		exit(1);
+	});
+	if !check_strict(&sources, options.strict) {
+		eprintln!("{}", "refusing to run with --strict=error".red());
+		exit(1);
+	}
+	if !scratchpad && is_empty_program(&program) {
+		if options.run_only || options.bench.is_some() || options.emit.is_some() {
+			eprintln!("{}", "source contains no brainfuck commands".red());
+			exit(1);
+		}
+		println!(
+			"{}",
+			"source contains no brainfuck commands, only eval can run anything".yellow()
+		);
+	}
+	if !options.asserts {
+		program = strip_asserts(program);
+	}
+	if optimize {
+		program = optimize_clear_loops(program);
+	}
 
-	// dbg!(&code_dbg);
 	let mut interpreter = BFInterpreter::new(program, input_data);
+	interpreter.eof_behavior = eof_behavior;
+	interpreter.numeric_io = numeric_io;
+	interpreter.transfer_loops = optimize;
+	if options.profile {
+		interpreter.start_profile();
+	}
+	interpreter.max_steps = options.max_steps;
+	interpreter.memory_limit = options.max_memory;
+	interpreter.loop_detect = options.loop_detect;
+	interpreter.ignore_code_breaks = options.ignore_breaks.unwrap_or(options.run_only);
+	interpreter.set_cell_size(cell_size);
+	interpreter
+		.set_tape(tape_size, tape_mode)
+		.unwrap_or_else(|err| {
+			println!("{}", err.red());
+			exit(1);
+		});
+	if let Some(path) = &options.mem_init {
+		let cells = fs::read(path).unwrap_or_else(|err| {
+			println!("Error reading file: {err}");
+			exit(1);
+		});
+		if let Err(err) = interpreter.set_initial_memory(cells.into_iter().map(u32::from).collect())
+		{
+			println!("{}", format!("--mem-init: {err}").red());
+			exit(1);
+		}
+	}
+	let expected_output = options.expect.map(|path| {
+		let expected = fs::read(&path).unwrap_or_else(|err| {
+			println!("Error reading file: {err}");
+			exit(1);
+		});
+		(path, expected)
+	});
+	if let Some((language, path)) = &options.emit {
+		if let Err(err) = emit(&interpreter, *language, path) {
+			eprintln!("{}", err.red());
+			exit(1);
+		}
+		exit(0);
+	}
+	let interrupt = Arc::new(AtomicBool::new(false));
+	let flag = interrupt.clone();
+	if let Err(err) = ctrlc::set_handler(move || flag.store(true, atomic::Ordering::Relaxed)) {
+		eprintln!(
+			"{}",
+			format!("Ctrl-C will not stop a running program: {err}").red()
+		);
+	}
+	interpreter.interrupt = Some(interrupt.clone());
+	if let Some(runs) = options.bench {
+		if let Err(err) = bench(&interpreter, runs, expected_output.as_ref()) {
+			eprintln!("{}", err.red());
+			exit(1);
+		}
+		exit(0);
+	}
+	if options.run_only {
+		run_non_interactive(interpreter, expected_output, &sources);
+	}
+	let labels = source_labels(&sources);
+	let mut debugger = Debugger::new(interpreter, sources, optimize);
+	debugger.expected_output = expected_output;
+	debugger.input_source = input_source;
+	debugger.json = options.json;
+	debugger.strict = options.strict;
+	debugger.asserts = options.asserts;
+	if scratchpad {
+		debugger.scratchpad = Some(Scratchpad::default());
+	}
+	for (index, name) in labels {
+		// the names are already known to be valid
+		_ = debugger.add_label(index, &name);
+	}
+	if let Some(session) = session {
+		session.apply(&mut debugger).unwrap_or_else(|err| {
+			println!("{}", err.red());
+			exit(1);
+		});
+	}
+	if let Some(path) = options.script {
+		run_script(&mut debugger, &path);
+	}
+	if options.tui {
+		#[cfg(unix)]
+		if let Err(err) = tui::run(&mut debugger, &interrupt) {
+			println!("{}", err.red());
+			exit(1);
+		}
+		debugger.stop_trace();
+		return;
+	}
+	let mut editor = DefaultEditor::new().unwrap_or_else(|err| {
+		println!("could not set up the terminal: {err}");
+		exit(1);
+	});
+	let history_path = env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+	if let Some(path) = &history_path {
+		// there is no history yet the first time
+		_ = editor.load_history(path);
+	}
 	loop {
-		interpreter.show();
-		let mut action = String::new();
-		stdin().read_line(&mut action).unwrap();
-		let action: Vec<_> = action.trim().split_ascii_whitespace().collect();
-		match action.as_slice() {
-			["help"] => println!("{}", HELP_TEXT.green()),
-			["step"] => interpreter.step_once(),
-			["step", num] => _ = num.parse().map(|n| interpreter.step(n)),
-			["watch"] => println!("usage: watch [memory index] [value]"),
-			["watch", _] => println!("usage: watch [memory index] [value]"),
-			["watch", index, value] => {
-				if let (Ok(index), Ok(value)) = (index.parse(), value.parse()) {
-					interpreter.add_watch(index, value)
-				} else {
-					println!(
-						"{}",
-						"index and value must be valid usize and u8 integers".red()
-					);
-				}
+		debugger.show();
+		let action = match editor.readline(&debugger.prompt()) {
+			Ok(line) => line,
+			Err(ReadlineError::Interrupted) => continue,
+			Err(ReadlineError::Eof) => break,
+			Err(err) => {
+				debugger.show_error(&format!("could not read command: {err}"));
+				break;
+			}
+		};
+		if !action.trim().is_empty() && !debugger.json {
+			_ = editor.add_history_entry(action.as_str());
+		}
+		// a Ctrl-C from before the command started should not stop it
+		interrupt.store(false, atomic::Ordering::Relaxed);
+		debugger.clear_screen();
+		let (start, start_steps) = (Instant::now(), debugger.interpreter.steps);
+		match execute_command(&mut debugger, &action) {
+			Ok(ControlFlow::Break(())) => break,
+			Ok(ControlFlow::Continue(())) if !controls_recording(&action) => {
+				debugger.record_line(&action)
 			}
-			["run"] => interpreter.run(),
-			["q" | "exit" | "quit"] => break,
-			[] => interpreter.step_once(),
-			_ => println!("{}", "unrecognised command".red()),
+			Ok(ControlFlow::Continue(())) => (),
+			Err(err) => debugger.show_error(&err),
 		}
+		let steps = debugger.interpreter.steps.saturating_sub(start_steps);
+		debugger.command_time = Some((start.elapsed(), steps));
+		read_program_input(&mut debugger);
+		debugger.flush_trace();
 	}
+	if let Some(path) = &history_path {
+		if let Err(err) = editor.save_history(path) {
+			println!("{}", format!("could not save command history: {err}").red());
+		}
+	}
+	debugger.stop_trace();
 }
 
-impl BFInterpreter {
-	fn new(program: Vec<DebugCommand>, input: Vec<u8>) -> Self {
-		Self {
-			memory: vec![0],
-			mem_ptr: 0,
-			program,
-			program_ptr: 0,
-			output: Vec::new(),
-			input,
-			input_ptr: 0,
-			state: State::Running,
-			steps: 0,
-			watchers: Vec::new(),
-		}
-	}
-
-	fn show(&self) {
-		for (index, c) in self.program.iter().enumerate() {
-			if index == self.program_ptr {
-				print!("{}", c.command.on_cyan());
-			} else {
-				print!("{}", c.command);
-			}
+/// Runs the debugger commands in a script file, exiting if the script quits
+fn run_script(debugger: &mut Debugger, path: &str) {
+	match replay(debugger, path) {
+		Ok(ControlFlow::Break(())) => {
+			debugger.stop_trace();
+			exit(0);
 		}
-		println!();
-		println!(
-			"source (line:column): {}:{}",
-			self.program[self.program_ptr].line_number, self.program[self.program_ptr].column
-		);
-		print!("mem: ");
-		for (index, cell) in self.memory.iter().enumerate() {
-			if index == self.mem_ptr {
-				print!("{:3} ", cell.on_red());
-			} else {
-				print!("{:3} ", cell);
-			}
+		Ok(ControlFlow::Continue(())) => (),
+		Err(err) => {
+			println!("{}", err.red());
+			exit(1);
 		}
-		println!();
-		print!("ind: ");
-		for i in 0..self.memory.len() {
-			if i == self.mem_ptr {
-				print!("{:3} ", i.on_red());
-			} else {
-				print!("{:3} ", i);
+	}
+}
+
+/// Runs the debugger commands in a file, warning if it was recorded with a different program
+fn replay(debugger: &mut Debugger, path: &str) -> Result<ControlFlow<()>, String> {
+	let script = fs::read_to_string(path).map_err(|err| format!("could not read {path}: {err}"))?;
+	let lines: Vec<_> = script.lines().collect();
+	for (line_number, line) in lines.iter().enumerate() {
+		if let Some(hash) = line.strip_prefix(PROGRAM_HASH_HEADER) {
+			if hash.trim() != format!("{:016x}", program_hash(&debugger.interpreter.program)) {
+				println!(
+					"{}",
+					format!(
+						"warning: {path} was recorded with a different program, replaying it may not reproduce the same steps"
+					)
+					.yellow()
+				);
 			}
+			continue;
+		}
+		if line.trim_start().starts_with('#') {
+			continue;
+		}
+		match execute_command(debugger, line) {
+			Ok(ControlFlow::Break(())) => return Ok(ControlFlow::Break(())),
+			Ok(ControlFlow::Continue(())) => (),
+			Err(err) => debugger.show_error(&format!("{path}:{}: {err}", line_number + 1)),
+		}
+		// recorded program input is on the next line, instead of being typed again
+		if !lines
+			.get(line_number + 1)
+			.is_some_and(|next| next.starts_with("input type"))
+		{
+			read_program_input(debugger);
 		}
-		println!();
-		println!("{:?}. steps: {}", self.state, self.steps);
-		println!("output: {}", String::from_utf8_lossy(&self.output).blue());
-		// println!("input: {}", String::from_utf8_lossy(&self.input));
 	}
+	Ok(ControlFlow::Continue(()))
+}
 
-	fn add_watch(&mut self, index: usize, value: u8) {
-		self.watchers.push(MemoryWatcher { index, value });
-	}
+/// `record session` and `record stop` are left out of the recording they control
+fn controls_recording(line: &str) -> bool {
+	matches!(
+		line.split_ascii_whitespace().collect::<Vec<_>>()[..],
+		["record", "session", ..] | ["record", "stop"]
+	)
+}
 
-	fn step_once(&mut self) {
-		self.state = State::Running;
-		self.step_internal();
+/// Runs the `;` separated commands on a line in order, stopping at the first error
+fn execute_command(debugger: &mut Debugger, line: &str) -> Result<ControlFlow<()>, String> {
+	if line.trim().is_empty() {
+		let result = execute_single(debugger, line);
+		debugger.show_logs();
+		return result;
 	}
+	let result = execute_line(debugger, line, &mut Vec::new());
+	debugger.show_logs();
+	result
+}
 
-	fn step(&mut self, num: usize) {
-		for _ in 0..num {
-			self.step_internal();
-			if self.state != State::Running {
-				break;
+/// `expanding` holds the aliases that are being run, to catch an alias that uses itself
+fn execute_line(
+	debugger: &mut Debugger,
+	line: &str,
+	expanding: &mut Vec<String>,
+) -> Result<ControlFlow<()>, String> {
+	let mut rest = Some(line);
+	while let Some(text) = rest {
+		// an alias definition keeps its `;` for when the alias is used
+		let (command, next) = if text.split_whitespace().next() == Some("alias") {
+			(text, None)
+		} else {
+			split_command(text)
+		};
+		rest = next;
+		let name = command.split_whitespace().next().unwrap_or("");
+		let flow = if let Some(expansion) = debugger.aliases.get(name).cloned() {
+			if expanding.iter().any(|a| a == name) {
+				return Err(format!(
+					"alias {name} uses itself: {} -> {name}",
+					expanding.join(" -> ")
+				));
 			}
+			expanding.push(name.to_string());
+			let flow = execute_line(
+				debugger,
+				&format!("{expansion} {}", rest_of_line(command, 1)),
+				expanding,
+			);
+			expanding.pop();
+			flow?
+		} else if command.trim().is_empty() {
+			continue;
+		} else {
+			execute_single(debugger, command)?
+		};
+		if flow.is_break() {
+			return Ok(flow);
 		}
 	}
+	Ok(ControlFlow::Continue(()))
+}
 
-	fn run(&mut self) {
-		while self.state == State::Running {
-			self.step_internal();
+/// The text up to the first `;` that is not inside quotes, and what comes after it
+fn split_command(text: &str) -> (&str, Option<&str>) {
+	let mut quoted = false;
+	for (i, c) in text.char_indices() {
+		match c {
+			'"' => quoted = !quoted,
+			';' if !quoted => return (&text[..i], Some(&text[i + 1..])),
+			_ => (),
 		}
 	}
+	(text, None)
+}
 
-	fn step_internal(&mut self) {
-		if self.program_ptr + 1 == self.program.len() {
-			self.state = State::EndOfProgram;
+fn execute_single(debugger: &mut Debugger, line: &str) -> Result<ControlFlow<()>, String> {
+	let mut action: Vec<_> = line.trim().split_ascii_whitespace().collect();
+	if let Some(name) = action.first_mut() {
+		*name = commands::resolve(name).ok_or(format!(
+			"unrecognised command {name}, type help for a list of commands"
+		))?;
+	}
+	// words at the end of `watch` and `break` that change how they stop
+	let mut modifiers = BreakModifiers::default();
+	let mut modifier_words = 0;
+	if matches!(action.first(), Some(&("watch" | "break"))) {
+		loop {
+			match action.as_slice() {
+				[_, _, .., "log"] => modifiers.log = true,
+				[_, _, .., "once"] => modifiers.once = true,
+				[_, _, .., "ignore", count] => {
+					modifiers.ignore = count_arg("ignore", count)?;
+					action.pop();
+					modifier_words += 1;
+				}
+				_ => break,
+			}
+			action.pop();
+			modifier_words += 1;
 		}
-		if self.state != State::Running {
-			return;
+		if action[0] == "watch" && (modifiers.once || modifiers.ignore > 0) {
+			return Err("once and ignore only work with break".into());
+		}
+	}
+	let log = modifiers.log;
+	match action.as_slice() {
+		["help"] => println!("{}", commands::help_text().green()),
+		["help", name] => println!(
+			"{}",
+			commands::command_help(name).ok_or(format!("no command named {name}"))?
+		),
+		["step"] => debugger.interpreter.step_once(),
+		["step", num] => debugger.interpreter.step(count_arg("step", num)?),
+		["watch", "ptr", "range", start, end] => {
+			let (start, end) = (debugger.cell_index(start)?, debugger.cell_index(end)?);
+			debugger.add_watch(0, WatchCondition::PointerOutside(start, end), log)?;
+		}
+		["watch", "ptr", condition] => {
+			let condition = debugger.pointer_condition(condition)?;
+			debugger.add_watch(0, condition, log)?;
+		}
+		["watch", "outlen", len] => {
+			let len = count_arg("watch outlen", len)?;
+			debugger.add_watch(0, WatchCondition::OutputLength(len), log)?;
+		}
+		["watch", "outchar", byte, rest @ ..] if rest.len() <= 1 => {
+			let [byte] = parse_output_bytes("watch outchar", byte)?[..] else {
+				return Err("watch outchar needs a single byte, use break output for text".into());
+			};
+			let count = match rest {
+				[count] => count_arg("watch outchar", count)?,
+				_ => 1,
+			};
+			debugger.add_watch(0, WatchCondition::OutputByte(byte, count), log)?;
+		}
+		["watch", index] => {
+			let index = debugger.cell_index(index)?;
+			let last = debugger.interpreter.memory.get(index).unwrap_or(0);
+			debugger.add_watch(index, WatchCondition::Changed { last }, log)?;
+		}
+		["watch", index, condition] => {
+			let index = debugger.cell_index(index)?;
+			let condition =
+				WatchCondition::parse(condition).map_err(|err| format!("watch: {err}"))?;
+			debugger.add_watch(index, condition, log)?;
+		}
+		["break", "step", step] => {
+			let step = number_arg("break step", step)?;
+			debugger.add_breakpoint(BreakTarget::Step(step), None, modifiers)?;
+		}
+		["break", "loop", limit] => {
+			let limit = number_arg("break loop", limit)?;
+			debugger.add_breakpoint(BreakTarget::Loop(limit), None, modifiers)?;
+		}
+		["break", "memsize", cells] => {
+			let cells = count_arg("break memsize", cells)?;
+			debugger.add_breakpoint(BreakTarget::MemorySize(cells), None, modifiers)?;
+		}
+		["break", "output", _, ..] => {
+			let text = without_last_words(rest_of_line(line, 2), modifier_words);
+			let bytes = parse_output_bytes("break output", text)?;
+			debugger.add_breakpoint(BreakTarget::Output(bytes), None, modifiers)?;
+		}
+		["break", location] => {
+			let index = debugger
+				.find_instruction(location)
+				.ok_or("no instruction at that location")?;
+			debugger.add_breakpoint(BreakTarget::Index(index), None, modifiers)?;
+		}
+		["break", location, "if", cell, comparison, value] => {
+			let index = debugger
+				.find_instruction(location)
+				.ok_or("no instruction at that location")?;
+			let cell = match *cell {
+				"cell" => "cell".to_string(),
+				index => debugger.cell_index(index)?.to_string(),
+			};
+			let condition = CellCondition::parse(&cell, comparison, value)
+				.map_err(|err| format!("break: {err}"))?;
+			debugger.add_breakpoint(BreakTarget::Index(index), Some(condition), modifiers)?;
 		}
-		let command = self.program[self.program_ptr].command;
-		match command {
-			Command::Inc => {
-				self.memory[self.mem_ptr] = self.memory[self.mem_ptr].wrapping_add(1);
-				self.update_watchers();
+		["breakpoints"] => debugger.list_breakpoints(),
+		["enable", what @ ..] => debugger.set_enabled(what, true)?,
+		["disable", what @ ..] => debugger.set_enabled(what, false)?,
+		["delete", id] => debugger
+			.interpreter
+			.delete_breakpoint(number_arg("delete", id)?)?,
+		["watches"] => debugger.list_watches(),
+		["unwatch", "all"] => debugger.interpreter.watchers.clear(),
+		["unwatch", id] => debugger
+			.interpreter
+			.remove_watch(number_arg("unwatch", id)?)?,
+		["next"] => debugger.interpreter.step_over(),
+		["finish"] => debugger.interpreter.finish(),
+		["stepline"] => debugger.interpreter.step_line(1),
+		["stepline", num] => debugger.interpreter.step_line(count_arg("stepline", num)?),
+		["match"] => debugger.show_match(debugger.interpreter.program_ptr),
+		["match", location] => {
+			let (file, line, column) = debugger
+				.parse_location(location)
+				.ok_or("location must be [file:]<line> or [file:]<line>:<column>")?;
+			let index = debugger.interpreter.program[..debugger.interpreter.program.len() - 1]
+				.iter()
+				.position(|c| c.is_at(file, line, column))
+				.ok_or(format!("no instruction at {location}"))?;
+			debugger.show_match(index);
+		}
+		["until", location] => {
+			let (file, line, column) = debugger
+				.parse_location(location)
+				.ok_or("location must be [file:]<line> or [file:]<line>:<column>")?;
+			debugger
+				.interpreter
+				.run_until_location(file, line, column)?;
+		}
+		["back"] => debugger.interpreter.back(1)?,
+		["back", num] => debugger.interpreter.back(count_arg("back", num)?)?,
+		["snapshot", name] => {
+			let snapshot = debugger.interpreter.snapshot();
+			println!(
+				"snapshot {name} at step {} ({})",
+				snapshot.steps,
+				format_size(snapshot.size())
+			);
+			debugger.snapshots.insert(name.to_string(), snapshot);
+		}
+		["restore", name] => {
+			let snapshot = debugger
+				.snapshots
+				.get(*name)
+				.ok_or(format!("no snapshot named {name}"))?;
+			debugger.interpreter.restore(snapshot);
+		}
+		["snapshots"] => debugger.list_snapshots(),
+		["diff", name] => debugger.show_diff(name)?,
+		["trace", "on", path] => debugger.start_trace(path, None)?,
+		["trace", "on", path, "last", count] => {
+			let count = count_arg("trace last", count)?;
+			debugger.start_trace(path, Some(count))?;
+		}
+		["trace", "off"] => debugger.stop_trace(),
+		["profile"] => match debugger.interpreter.profile() {
+			Some(counts) => print!(
+				"{}",
+				format_profile(&debugger.interpreter.program, counts, &debugger.sources)
+			),
+			None => return Err("profiling is off, start it with `profile on`".into()),
+		},
+		["profile", "on"] => debugger.interpreter.start_profile(),
+		["profile", "off"] => debugger.interpreter.stop_profile(),
+		["profile", "reset"] => debugger.interpreter.reset_profile(),
+		["coverage"] => print!(
+			"{}",
+			format_coverage(
+				&debugger.interpreter.program,
+				debugger.interpreter.coverage(),
+				&debugger.sources
+			)
+		),
+		["coverage", "reset"] => debugger.interpreter.reset_coverage(),
+		["find", "mem", _, ..] => {
+			let text = rest_of_line(line, 2);
+			let values: Vec<u32> = match text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+				Some(quoted) => unescape(quoted)?.into_iter().map(u32::from).collect(),
+				None => vec![parse_value(text).map_err(|err| format!("find: {err}"))?],
+			};
+			if values.is_empty() {
+				return Err("can not search for empty text".into());
 			}
-			Command::Dec => {
-				self.memory[self.mem_ptr] = self.memory[self.mem_ptr].wrapping_sub(1);
-				self.update_watchers();
+			debugger.find_in_memory(&values);
+		}
+		["find", "prog", pattern @ ..] if !pattern.is_empty() => {
+			debugger.find_in_program(&pattern.concat());
+		}
+		["code", _, ..] => {
+			debugger.append_code(rest_of_line(line, 1))?;
+			if let Some((line, column)) = debugger.scratchpad.as_ref().and_then(|s| s.open) {
+				println!(
+					"{}",
+					format!("the [ at {line}:{column} is waiting for its ], the code from there on is not run yet").yellow()
+				);
 			}
-			Command::Right => {
-				self.mem_ptr += 1;
-				if self.mem_ptr >= self.memory.len() {
-					self.memory.push(0);
-				}
+		}
+		["save", "program", path] => {
+			let Some(source) = debugger
+				.sources
+				.first()
+				.filter(|_| debugger.scratchpad.is_some())
+			else {
+				return Err(
+					"only the scratchpad can be saved, the program is already in its source files"
+						.into(),
+				);
+			};
+			fs::write(path, &source.text)
+				.map_err(|err| format!("could not write {path}: {err}"))?;
+			println!(
+				"wrote {} lines of code to {path}",
+				source.text.lines().count()
+			);
+		}
+		["eval", code @ ..] | ["eval!", code @ ..] => {
+			let code = code.concat();
+			let snippet = [SourceFile::snippet(code)];
+			let snippet = parse_sources(&snippet)?;
+			if snippet.iter().any(|c| matches!(c.command, Command::Break)) {
+				return Err("eval code can not contain breakpoints (!)".into());
 			}
-			Command::Left => {
-				if self.mem_ptr == 0 {
-					self.state = State::TooFarLeft;
-				} else {
-					self.mem_ptr -= 1;
-				}
+			let output = debugger.interpreter.eval(snippet, action[0] == "eval!")?;
+			if !output.is_empty() {
+				let text: String = output.iter().map(|&b| escape_byte(b)).collect();
+				println!("eval output: {}", text.blue());
 			}
-			Command::Read => {
-				if self.input_ptr < self.input.len() {
-					self.memory[self.mem_ptr] = self.input[self.input_ptr];
-					self.input_ptr += 1;
-				} else {
-					self.memory[self.mem_ptr] = 0;
-				}
+		}
+		["bench"] => bench(&debugger.interpreter, 1, debugger.expected_output.as_ref())?,
+		["emit", language, path] => {
+			let language = Language::parse(language).ok_or("language must be one of c or rust")?;
+			emit(&debugger.interpreter, language, path)?;
+		}
+		["bench", runs] => bench(
+			&debugger.interpreter,
+			count_arg("bench", runs)?,
+			debugger.expected_output.as_ref(),
+		)?,
+		["stats"] => print!("{}", format_stats(&debugger.interpreter)),
+		["dump", "mem", path, rest @ ..] => {
+			let (rest, text) = match rest {
+				[rest @ .., "--format", "text"] => (rest, true),
+				[rest @ .., "--format", "raw"] => (rest, false),
+				[.., "--format", _] => return Err("dump format must be raw or text".into()),
+				rest => (rest, false),
+			};
+			let (start, len) = match rest {
+				[] => (0, debugger.interpreter.memory.len()),
+				[start, len] => (debugger.cell_index(start)?, count_arg("dump mem", len)?),
+				_ => return Err(commands::usage("dump")),
+			};
+			debugger.dump_memory(path, start, len, text)?;
+		}
+		["load", "mem", path] | ["load", "mem", path, _] => {
+			let start = action.get(3).map_or(Ok(0), |i| debugger.cell_index(i))?;
+			let bytes = fs::read(path).map_err(|err| format!("could not read {path}: {err}"))?;
+			let cells: Vec<_> = bytes.into_iter().map(u32::from).collect();
+			debugger.interpreter.load_memory(start, &cells)?;
+			println!("loaded {} cells from {path} at cell {start}", cells.len());
+		}
+		["dump", "output", path] => debugger.dump_output(path)?,
+		["dump", "state", path] => debugger.dump_state(path)?,
+		["save-session", _] if debugger.scratchpad.is_some() => {
+			return Err("a session needs a source file, write the scratchpad to one with save program first".into());
+		}
+		["save-session", path] => {
+			Session::capture(debugger).save(path)?;
+			println!("session saved to {path}");
+		}
+		["set", "mem", index, value] => {
+			let index = debugger.cell_index(index)?;
+			let value = parse_value(value).map_err(|err| format!("set mem: {err}"))?;
+			debugger.interpreter.set_cell(index, value)?;
+		}
+		["set", "ptr", index] => {
+			let index = debugger.cell_index(index)?;
+			debugger.interpreter.set_mem_ptr(index)?;
+		}
+		["list"] => debugger.show_program(0, debugger.interpreter.program.len()),
+		["list", start] | ["list", start, _] => {
+			let start: usize = number_arg("list", start)?;
+			let count = action
+				.get(2)
+				.map_or(Ok(debugger.code_width), |c| count_arg("list", c))?;
+			let len = debugger.interpreter.program.len();
+			debugger.show_program(start.min(len), (start + count).min(len));
+		}
+		["set", "codewidth", num] => debugger.code_width = number_arg("set codewidth", num)?,
+		["mem", start] | ["mem", start, _] => {
+			let start = debugger.cell_index(start)?;
+			let count = action
+				.get(2)
+				.map_or(Ok(debugger.mem_width), |c| count_arg("mem", c))?;
+			let len = debugger.interpreter.memory.len();
+			if start >= len {
+				return Err(format!("cell {start} is outside the tape of {len} cells"));
 			}
-			Command::Write => self.output.push(self.memory[self.mem_ptr]),
-			Command::BeginLoop(end_of_loop) => {
-				if self.memory[self.mem_ptr] == 0 {
-					self.program_ptr = end_of_loop;
-				}
+			debugger.show_memory(start, count);
+		}
+		["set", "memwidth", num] => debugger.mem_width = number_arg("set memwidth", num)?,
+		["set", "memview", view] => {
+			debugger.mem_view =
+				MemView::parse(view).ok_or("memview must be one of dec, hex, ascii or mixed")?
+		}
+		["set", "output", view] => {
+			debugger.output_view =
+				OutputView::parse(view).ok_or("output must be one of raw, escaped or hex")?
+		}
+		["set", "outputlimit", num] => debugger.output_limit = number_arg("set outputlimit", num)?,
+		["output"] => println!(
+			"{}",
+			debugger.output_view.format(&debugger.interpreter.output)
+		),
+		["output", view] => {
+			let view =
+				OutputView::parse(view).ok_or("output must be one of raw, escaped or hex")?;
+			println!("{}", view.format(&debugger.interpreter.output));
+		}
+		["set", "history", num] => debugger
+			.interpreter
+			.set_history_limit(number_arg("set history", num)?),
+		["set", "max-memory", "off"] => debugger.interpreter.memory_limit = None,
+		["set", "max-memory", num] => {
+			debugger.interpreter.memory_limit = Some(count_arg("set max-memory", num)?)
+		}
+		["set", "loop-detect", "on"] => debugger.interpreter.loop_detect = true,
+		["set", "loop-detect", "off"] => debugger.interpreter.loop_detect = false,
+		["set", "max-steps", "off"] => debugger.interpreter.max_steps = None,
+		["set", "max-steps", num] => {
+			debugger.interpreter.max_steps = Some(count_arg("set max-steps", num)?)
+		}
+		["set", "wrap", "on"] => debugger.interpreter.wrap = true,
+		["set", "wrap", "off"] => debugger.interpreter.wrap = false,
+		["expect", "off"] => debugger.expected_output = None,
+		["expect", path] => {
+			let expected = fs::read(path).map_err(|err| format!("could not read {path}: {err}"))?;
+			debugger.expected_output = Some((path.to_string(), expected));
+		}
+		["set", "stream", "on"] => debugger.stream = true,
+		["set", "stream", "off"] => debugger.stream = false,
+		["set", "highlight-changes", "on"] => debugger.highlight_changes = true,
+		["set", "highlight-changes", "off"] => debugger.highlight_changes = false,
+		["set", "breaks", "on"] => debugger.interpreter.ignore_code_breaks = false,
+		["set", "breaks", "off"] => debugger.interpreter.ignore_code_breaks = true,
+		["set", "numeric-io", "on"] => debugger.interpreter.numeric_io = true,
+		["set", "numeric-io", "off"] => debugger.interpreter.numeric_io = false,
+		["set", "rainbow", "on"] => debugger.rainbow = true,
+		["set", "rainbow", "off"] => debugger.rainbow = false,
+		["set", "redraw", "on"] => debugger.redraw = true,
+		["set", "redraw", "off"] => debugger.redraw = false,
+		["set", "prompt", "on"] => debugger.prompt = true,
+		["set", "prompt", "off"] => debugger.prompt = false,
+		["label", index, name] => {
+			let index = debugger.cell_index(index)?;
+			debugger.add_label(index, name)?;
+		}
+		["labels"] => debugger.list_labels(),
+		["alias"] => debugger.list_aliases(),
+		["alias", ..] => {
+			let (name, commands) = rest_of_line(line, 1)
+				.split_once('=')
+				.ok_or("usage: alias <name> = <commands>")?;
+			let (name, commands) = (name.trim(), commands.trim());
+			if name.is_empty() || name.contains(char::is_whitespace) {
+				return Err("an alias name must be one word".into());
 			}
-			Command::EndLoop(start_of_loop) => {
-				if self.memory[self.mem_ptr] != 0 {
-					self.program_ptr = start_of_loop;
-				}
+			if commands::is_command(name) {
+				return Err(format!("{name} is already a command"));
+			}
+			if commands.is_empty() {
+				return Err(format!("alias {name} needs commands to run"));
+			}
+			debugger
+				.aliases
+				.insert(name.to_string(), commands.to_string());
+		}
+		["unalias", name] => {
+			debugger
+				.aliases
+				.remove(*name)
+				.ok_or(format!("no alias named {name}"))?;
+		}
+		["unlabel", name] => {
+			let index = debugger.cell_index(&format!("${}", name.trim_start_matches('$')))?;
+			debugger.labels.remove(&index);
+		}
+		["lastwrite", cell] => {
+			let cell = debugger.cell_index(cell)?;
+			debugger.show_last_write(cell)?;
+		}
+		["record"] => {
+			let cells: Vec<_> = debugger
+				.interpreter
+				.recorded_cells()
+				.map(|c| c.to_string())
+				.collect();
+			if cells.is_empty() {
+				println!("no cells are being recorded");
+			} else {
+				println!("recording cells {}", cells.join(", "));
+			}
+			if let Some((path, _)) = &debugger.session_recording {
+				println!("recording commands to {path}");
+			}
+		}
+		["record", "session", path] => debugger.record_session(path)?,
+		["record", "stop"] => {
+			let (path, _) = debugger
+				.session_recording
+				.take()
+				.ok_or("no session is being recorded")?;
+			println!("stopped recording commands to {path}");
+		}
+		["record", "stop", cell] => {
+			let cell = debugger.cell_index(cell)?;
+			debugger.interpreter.stop_recording(cell)?;
+		}
+		["record", cell] => {
+			let cell = debugger.cell_index(cell)?;
+			debugger.interpreter.start_recording(cell);
+		}
+		["history", cell] => {
+			let cell = debugger.cell_index(cell)?;
+			debugger.show_history(cell, usize::MAX)?;
+		}
+		["history", cell, count] => {
+			let cell = debugger.cell_index(cell)?;
+			let count = count_arg("history", count)?;
+			debugger.show_history(cell, count)?;
+		}
+		["set", "track-writes", "on"] => debugger.interpreter.track_writes = true,
+		["set", "track-writes", "off"] => debugger.interpreter.track_writes = false,
+		["reset"] => debugger.interpreter.reset(),
+		["reset", "input", path] => {
+			let input = fs::read(path).map_err(|err| format!("could not read {path}: {err}"))?;
+			debugger.interpreter.input = input;
+			debugger.input_source = path.to_string();
+			debugger.interpreter.reset();
+		}
+		["reload"] => debugger.reload(false)?,
+		["reload", "keep-memory"] => debugger.reload(true)?,
+		["info", "loops"] => debugger.list_loops(),
+		["info"] => debugger.show_info(),
+		["info", "breakpoints"] => debugger.list_breakpoints(),
+		["info", "watches"] => debugger.list_watches(),
+		["info", "labels"] => debugger.list_labels(),
+		["info", "aliases"] => debugger.list_aliases(),
+		["info", "settings"] => debugger.show_settings(),
+		["where" | "stack"] => debugger.show_loop_stack(),
+		["skip"] => debugger.interpreter.skip()?,
+		["skip", "loop"] => debugger.interpreter.skip_loop()?,
+		["goto", "index", index] => debugger.goto(number_arg("goto index", index)?)?,
+		["goto", location] => {
+			let (file, line, column) = debugger
+				.parse_location(location)
+				.and_then(|(file, line, column)| Some((file, line, column?)))
+				.ok_or("location must be [file:]<line>:<column>")?;
+			let index = debugger
+				.interpreter
+				.instruction_at(file, line, column)
+				.ok_or(format!("no instruction at {location}"))?;
+			debugger.goto(index)?;
+		}
+		["run"] => debugger.run(),
+		["continue"] => {
+			debugger.interpreter.clear_error();
+			debugger.run();
+		}
+		#[cfg(unix)]
+		["animate"] => animate::run(debugger, animate::DEFAULT_RATE)?,
+		#[cfg(unix)]
+		["animate", rate] => animate::run(debugger, count_arg("animate", rate)?)?,
+		["set", "eof", mode] => {
+			debugger.interpreter.eof_behavior =
+				EofBehavior::parse(mode).ok_or("eof mode must be one of 0, 255 or unchanged")?
+		}
+		["input"] => debugger.show_input(),
+		["input", "add", ..] => {
+			let text = line.trim_start()["input".len()..].trim_start()["add".len()..].trim_start();
+			let bytes = unescape(text.trim_end_matches(['\r', '\n']))?;
+			debugger.interpreter.append_input(&bytes);
+		}
+		["input", "type", ..] => {
+			if debugger.interpreter.state != State::WaitingForInput {
+				return Err(
+					"the program is not waiting for input, use input add to add to the input"
+						.into(),
+				);
 			}
-			Command::Break => self.state = State::BreakPointHit,
-			Command::End => (),
+			let bytes = unescape(rest_of_line(line, 2))?;
+			debugger.interpreter.provide_input(&bytes);
+			debugger.interpreter.step_once();
 		}
+		["replay", path] => return replay(debugger, path),
+		["set", "input", "interactive"] => debugger.interpreter.interactive_input = true,
+		["set", "input", "preloaded"] => debugger.interpreter.interactive_input = false,
+		["q" | "exit" | "quit"] => return Ok(ControlFlow::Break(())),
+		[] => debugger.interpreter.step_once(),
+		[name, ..] => return Err(commands::usage(name)),
+	}
+	Ok(ControlFlow::Continue(()))
+}
 
-		self.program_ptr += 1;
-		self.steps += 1;
+/// Asks for more input on the terminal when the program is waiting for it
+fn read_program_input(debugger: &mut Debugger) {
+	if debugger.interpreter.state != State::WaitingForInput {
+		return;
+	}
+	if !debugger.json {
+		print!("{}", "program input> ".yellow());
+		stdout().flush().unwrap();
 	}
+	// bytes, since the program may be given input that is not UTF-8
+	let mut line = Vec::new();
+	if let Err(err) = stdin().lock().read_until(b'\n', &mut line) {
+		debugger.show_error(&format!("could not read the program input: {err}"));
+		return;
+	}
+	debugger.record_line(&format!("input type {}", escape(&line)));
+	debugger.interpreter.provide_input(&line);
+	debugger.interpreter.step_once();
+}
 
-	fn update_watchers(&mut self) {
-		for watcher in &self.watchers {
-			if watcher.index == self.mem_ptr && self.memory[watcher.index] == watcher.value {
-				self.state = State::StoppedOnMemoryValue;
+/// Times `runs` runs of the whole program and prints the steps, times and speed.
+/// Fails if a run does not reach the end or its output does not match the expected output
+fn bench(
+	interpreter: &BFInterpreter,
+	runs: usize,
+	expected_output: Option<&(String, Vec<u8>)>,
+) -> Result<(), String> {
+	let mut times = Vec::with_capacity(runs);
+	let mut steps = 0;
+	for i in 1..=runs {
+		let run = interpreter.bench_run();
+		println!(
+			"run {i}: {} steps in {:.3}s, {} steps per second",
+			separated(run.steps),
+			run.time.as_secs_f64(),
+			separated((run.steps as f64 / run.time.as_secs_f64()) as usize)
+		);
+		if run.state != State::EndOfProgram {
+			return Err(format!(
+				"bench: the program stopped with {:?} after {} steps",
+				run.state, run.steps
+			));
+		}
+		if let Some((path, expected)) = expected_output {
+			if let Some(diff) = compare_output(expected, &run.output) {
+				return Err(format!("bench: output does not match {path}\n{diff}"));
 			}
 		}
+		times.push(run.time);
+		steps = run.steps;
+	}
+	times.sort();
+	let median = times[times.len() / 2];
+	println!(
+		"{runs} runs of {} steps: min {:.3}s, median {:.3}s, {} steps per second at the median",
+		separated(steps),
+		times[0].as_secs_f64(),
+		median.as_secs_f64(),
+		separated((steps as f64 / median.as_secs_f64()) as usize)
+	);
+	if let Some((path, _)) = expected_output {
+		println!("output matches {path}");
 	}
+	Ok(())
 }
 
-fn parse(source_text: &str) -> Vec<DebugCommand> {
-	let mut out: Vec<DebugCommand> = Vec::new();
-	let mut loop_starts = Vec::new();
-	for (line_number, line) in source_text
-		.lines()
-		.enumerate()
-		.map(|(num, line)| (num + 1, line))
-	{
-		for (column, char) in line.chars().enumerate() {
-			let cmd = match char {
-				'+' => Command::Inc,
-				'-' => Command::Dec,
-				'>' => Command::Right,
-				'<' => Command::Left,
-				',' => Command::Read,
-				'.' => Command::Write,
-				'[' => {
-					loop_starts.push(out.len());
-					Command::BeginLoop(usize::MAX)
+/// Writes the program translated into C or Rust to a file
+fn emit(interpreter: &BFInterpreter, language: Language, path: &str) -> Result<(), String> {
+	let source = interpreter.emit(language)?;
+	fs::write(path, &source).map_err(|err| format!("could not write {path}: {err}"))?;
+	println!(
+		"wrote {} lines of {language:?} to {path}",
+		source.lines().count()
+	);
+	Ok(())
+}
+
+/// Runs the program like a plain interpreter, writing its output raw to stdout
+fn run_non_interactive(
+	mut interpreter: BFInterpreter,
+	expected_output: Option<(String, Vec<u8>)>,
+	sources: &[SourceFile],
+) -> ! {
+	interpreter.set_history_limit(0);
+	interpreter.track_writes = false;
+	// whole lines go out as they are written, and the rest on every interrupt check
+	let mut out = stdout().lock();
+	interpreter.output_stream = Some(OutputStream(Box::new(move |byte| {
+		if let Err(err) = out.write_all(&[byte]) {
+			output_failed(err);
+		}
+	})));
+	interpreter.progress = Some(ProgressReport(Box::new(|_| {
+		if let Err(err) = stdout().flush() {
+			output_failed(err);
+		}
+	})));
+	interpreter.run();
+	interpreter.output_stream = None;
+	if let Err(err) = stdout().flush() {
+		output_failed(err);
+	}
+	if interpreter.skipped_code_breaks() > 0 {
+		eprintln!(
+			"skipped ! {} times, use --stop-at-breaks to stop there",
+			interpreter.skipped_code_breaks()
+		);
+	}
+	if let Some(counts) = interpreter.profile() {
+		eprint!("{}", format_profile(&interpreter.program, counts, sources));
+	}
+	let current = &interpreter.program[interpreter.program_ptr];
+	match interpreter.state {
+		State::EndOfProgram => match expected_output {
+			Some((path, expected)) => match compare_output(&expected, &interpreter.output) {
+				Some(diff) => {
+					eprintln!("fail: output does not match {path}\n{diff}");
+					exit(5);
+				}
+				None => {
+					eprintln!("pass: output matches {path}");
+					exit(0);
 				}
-				']' => {
-					if loop_starts.is_empty() {
-						println!("Parser error: no opening bracket for closing bracket at {line_number}:{column}");
-						exit(1);
-					}
-					let last_loop_start = loop_starts.pop().unwrap();
-					out[last_loop_start].command = Command::BeginLoop(out.len());
-
-					Command::EndLoop(last_loop_start)
+			},
+			None => exit(0),
+		},
+		State::TooFarLeft => {
+			let current = &interpreter.program[interpreter.program_ptr];
+			eprintln!(
+				"error: memory pointer moved left of cell 0 at {}",
+				location(current, sources)
+			);
+			exit(2);
+		}
+		State::TooFarRight => {
+			let current = &interpreter.program[interpreter.program_ptr];
+			eprintln!(
+				"error: memory pointer moved past the last of {} cells at {}",
+				interpreter.memory.len(),
+				location(current, sources)
+			);
+			exit(2);
+		}
+		State::StepLimitReached => {
+			eprintln!(
+				"error: step limit of {} reached at {}",
+				interpreter.steps,
+				location(current, sources)
+			);
+			exit(4);
+		}
+		State::MemoryLimitReached => {
+			eprintln!(
+				"error: > at {} would grow the tape past the limit of {} cells",
+				location(current, sources),
+				interpreter.memory.len()
+			);
+			exit(4);
+		}
+		State::AssertionFailed => {
+			let (c, actual) = match interpreter.stop_reason {
+				Some(StopReason::Assertion { index, actual }) => {
+					(&interpreter.program[index], actual)
 				}
-				'!' => Command::Break,
-				_ => continue,
+				_ => (current, interpreter.memory[interpreter.mem_ptr]),
 			};
-			out.push(DebugCommand {
-				command: cmd,
-				line_number,
-				column,
-			});
+			eprintln!(
+				"error: assertion {} at {} failed, cell {} is {actual}",
+				c.command,
+				location(c, sources),
+				interpreter.mem_ptr
+			);
+			exit(6);
+		}
+		State::InfiniteLoop => {
+			eprintln!(
+				"error: infinite loop at {}, step {} is in the same state as step {}",
+				location(current, sources),
+				interpreter.steps,
+				match interpreter.stop_reason {
+					Some(StopReason::Cycle { first_step }) => first_step,
+					_ => interpreter.steps,
+				}
+			);
+			exit(4);
+		}
+		State::Interrupted => {
+			eprintln!("interrupted at {}", location(current, sources));
+			exit(130);
+		}
+		state => {
+			eprintln!("stopped: {state:?} at {}", location(current, sources));
+			exit(3);
 		}
 	}
-	out.push(DebugCommand {
-		command: Command::End,
-		line_number: 0,
-		column: 0,
-	});
-	if let Some(loop_start_index) = loop_starts.pop() {
-		let loop_start = &out[loop_start_index];
-		println!(
-			"Parser error: no matching closing bracket for open bracket at {}:{}",
-			loop_start.line_number, loop_start.column
-		);
-		exit(1);
+}
+
+/// Stops `--run` when its output can't be written, quietly if the reader went away
+fn output_failed(err: io::Error) -> ! {
+	if err.kind() != io::ErrorKind::BrokenPipe {
+		eprintln!("error: could not write the output: {err}");
 	}
-	out
+	exit(1);
 }
 
-impl Display for Command {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		write!(
-			f,
-			"{}",
-			match self {
-				Command::Inc => '+',
-				Command::Dec => '-',
-				Command::Right => '>',
-				Command::Left => '<',
-				Command::Read => ',',
-				Command::Write => '.',
-				Command::BeginLoop(_) => '[',
-				Command::EndLoop(_) => ']',
-				Command::Break => '!',
-				Command::End => ' ',
+impl Debugger {
+	fn new(interpreter: BFInterpreter, sources: Vec<SourceFile>, optimize: bool) -> Self {
+		let shown_memory = interpreter.memory.clone();
+		Self {
+			program_text: ProgramText::new(&interpreter.program),
+			interpreter,
+			sources,
+			input_source: "none".into(),
+			optimize,
+			asserts: true,
+			mem_width: DEFAULT_MEM_WIDTH,
+			mem_view: MemView::Dec,
+			code_width: DEFAULT_CODE_WIDTH,
+			output_view: OutputView::default(),
+			output_limit: DEFAULT_OUTPUT_LIMIT,
+			snapshots: BTreeMap::new(),
+			highlight_changes: true,
+			rainbow: true,
+			redraw: false,
+			prompt: true,
+			strict: Strict::Off,
+			shown_memory,
+			shown_steps: 0,
+			command_time: None,
+			labels: BTreeMap::new(),
+			aliases: BTreeMap::new(),
+			stream: true,
+			expected_output: None,
+			json: false,
+			session_recording: None,
+			scratchpad: None,
+		}
+	}
+
+	/// Clears the terminal with `set redraw on`, before a command prints anything
+	/// so its messages stay on screen above the view that follows
+	fn clear_screen(&self) {
+		if self.redraw && !self.json && stdout().is_terminal() {
+			print!("\x1b[H\x1b[2J");
+		}
+	}
+
+	/// The text before each command, like `[Running · step 1,520 · 14:3] > `
+	fn prompt(&self) -> String {
+		if !self.prompt || self.json {
+			return String::new();
+		}
+		let interpreter = &self.interpreter;
+		let state = format!("{:?}", interpreter.state);
+		let state = match interpreter.state {
+			State::Running => state.green().to_string(),
+			State::TooFarLeft | State::TooFarRight | State::AssertionFailed => {
+				state.red().to_string()
 			}
+			State::EndOfProgram => state.cyan().to_string(),
+			_ => state.yellow().to_string(),
+		};
+		let current = &interpreter.program[interpreter.program_ptr];
+		let location = if interpreter.program_ptr == interpreter.program.len() - 1 {
+			"end".to_string()
+		} else {
+			self.location(current)
+		};
+		format!(
+			"[{state} · step {} · {location}] > ",
+			separated(interpreter.steps)
 		)
 	}
+
+	fn show(&mut self) {
+		let view = self.collect_view();
+		if self.json {
+			println!("{}", serde_json::to_string(&view).unwrap());
+		} else {
+			self.render(&view);
+		}
+		self.shown_memory.clone_from(&self.interpreter.memory);
+		self.shown_steps = self.interpreter.steps;
+		self.command_time = None;
+	}
+
+	/// Prints the state for people, with colors
+	fn render(&self, view: &View) {
+		let (start, end) = centered_window(
+			view.program_ptr,
+			self.code_width,
+			self.interpreter.program.len(),
+		);
+		self.show_program(start, end);
+		self.show_source_line();
+		self.show_memory(view.memory_start, view.memory.len());
+		print!("{:?}. steps: {}", view.state, separated(view.steps));
+		match view.steps.cmp(&self.shown_steps) {
+			Ordering::Greater => print!(" (+{})", separated(view.steps - self.shown_steps)),
+			Ordering::Less => print!(" (-{})", separated(self.shown_steps - view.steps)),
+			Ordering::Equal => (),
+		}
+		print!(". eof: {}", view.eof_behavior);
+		if view.numeric_io {
+			print!(". {}", "numeric io".yellow());
+		}
+		println!();
+		if let Some((elapsed, steps)) = self.command_time.filter(|(t, _)| *t >= TIMING_THRESHOLD) {
+			print!("took {:.2}s", elapsed.as_secs_f64());
+			if steps > 0 {
+				let rate = steps as f64 / elapsed.as_secs_f64();
+				print!(", {} steps per second", separated(rate as usize));
+			}
+			println!();
+		}
+		match view.stop_reason {
+			Some(StopReason::Watcher { watcher, value }) if watcher.condition.is_pointer() => {
+				println!("stopped: watcher {watcher} (pointer moved to {value})")
+			}
+			Some(StopReason::Watcher { watcher, value }) if watcher.condition.is_output() => {
+				// the `.` that wrote it was just executed
+				let c = &self.interpreter.program[view.program_ptr - 1];
+				println!(
+					"stopped: watcher {watcher} (output offset {value}, written by . at {})", self.location(c))
+			}
+			Some(StopReason::Watcher { watcher, value }) => {
+				println!("stopped: watcher {watcher} (value {value})")
+			}
+			Some(StopReason::Breakpoint(breakpoint)) => {
+				print!(
+					"stopped: breakpoint #{} {}",
+					breakpoint.id,
+					self.describe_break_target(&breakpoint.target)
+				);
+				if let Some(condition) = &breakpoint.condition {
+					print!(" if {condition}");
+				}
+				if let BreakTarget::Output(text) = &breakpoint.target {
+					let offset = self.interpreter.output.len() - text.len();
+					print!(" at output offset {offset}");
+				}
+				if let BreakTarget::MemorySize(_) = breakpoint.target {
+					// the `>` that grew the tape was just executed
+					let c = &self.interpreter.program[view.program_ptr - 1];
+					print!(
+						", pointer at cell {} after > at {}",
+						view.mem_ptr, self.location(c));
+				}
+				if breakpoint.once {
+					print!(" (once, now deleted)");
+				}
+				println!();
+			}
+			Some(StopReason::CodeBreakpoint(index)) => {
+				let c = &self.interpreter.program[*index];
+				println!("stopped: ! at {}", self.location(c));
+			}
+			Some(StopReason::Assertion { index, actual }) => {
+				let c = &self.interpreter.program[*index];
+				println!(
+					"{}",
+					format!(
+						"stopped: assertion {} at {} failed, cell {} is {actual}",
+						c.command,
+						self.location(c),
+						view.mem_ptr
+					)
+					.red()
+				);
+			}
+			Some(StopReason::Skipped { from, to }) => {
+				let c = &self.interpreter.program[*from];
+				let skipped = if to - from == 1 {
+					format!("skipped {} at {}", c.command, self.location(c))
+				} else {
+					format!(
+						"skipped {} instructions from {}",
+						to - from, self.location(c))
+				};
+				println!(
+					"{}",
+					format!("{skipped} without executing, memory and steps are unchanged").yellow()
+				);
+			}
+			// shown with the state below
+			Some(StopReason::OffTape(_)) => (),
+			Some(StopReason::Cycle { first_step }) => println!(
+				"{}",
+				format!(
+					"stopped: infinite loop, step {} is in the same state as step {}, so the program would repeat steps {}..{} forever",
+					separated(view.steps),
+					separated(*first_step),
+					separated(*first_step),
+					separated(view.steps)
+				)
+				.yellow()
+			),
+			None => (),
+		}
+		if let Some(size) = view.tape_size {
+			print!("tape: {size} cells");
+			match (
+				self.interpreter.tape_mode(),
+				self.interpreter.last_wrap_step,
+			) {
+				(TapeMode::Wrap, Some(step)) if step == view.steps => {
+					println!(", wrapping. {}", "the pointer just wrapped around".yellow())
+				}
+				(TapeMode::Wrap, Some(step)) => println!(", wrapping. last wrapped at step {step}"),
+				(TapeMode::Wrap, None) => println!(", wrapping"),
+				(TapeMode::Error, _) => println!(),
+			}
+		}
+		if let State::TooFarLeft | State::TooFarRight = view.state {
+			let edge = match view.state {
+				State::TooFarLeft => "left of cell 0".to_string(),
+				_ => format!("past the last of {} cells", self.interpreter.memory.len()),
+			};
+			let message = match view.stop_reason {
+				Some(StopReason::OffTape(index)) => {
+					let c = &self.interpreter.program[*index];
+					format!(
+						"stopped: {} at {} would move the memory pointer {edge}. Move the pointer with `set ptr`, or go past it with `skip` or `goto`, then `continue`",
+						c.command,
+						self.location(c)
+					)
+				}
+				// moved past it with `skip` or `goto`
+				_ => format!("the memory pointer moving {edge} is still an error, `continue` clears it and runs on"),
+			};
+			println!("{}", message.red());
+		}
+		if let State::CellOverflow | State::CellUnderflow = view.state {
+			let c = &self.interpreter.program[view.program_ptr];
+			println!(
+				"{}",
+				format!(
+					"stopped: {} at {} would wrap cell {} (value {}), use `skip` to go past it",
+					c.command,
+					self.location(c),
+					view.mem_ptr,
+					self.interpreter.memory[view.mem_ptr]
+				)
+				.yellow()
+			);
+		}
+		if let (State::MemoryLimitReached, Some(max)) = (view.state, self.interpreter.memory_limit)
+		{
+			let c = &self.interpreter.program[view.program_ptr];
+			println!(
+				"{}",
+				format!(
+					"stopped: > at {} would grow the tape past the limit of {max} cells, change it with `set max-memory`",
+					self.location(c)
+				)
+				.yellow()
+			);
+		}
+		if let (State::StepLimitReached, Some(max)) = (view.state, view.max_steps) {
+			println!(
+				"{}",
+				format!("stopped at the step limit of {max}, change it with `set max-steps`")
+					.yellow()
+			);
+		}
+		if let Some(Expect { path, difference }) = &view.expect {
+			match difference {
+				Some(diff) => println!(
+					"{}",
+					format!("fail: output does not match {path}\n{diff}").red()
+				),
+				None => println!("{}", format!("pass: output matches {path}").green()),
+			}
+		}
+		match view.input_exhausted_at {
+			// only warned about once, by the first view after it happened
+			Some(step) if step > self.shown_steps => println!(
+				"{}",
+				format!(
+					"input exhausted at step {}, subsequent reads return EOF value",
+					separated(step)
+				)
+				.yellow()
+			),
+			_ => (),
+		}
+		if view.input_len > 0 || view.input_exhausted_at.is_some() {
+			println!(
+				"input: {}/{} bytes consumed",
+				view.input_ptr, view.input_len
+			);
+		}
+		let output = view.output;
+		let shown = &output[..output.len().min(self.output_limit)];
+		print!("output: ");
+		if self.output_view == OutputView::Hex {
+			println!();
+		}
+		print!("{}", self.output_view.format(shown).blue());
+		if shown.len() < output.len() {
+			print!(
+				"… ({} more bytes, use `output` to view)",
+				output.len() - shown.len()
+			);
+		}
+		println!();
+	}
+
+	/// Prints an error from a command, as JSON with `--json`
+	fn show_error(&self, err: &str) {
+		if self.json {
+			println!("{}", serde_json::json!({ "error": err }));
+		} else {
+			println!("{}", err.red());
+		}
+	}
+
+	/// Whether a cell is different from when the prompt was last shown. Cells that did not exist then count as changed
+	fn cell_changed(&self, index: usize) -> bool {
+		self.highlight_changes && self.shown_memory.get(index) != self.interpreter.memory.get(index)
+	}
+
+	/// Runs the program, printing its output as it is written when streaming is on
+	fn run(&mut self) {
+		if self.json {
+			self.interpreter.run();
+			return;
+		}
+		// the progress line is only drawn at the start of a line, so it never hides streamed output
+		let progress_shown = Rc::new(Cell::new(false));
+		let line_start = Rc::new(Cell::new(true));
+		if stdout().is_terminal() {
+			let (shown, line_start) = (progress_shown.clone(), line_start.clone());
+			let (start, start_steps) = (Instant::now(), self.interpreter.steps);
+			let mut last_shown = start;
+			self.interpreter.progress = Some(ProgressReport(Box::new(move |progress| {
+				let now = Instant::now();
+				if now - last_shown < PROGRESS_INTERVAL || !line_start.get() {
+					return;
+				}
+				last_shown = now;
+				let rate = (progress.steps - start_steps) as f64 / (now - start).as_secs_f64();
+				let status = format!(
+					"running: {} steps, {rate:.0} steps/s, {} cells, {} bytes of output",
+					progress.steps, progress.memory_len, progress.output_len
+				);
+				print!("\r\x1b[K{}", status.dimmed());
+				_ = stdout().flush();
+				shown.set(true);
+			})));
+		}
+		if self.stream {
+			let raw = self.output_view == OutputView::Raw;
+			let mut started = false;
+			let (shown, line_start) = (progress_shown.clone(), line_start.clone());
+			self.interpreter.output_stream = Some(OutputStream(Box::new(move |byte| {
+				let mut out = stdout();
+				if shown.replace(false) {
+					_ = write!(out, "\r\x1b[K");
+				}
+				if !started {
+					started = true;
+					_ = writeln!(out, "{}", "--- program output ---".dimmed());
+				}
+				if raw || byte == b'\n' || byte == b'\t' || !byte.is_ascii_control() {
+					_ = out.write_all(&[byte]);
+				} else {
+					_ = write!(out, "\\x{byte:02x}");
+				}
+				_ = out.flush();
+				line_start.set(byte == b'\n');
+			})));
+		}
+		let written = self.interpreter.output.len();
+		self.interpreter.run();
+		self.interpreter.output_stream = None;
+		self.interpreter.progress = None;
+		if progress_shown.get() {
+			print!("\r\x1b[K");
+			_ = stdout().flush();
+		}
+		if self.stream && self.interpreter.output.len() > written {
+			if self.interpreter.output.last() != Some(&b'\n') {
+				println!();
+			}
+			println!("{}", "--- end of program output ---".dimmed());
+		}
+	}
+
+	/// Prints the source line of the current instruction with a caret under it
+	fn show_source_line(&self) {
+		let current = &self.interpreter.program[self.interpreter.program_ptr];
+		println!("source (line:column): {}", self.location(current));
+		let Some(line) = self.sources[current.file].line(current.line_number) else {
+			return;
+		};
+		let margin = " ".repeat(current.line_number.to_string().len());
+		let indent: String = line
+			.chars()
+			.take(current.column.saturating_sub(1))
+			.map(|c| if c == '\t' { '\t' } else { ' ' })
+			.collect();
+		println!("{} | {line}", current.line_number);
+		println!("{margin} | {indent}{}", "^".cyan());
+	}
+
+	/// Prints the instructions in `start..end`, with their indices when there are more outside the range
+	fn show_program(&self, start: usize, end: usize) {
+		let interpreter = &self.interpreter;
+		let program = &self.program_text;
+		let mut line = String::new();
+		if start > 0 {
+			line.push_str(&format!("{start}… "));
+		}
+		let prefix = line.chars().count();
+		let partner = interpreter.program[interpreter.program_ptr]
+			.command
+			.matching_bracket();
+		let breakpoints: BTreeSet<usize> = interpreter
+			.breakpoints
+			.iter()
+			.filter_map(|b| match b.target {
+				BreakTarget::Index(index) if !b.disabled => Some(index),
+				_ => None,
+			})
+			.collect();
+		// instructions without a color of their own are copied from the plain text in runs
+		let mut plain_start = start;
+		for index in start..end {
+			let command = &interpreter.program[index].command;
+			let bracket = matches!(command, Command::BeginLoop(_) | Command::EndLoop(_));
+			let plain = index != interpreter.program_ptr
+				&& partner != Some(index)
+				&& !breakpoints.contains(&index)
+				&& !(self.rainbow && bracket);
+			if plain {
+				continue;
+			}
+			line.push_str(program.range(plain_start, index));
+			plain_start = index + 1;
+			let text = match command {
+				// the depth of a `]` still counts its own loop
+				Command::BeginLoop(_) if self.rainbow => rainbow(command, program.depths[index]),
+				Command::EndLoop(_) if self.rainbow => {
+					rainbow(command, program.depths[index].saturating_sub(1))
+				}
+				_ => program.range(index, index + 1).to_string(),
+			};
+			let text = if index == interpreter.program_ptr {
+				text.on_cyan().to_string()
+			} else if partner == Some(index) {
+				text.on_magenta().to_string()
+			} else if breakpoints.contains(&index) {
+				text.on_red().to_string()
+			} else {
+				text
+			};
+			line.push_str(&text);
+		}
+		line.push_str(program.range(plain_start, end));
+		if end < interpreter.program.len() {
+			line.push_str(&format!(" …{}", end - 1));
+		}
+		println!("{line}");
+		// without colors, the current instruction is marked on a line of its own
+		let ptr = interpreter.program_ptr;
+		if (start..end).contains(&ptr) && !style::enabled() {
+			let column = prefix + program.range(start, ptr).len();
+			println!(
+				"{}{}",
+				" ".repeat(column),
+				"^".repeat(program.range(ptr, ptr + 1).len())
+			);
+		}
+	}
+
+	/// Prints `count` cells starting at `start`, with markers when there are more cells outside the range
+	fn show_memory(&self, start: usize, count: usize) {
+		let start = start.min(self.interpreter.memory.len() - 1);
+		let end = (start + count.max(1)).min(self.interpreter.memory.len());
+		let cells: Vec<_> = (self.interpreter.memory.range(start, end))
+			.map(|cell| self.mem_view.format(cell, self.interpreter.cell_size()))
+			.collect();
+		let labels: Vec<_> = (start..end)
+			.map(|i| self.labels.get(&i).map_or("", String::as_str))
+			.collect();
+		let width = cells
+			.iter()
+			.map(String::as_str)
+			.chain(labels.iter().copied())
+			.map(|c| c.chars().count())
+			.max()
+			.unwrap_or(0)
+			.max((end - 1).to_string().len())
+			.max(3);
+		let before = if start > 0 { "… " } else { "" };
+		let after = if end < self.interpreter.memory.len() {
+			"…"
+		} else {
+			""
+		};
+		// each row is built first and printed at once
+		if labels.iter().any(|l| !l.is_empty()) {
+			let mut row = format!("lbl: {before}");
+			for label in &labels {
+				row.push_str(&format!("{:>width$} ", label.magenta()));
+			}
+			println!("{row}{after}");
+		}
+		let mut row = format!("mem: {before}");
+		for (index, cell) in cells.iter().enumerate() {
+			let cell = if start + index == self.interpreter.mem_ptr {
+				format!("{:>width$} ", cell.on_red())
+			} else if self.cell_changed(start + index) {
+				format!("{:>width$} ", cell.on_yellow())
+			} else {
+				format!("{:>width$} ", cell)
+			};
+			row.push_str(&cell);
+		}
+		println!("{row}{after}");
+		let mut row = format!("ind: {before}");
+		for i in start..end {
+			let index = if i == self.interpreter.mem_ptr {
+				format!("{:width$} ", i.on_red())
+			} else {
+				format!("{:width$} ", i)
+			};
+			row.push_str(&index);
+		}
+		println!("{row}{after}");
+		if !style::enabled() && (start..end).contains(&self.interpreter.mem_ptr) {
+			let offset = "ind: ".len() + before.chars().count();
+			let offset = offset + (self.interpreter.mem_ptr - start) * (width + 1);
+			println!("{}{}", " ".repeat(offset), "^".repeat(width));
+		}
+	}
+
+	fn list_watches(&self) {
+		if self.interpreter.watchers.is_empty() {
+			println!("no watchers");
+		}
+		for watcher in &self.interpreter.watchers {
+			if watcher.disabled {
+				println!("{}", format!("{watcher} (disabled)").dimmed());
+			} else {
+				println!("{watcher}");
+			}
+		}
+	}
+
+	/// A breakpoint as it is listed, without the disabled marker
+	fn describe_breakpoint(&self, breakpoint: &Breakpoint) -> String {
+		let mut line = format!(
+			"#{} {}",
+			breakpoint.id,
+			self.describe_break_target(&breakpoint.target)
+		);
+		if let Some(condition) = &breakpoint.condition {
+			line.push_str(&format!(" if {condition}"));
+		}
+		line.push_str(&format!(
+			"{} (hits: {})",
+			describe_modifiers(breakpoint),
+			breakpoint.hits
+		));
+		line
+	}
+
+	fn list_breakpoints(&self) {
+		if self.interpreter.breakpoints.is_empty() {
+			println!("no breakpoints");
+		}
+		for breakpoint in &self.interpreter.breakpoints {
+			let line = self.describe_breakpoint(breakpoint);
+			if breakpoint.disabled {
+				println!("{}", format!("{line} (disabled)").dimmed());
+			} else {
+				println!("{line}");
+			}
+		}
+		for index in self.interpreter.disabled_code_breaks() {
+			let c = &self.interpreter.program[index];
+			let line = format!("! at {index} ({}) (disabled)", self.location(c));
+			println!("{}", line.dimmed());
+		}
+	}
+
+	/// Switches breakpoints, watchers or `!`s on or off
+	fn set_enabled(&mut self, what: &[&str], enabled: bool) -> Result<(), String> {
+		let command = if enabled { "enable" } else { "disable" };
+		let interpreter = &mut self.interpreter;
+		match what {
+			["all"] => {
+				for breakpoint in &mut interpreter.breakpoints {
+					breakpoint.disabled = !enabled;
+				}
+				for watcher in &mut interpreter.watchers {
+					watcher.disabled = !enabled;
+				}
+				self.set_code_breaks_enabled(enabled);
+			}
+			["watch", id] => interpreter.watcher_mut(number_arg(command, id)?)?.disabled = !enabled,
+			["code"] => self.set_code_breaks_enabled(enabled),
+			["code", location] => {
+				let index = self
+					.find_instruction(location)
+					.ok_or("no instruction at that location")?;
+				self.interpreter.set_code_break_enabled(index, enabled)?;
+			}
+			[id] => {
+				interpreter
+					.breakpoint_mut(number_arg(command, id)?)?
+					.disabled = !enabled
+			}
+			_ => return Err(commands::usage(command)),
+		}
+		Ok(())
+	}
+
+	fn set_code_breaks_enabled(&mut self, enabled: bool) {
+		for index in 0..self.interpreter.program.len() {
+			if let Command::Break = self.interpreter.program[index].command {
+				// only fails for instructions that are not `!`
+				_ = self.interpreter.set_code_break_enabled(index, enabled);
+			}
+		}
+	}
+
+	/// Everything `info` shows, section by section
+	fn show_info(&self) {
+		let interpreter = &self.interpreter;
+		print_aligned(&[
+			("source", self.source_paths().join(" ")),
+			("input", self.input_source.clone()),
+			("state", format!("{:?}", interpreter.state)),
+			("steps", interpreter.steps.to_string()),
+			(
+				"input read",
+				match interpreter.input_exhausted_at {
+					Some(step) => format!(
+						"{} of {} bytes, exhausted at step {step}",
+						interpreter.input_ptr,
+						interpreter.input.len()
+					),
+					None => format!(
+						"{} of {} bytes",
+						interpreter.input_ptr,
+						interpreter.input.len()
+					),
+				},
+			),
+		]);
+		println!("\nbreakpoints:");
+		self.list_breakpoints();
+		println!("\nwatches:");
+		self.list_watches();
+		println!("\nlabels:");
+		self.list_labels();
+		println!("\naliases:");
+		self.list_aliases();
+		println!("\nsettings:");
+		self.show_settings();
+	}
+
+	fn show_settings(&self) {
+		let interpreter = &self.interpreter;
+		let on_off = |on: bool| if on { "on" } else { "off" }.to_string();
+		let tape = match (interpreter.tape_size(), interpreter.tape_mode()) {
+			(None, _) => "growing".to_string(),
+			(Some(size), TapeMode::Error) => format!("{size} cells"),
+			(Some(size), TapeMode::Wrap) => format!("{size} cells, wrapping"),
+		};
+		print_aligned(&[
+			("memview", format!("{:?}", self.mem_view).to_lowercase()),
+			("memwidth", self.mem_width.to_string()),
+			("codewidth", self.code_width.to_string()),
+			("output", format!("{:?}", self.output_view).to_lowercase()),
+			("outputlimit", self.output_limit.to_string()),
+			("eof", interpreter.eof_behavior.to_string()),
+			("numeric-io", on_off(interpreter.numeric_io)),
+			("wrap", on_off(interpreter.wrap)),
+			(
+				"cell size",
+				format!("{} bits", interpreter.cell_size().bits()),
+			),
+			("tape", tape),
+			("history", interpreter.history_limit().to_string()),
+			(
+				"max-steps",
+				interpreter
+					.max_steps
+					.map_or("off".into(), |max| max.to_string()),
+			),
+			(
+				"max-memory",
+				interpreter
+					.memory_limit
+					.map_or("off".into(), |max| max.to_string()),
+			),
+			("loop-detect", on_off(interpreter.loop_detect)),
+			(
+				"input",
+				if interpreter.interactive_input {
+					"interactive"
+				} else {
+					"preloaded"
+				}
+				.into(),
+			),
+			("stream", on_off(self.stream)),
+			("highlight-changes", on_off(self.highlight_changes)),
+			("rainbow", on_off(self.rainbow)),
+			("redraw", on_off(self.redraw)),
+			("prompt", on_off(self.prompt)),
+			("track-writes", on_off(interpreter.track_writes)),
+			("breaks", on_off(!interpreter.ignore_code_breaks)),
+			("optimize", on_off(self.optimize)),
+			("profile", on_off(interpreter.profile().is_some())),
+			("trace", on_off(interpreter.is_tracing())),
+			(
+				"expect",
+				self.expected_output
+					.as_ref()
+					.map_or("off".into(), |(path, _)| path.clone()),
+			),
+		]);
+	}
+
+	/// Resolves `line:column` or a program index to a program index
+	fn find_instruction(&self, location: &str) -> Option<usize> {
+		let (_, rest) = self.split_file(location);
+		if rest.contains(':') || rest.len() < location.len() {
+			let (file, line, column) = self.parse_location(location)?;
+			let program = &self.interpreter.program;
+			program[..program.len() - 1]
+				.iter()
+				.position(|c| c.is_at(file, line, column))
+		} else {
+			let last = self.interpreter.program.len() - 1;
+			location.parse().ok().filter(|&index| index < last)
+		}
+	}
+
+	/// Parses `line` or `line:column`, after a file name and `:` to pick another file than the first
+	fn parse_location(&self, text: &str) -> Option<(usize, usize, Option<usize>)> {
+		let (file, text) = self.split_file(text);
+		match text.split_once(':') {
+			Some((line, column)) => Some((file, line.parse().ok()?, Some(column.parse().ok()?))),
+			None => Some((file, text.parse().ok()?, None)),
+		}
+	}
+
+	/// The source file named at the start of a location and the rest of it, or the first file
+	fn split_file<'a>(&self, text: &'a str) -> (usize, &'a str) {
+		self.sources
+			.iter()
+			.enumerate()
+			.find_map(|(index, file)| {
+				let rest = text.strip_prefix(file.name.as_str())?.strip_prefix(':')?;
+				Some((index, rest))
+			})
+			.unwrap_or((0, text))
+	}
+
+	/// `line:column` of an instruction, with the file name when there are several files
+	fn location(&self, c: &DebugCommand) -> String {
+		location(c, &self.sources)
+	}
+
+	fn source_paths(&self) -> Vec<&str> {
+		self.sources.iter().map(|file| file.path.as_str()).collect()
+	}
+
+	fn add_watch(
+		&mut self,
+		index: usize,
+		condition: WatchCondition,
+		log: bool,
+	) -> Result<(), String> {
+		let id = self.interpreter.add_watch(index, condition)?;
+		let watcher = self.interpreter.watcher_mut(id)?;
+		watcher.log = log;
+		println!("added watcher {watcher}");
+		Ok(())
+	}
+
+	/// Starts appending every command that works to a file, after a header naming the program
+	fn record_session(&mut self, path: &str) -> Result<(), String> {
+		let mut file = OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(path)
+			.map_err(|err| format!("could not open {path}: {err}"))?;
+		let header = format!(
+			"# session recorded for {}\n# input: {}\n{PROGRAM_HASH_HEADER}{:016x}\n",
+			self.source_paths().join(" "),
+			self.input_source,
+			program_hash(&self.interpreter.program)
+		);
+		file.write_all(header.as_bytes())
+			.map_err(|err| format!("could not write to {path}: {err}"))?;
+		println!("recording commands to {path}, replay them with `replay {path}` or -x {path}");
+		self.session_recording = Some((path.into(), file));
+		Ok(())
+	}
+
+	/// Adds a line to the recorded session, if there is one
+	fn record_line(&mut self, line: &str) {
+		let Some((path, file)) = &mut self.session_recording else {
+			return;
+		};
+		if let Err(err) = writeln!(file, "{line}") {
+			let err = format!("could not write to {path}, stopped recording: {err}");
+			self.session_recording = None;
+			self.show_error(&err);
+		}
+	}
+
+	/// Jumps to a program index, warning when that goes into or out of loops
+	fn goto(&mut self, index: usize) -> Result<(), String> {
+		let from = self
+			.interpreter
+			.enclosing_loops(self.interpreter.program_ptr);
+		self.interpreter.goto(index)?;
+		let to = self.interpreter.enclosing_loops(index);
+		let shared = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+		let (left, entered) = (from.len() - shared, to.len() - shared);
+		if left > 0 || entered > 0 {
+			println!(
+				"{}",
+				format!(
+					"warning: jumped across loop boundaries (out of {left}, into {entered}) without running the brackets, the loop state may not match the memory"
+				)
+				.yellow()
+			);
+		}
+		Ok(())
+	}
+
+	/// Prints what the logpoints noted during the last command
+	fn show_logs(&mut self) {
+		let logs = self.interpreter.take_logs();
+		for message in &logs.messages {
+			let c = &self.interpreter.program[message.program_ptr];
+			println!(
+				"log: {} at step {} ({}) cell {} = {}",
+				message.source,
+				message.step,
+				self.location(c),
+				message.cell,
+				message.value
+			);
+		}
+		for (source, count) in logs.counts.iter().filter(|(_, &count)| count > LOG_LIMIT) {
+			println!(
+				"{}",
+				format!("{source} logged {count} times, showing the first {LOG_LIMIT}").yellow()
+			);
+		}
+	}
+
+	/// Writes `len` cells from `start` to a file, as raw little endian cells or one `index value` line per cell
+	fn dump_memory(&self, path: &str, start: usize, len: usize, text: bool) -> Result<(), String> {
+		let memory = &self.interpreter.memory;
+		let end = start.saturating_add(len).min(memory.len());
+		let start = start.min(end);
+		let bytes = (self.interpreter.cell_size().bits() / 8) as usize;
+		let mut data = Vec::new();
+		for (index, cell) in (start..end).zip(memory.range(start, end)) {
+			if text {
+				data.extend(format!("{index} {cell}\n").bytes());
+			} else {
+				data.extend(&cell.to_le_bytes()[..bytes]);
+			}
+		}
+		fs::write(path, data).map_err(|err| format!("could not write {path}: {err}"))?;
+		println!("wrote cells {start} to {} to {path}", end.max(1) - 1);
+		Ok(())
+	}
+
+	fn dump_output(&self, path: &str) -> Result<(), String> {
+		let output = &self.interpreter.output;
+		fs::write(path, output).map_err(|err| format!("could not write {path}: {err}"))?;
+		println!("wrote {} bytes of output to {path}", output.len());
+		Ok(())
+	}
+
+	/// Writes the pointers, state, watchers and breakpoints to a file for people to read
+	fn dump_state(&self, path: &str) -> Result<(), String> {
+		let interpreter = &self.interpreter;
+		let c = &interpreter.program[interpreter.program_ptr];
+		let mut text = format!(
+			"source: {}\ninput: {}\nstate: {:?}\nsteps: {}\nprogram pointer: {} ({}) {}\n\
+			 memory pointer: {} (value {})\nmemory: {} cells\noutput: {} bytes\ninput read: {} of {} bytes\n",
+			self.source_paths().join(" "),
+			self.input_source,
+			interpreter.state,
+			interpreter.steps,
+			interpreter.program_ptr, self.location(c),
+			c.command,
+			interpreter.mem_ptr,
+			interpreter.memory[interpreter.mem_ptr],
+			interpreter.memory.len(),
+			interpreter.output.len(),
+			interpreter.input_ptr,
+			interpreter.input.len(),
+		);
+		text.push_str("watchers:\n");
+		for watcher in &interpreter.watchers {
+			let disabled = if watcher.disabled { " (disabled)" } else { "" };
+			text.push_str(&format!("  {watcher}{disabled}\n"));
+		}
+		text.push_str("breakpoints:\n");
+		for breakpoint in &interpreter.breakpoints {
+			let disabled = if breakpoint.disabled {
+				" (disabled)"
+			} else {
+				""
+			};
+			text.push_str(&format!(
+				"  {}{disabled}\n",
+				self.describe_breakpoint(breakpoint)
+			));
+		}
+		fs::write(path, text).map_err(|err| format!("could not write {path}: {err}"))?;
+		println!("wrote the state to {path}");
+		Ok(())
+	}
+
+	fn start_trace(&mut self, path: &str, last: Option<usize>) -> Result<(), String> {
+		let file =
+			fs::File::create(path).map_err(|err| format!("could not create {path}: {err}"))?;
+		self.interpreter
+			.start_trace(file, last)
+			.map_err(|err| format!("could not write trace: {err}"))
+	}
+
+	fn stop_trace(&mut self) {
+		if let Err(err) = self.interpreter.stop_trace() {
+			println!("{}", format!("could not write trace: {err}").red());
+		}
+	}
+
+	/// Writes out the trace so far, turning tracing off if the file can't be written
+	fn flush_trace(&mut self) {
+		if let Err(err) = self.interpreter.flush_trace() {
+			println!(
+				"{}",
+				format!("could not write trace, stopping it: {err}").red()
+			);
+			_ = self.interpreter.stop_trace();
+		}
+	}
+
+	/// Prints the input buffer with the consumed part dimmed and the next byte highlighted
+	fn show_input(&self) {
+		let input = &self.interpreter.input;
+		let ptr = self.interpreter.input_ptr;
+		print!("input: ");
+		for (index, &byte) in input.iter().enumerate() {
+			let text = escape_byte(byte);
+			match index.cmp(&ptr) {
+				Ordering::Less => print!("{}", text.dimmed()),
+				Ordering::Equal => print!("{}", text.on_cyan()),
+				Ordering::Greater => print!("{text}"),
+			}
+		}
+		println!();
+		println!(
+			"consumed: {ptr}, remaining: {}, total: {}",
+			input.len() - ptr,
+			input.len()
+		);
+	}
+
+	/// Prints the running loops, innermost first
+	fn list_loops(&self) {
+		if self.interpreter.loop_stack.is_empty() {
+			println!("not inside any loop");
+		}
+		for activation in self.interpreter.loop_stack.iter().rev() {
+			let c = &self.interpreter.program[activation.start];
+			println!(
+				"loop at {} ({}): entered at step {}, {} iterations",
+				activation.start,
+				self.location(c),
+				activation.entry_step,
+				activation.iterations
+			);
+		}
+	}
+
+	/// Prints the loop nesting like a call stack, innermost first
+	fn show_loop_stack(&self) {
+		if self.interpreter.loop_stack.is_empty() {
+			println!("not inside any loop");
+		}
+		for (depth, activation) in self.interpreter.loop_stack.iter().rev().enumerate() {
+			let c = &self.interpreter.program[activation.start];
+			let value = self.interpreter.memory.get(activation.cell).unwrap_or(0);
+			println!(
+				"#{depth} [ at {}, cell {} is {value}, iteration {}",
+				self.location(c),
+				activation.cell,
+				activation.iterations
+			);
+		}
+	}
+
+	fn list_snapshots(&self) {
+		if self.snapshots.is_empty() {
+			println!("no snapshots");
+		}
+		for (name, snapshot) in &self.snapshots {
+			println!(
+				"{name}: step {}, {:?} ({})",
+				snapshot.steps,
+				snapshot.state,
+				format_size(snapshot.size())
+			);
+		}
+	}
+
+	/// Prints the cells, pointers, steps and output that changed since a snapshot
+	fn show_diff(&self, name: &str) -> Result<(), String> {
+		let snapshot = self
+			.snapshots
+			.get(name)
+			.ok_or(format!("no snapshot named {name}"))?;
+		let diff = self.interpreter.diff(snapshot);
+		if diff.cells.is_empty() {
+			println!("no cells changed since {name}");
+		} else {
+			println!("cells changed since {name}:");
+		}
+		for &(index, old, new) in diff.cells.iter().take(DIFF_LIMIT) {
+			match self.labels.get(&index) {
+				Some(label) => println!("  {index} (${label}): {old} -> {new}"),
+				None => println!("  {index}: {old} -> {new}"),
+			}
+		}
+		if diff.cells.len() > DIFF_LIMIT {
+			println!(
+				"  …and {} more changed cells",
+				diff.cells.len() - DIFF_LIMIT
+			);
+		}
+		let (old, new) = diff.mem_ptr;
+		if old == new {
+			println!("memory pointer: still at {new}");
+		} else {
+			println!(
+				"memory pointer: {old} -> {new} ({:+})",
+				new as isize - old as isize
+			);
+		}
+		let program = &self.interpreter.program;
+		let location = |index: usize| {
+			let c = &program[index];
+			if index == program.len() - 1 {
+				"the end".to_string()
+			} else {
+				self.location(c)
+			}
+		};
+		let (old, new) = diff.program_ptr;
+		println!("program pointer: {} -> {}", location(old), location(new));
+		let (old, new) = diff.steps;
+		match new.cmp(&old) {
+			Ordering::Less => println!("steps: {} fewer", separated(old - new)),
+			_ => println!("steps: {} more", separated(new - old)),
+		}
+		match diff.new_output {
+			Some(output) if output.is_empty() => println!("output: nothing new"),
+			Some(output) => println!(
+				"output: {} bytes added \"{}\"",
+				output.len(),
+				output
+					.iter()
+					.map(|&byte| escape_byte(byte))
+					.collect::<String>()
+			),
+			None => println!(
+				"output: {} bytes now, it does not continue the {} bytes of the snapshot",
+				diff.output_len.1, diff.output_len.0
+			),
+		}
+		Ok(())
+	}
+
+	/// Adds code typed in the scratchpad to its text and to the end of the program. Code from a `[`
+	/// that is still open on waits there until its `]` is typed, and a `]` that closes nothing is
+	/// rejected together with the rest of the code
+	fn append_code(&mut self, code: &str) -> Result<(), String> {
+		let Some(scratchpad) = &mut self.scratchpad else {
+			return Err("code only adds to the scratchpad, started without a source file".into());
+		};
+		let source = &mut self.sources[0];
+		let old_len = source.text.len();
+		let line_number = source.text.lines().count() + 1;
+		source.text.push_str(code);
+		source.text.push('\n');
+		// parse from the open `[` on, blanking the code before it on its line to keep the columns
+		let (start_line, start_column) = scratchpad.open.unwrap_or((line_number, 1));
+		let mut lines: Vec<String> = source
+			.text
+			.lines()
+			.skip(start_line - 1)
+			.map(String::from)
+			.collect();
+		lines[0] = lines[0]
+			.chars()
+			.enumerate()
+			.map(|(i, c)| if i + 1 < start_column { ' ' } else { c })
+			.collect();
+		let PartialProgram {
+			program: mut code,
+			open,
+		} = match parse_partial(&lines.join("\n")) {
+			Ok(parsed) => parsed,
+			Err(mut errors) => {
+				for err in &mut errors {
+					err.line_number += start_line - 1;
+				}
+				let message = format_parse_errors(&self.sources, &errors);
+				self.sources[0].text.truncate(old_len);
+				println!("{message}");
+				return Err("the code was not added".into());
+			}
+		};
+		for c in &mut code {
+			c.line_number += start_line - 1;
+		}
+		scratchpad.open = open.map(|(line, column)| (line + start_line - 1, column));
+		if !self.asserts {
+			code = strip_asserts(code);
+		}
+		if self.optimize {
+			code = optimize_clear_loops(code);
+		}
+		self.interpreter.append_program(code);
+		let end = self.interpreter.program.last_mut().unwrap();
+		(end.line_number, end.column) = end_location(&self.sources[0].text);
+		self.program_text = ProgramText::new(&self.interpreter.program);
+		Ok(())
+	}
+
+	/// Reads and parses the source files again and starts the new program from the beginning.
+	/// Breakpoints on source locations are moved to the new program, and the old program is kept if parsing fails
+	fn reload(&mut self, keep_memory: bool) -> Result<(), String> {
+		if self.scratchpad.is_some() {
+			return Err("the scratchpad has no source file to reload".into());
+		}
+		let mut sources = Vec::new();
+		for file in &self.sources {
+			let text = fs::read_to_string(&file.path)
+				.map_err(|err| format!("could not read {}: {err}", file.path))?;
+			sources.push(SourceFile {
+				text,
+				..file.clone()
+			});
+		}
+		let mut program = match parse_sources(&sources) {
+			Ok(program) => program,
+			Err(errors) => {
+				println!("{errors}");
+				return Err("the program was not reloaded".into());
+			}
+		};
+		if !check_strict(&sources, self.strict) {
+			return Err("the program was not reloaded, because of --strict=error".into());
+		}
+		if is_empty_program(&program) {
+			println!("{}", "source contains no brainfuck commands".yellow());
+		}
+		if !self.asserts {
+			program = strip_asserts(program);
+		}
+		if self.optimize {
+			program = optimize_clear_loops(program);
+		}
+		let interpreter = &mut self.interpreter;
+		let locations: Vec<_> = interpreter
+			.breakpoints
+			.iter()
+			.map(|b| match b.target {
+				BreakTarget::Index(index) => Some(interpreter.program[index]),
+				_ => None,
+			})
+			.collect();
+		interpreter.load_program(program, keep_memory);
+		let moved: Vec<_> = locations
+			.into_iter()
+			.map(|location| {
+				location.map(|c| {
+					let index = interpreter.instruction_at(c.file, c.line_number, c.column);
+					(c, index)
+				})
+			})
+			.collect();
+		let mut moved = moved.into_iter();
+		let mut removed = Vec::new();
+		interpreter
+			.breakpoints
+			.retain_mut(|breakpoint| match moved.next().flatten() {
+				None => true,
+				Some((_, Some(index))) => {
+					breakpoint.target = BreakTarget::Index(index);
+					true
+				}
+				Some((c, None)) => {
+					removed.push(format!("#{} at {}", breakpoint.id, location(&c, &sources)));
+					false
+				}
+			});
+		for breakpoint in removed {
+			println!(
+				"{}",
+				format!("removed breakpoint {breakpoint}, there is no instruction there anymore")
+					.yellow()
+			);
+		}
+		if !self.snapshots.is_empty() {
+			println!(
+				"{}",
+				"removed all snapshots, they belong to the old program".yellow()
+			);
+			self.snapshots.clear();
+		}
+		for (index, name) in source_labels(&sources) {
+			_ = self.add_label(index, &name);
+		}
+		self.sources = sources;
+		self.program_text = ProgramText::new(&self.interpreter.program);
+		self.shown_memory.clone_from(&self.interpreter.memory);
+		println!(
+			"reloaded {} ({} instructions)",
+			self.source_paths().join(" "),
+			self.interpreter.program.len() - 1
+		);
+		Ok(())
+	}
+
+	fn show_last_write(&self, cell: usize) -> Result<(), String> {
+		let Some(write) = self.interpreter.last_write(cell) else {
+			if !self.interpreter.track_writes {
+				return Err("write tracking is off, turn it on with `set track-writes on`".into());
+			}
+			println!("cell {cell} has not been written since tracking started");
+			return Ok(());
+		};
+		let c = &self.interpreter.program[write.index];
+		println!(
+			"cell {cell} was last written at step {} by {} at {} (index {}): {} -> {}",
+			write.step,
+			c.command,
+			self.location(c),
+			write.index,
+			write.old,
+			write.new
+		);
+		Ok(())
+	}
+
+	/// Prints where the partner of the bracket at `index` is
+	fn show_match(&self, index: usize) {
+		let c = &self.interpreter.program[index];
+		let Some(partner) = c.command.matching_bracket() else {
+			println!("{} at {} is not a bracket", c.command, self.location(c));
+			return;
+		};
+		let p = &self.interpreter.program[partner];
+		println!(
+			"{} at {} (index {index}) matches {} at {} (index {partner})",
+			c.command,
+			self.location(c),
+			p.command,
+			self.location(p)
+		);
+	}
+
+	/// Lists the cells where `values` starts on the tape
+	fn find_in_memory(&self, values: &[u32]) {
+		let memory = &self.interpreter.memory;
+		let matches: Vec<usize> = (0..(memory.len() + 1).saturating_sub(values.len()))
+			.filter(|&start| (0..values.len()).all(|i| memory[start + i] == values[i]))
+			.collect();
+		let cells: Vec<_> = matches
+			.iter()
+			.take(FIND_LIMIT)
+			.map(|c| c.to_string())
+			.collect();
+		match matches.len() {
+			0 => println!("not found in memory"),
+			n if n > FIND_LIMIT => println!(
+				"found at cells {} and {} more matches",
+				cells.join(", "),
+				n - FIND_LIMIT
+			),
+			_ => println!("found at cells {}", cells.join(", ")),
+		}
+	}
+
+	/// Lists where the instructions in `pattern` appear in the program, like `[->+<]`
+	fn find_in_program(&self, pattern: &str) {
+		let program = &self.interpreter.program;
+		// the program as text, with the index of the instruction that starts at each offset
+		let mut text = String::new();
+		let mut starts = Vec::new();
+		for (index, c) in program[..program.len() - 1].iter().enumerate() {
+			starts.push((text.len(), index));
+			text.push_str(&c.command.to_string());
+		}
+		let matches: Vec<_> = starts
+			.iter()
+			.filter(|(offset, _)| text[*offset..].starts_with(pattern))
+			.map(|&(_, index)| index)
+			.collect();
+		if matches.is_empty() {
+			println!("{pattern} not found in the program");
+		}
+		for &index in matches.iter().take(FIND_LIMIT) {
+			let c = &program[index];
+			println!("index {index} at {}", self.location(c));
+		}
+		if matches.len() > FIND_LIMIT {
+			println!("{} more matches", matches.len() - FIND_LIMIT);
+		}
+	}
+
+	/// Prints the last `count` recorded writes to a cell
+	fn show_history(&self, cell: usize, count: usize) -> Result<(), String> {
+		let writes = self.interpreter.recorded_writes(cell).ok_or_else(|| {
+			format!("cell {cell} has not been recorded, start with `record {cell}`")
+		})?;
+		if writes.is_empty() {
+			println!("no writes to cell {cell} recorded yet");
+		}
+		for write in writes.iter().skip(writes.len().saturating_sub(count)) {
+			let c = &self.interpreter.program[write.index];
+			println!(
+				"step {:>8}: {} -> {} by {} at {}",
+				write.step,
+				write.old,
+				write.new,
+				c.command,
+				self.location(c)
+			);
+		}
+		Ok(())
+	}
+
+	/// Parses a cell index, or looks up a `$name` label
+	fn cell_index(&self, text: &str) -> Result<usize, String> {
+		match text.strip_prefix('$') {
+			Some(name) => self
+				.labels
+				.iter()
+				.find(|(_, label)| *label == name)
+				.map(|(&index, _)| index)
+				.ok_or_else(|| format!("no cell labeled {name}")),
+			None => parse_number(text)
+				.ok_or_else(|| format!("'{text}' is not a valid cell index or $label")),
+		}
+	}
+
+	/// Parses a cell index with an optional ==, !=, < or > prefix, like `>100` or `==$buffer`
+	fn pointer_condition(&self, text: &str) -> Result<WatchCondition, String> {
+		let split = text
+			.find(|c: char| c.is_ascii_digit() || c == '$')
+			.unwrap_or(text.len());
+		let comparison = match &text[..split] {
+			"" => Comparison::Equal,
+			comparison => Comparison::parse(comparison)
+				.ok_or("the pointer position must have an optional ==, !=, < or > prefix")?,
+		};
+		Ok(WatchCondition::Pointer(
+			comparison,
+			self.cell_index(&text[split..])?,
+		))
+	}
+
+	/// Names a cell, replacing any earlier name of that cell and moving the name if another cell had it
+	fn add_label(&mut self, index: usize, name: &str) -> Result<(), String> {
+		let name = name.strip_prefix('$').unwrap_or(name);
+		if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+			return Err("label names must not be empty or start with a digit".into());
+		}
+		self.labels.retain(|_, label| label != name);
+		self.labels.insert(index, name.to_string());
+		Ok(())
+	}
+
+	fn list_aliases(&self) {
+		if self.aliases.is_empty() {
+			println!("no aliases");
+		}
+		for (name, commands) in &self.aliases {
+			println!("{name} = {commands}");
+		}
+	}
+
+	fn list_labels(&self) {
+		if self.labels.is_empty() {
+			println!("no labels");
+		}
+		for (index, name) in &self.labels {
+			let value = self.interpreter.memory.get(*index).unwrap_or(0);
+			println!("${name}: cell {index} (value {value})");
+		}
+	}
+
+	fn add_breakpoint(
+		&mut self,
+		target: BreakTarget,
+		condition: Option<CellCondition>,
+		modifiers: BreakModifiers,
+	) -> Result<(), String> {
+		let description = self.describe_break_target(&target);
+		let id = self.interpreter.add_breakpoint(target, condition)?;
+		let breakpoint = self.interpreter.breakpoint_mut(id)?;
+		breakpoint.log = modifiers.log;
+		breakpoint.once = modifiers.once;
+		breakpoint.ignore = modifiers.ignore;
+		println!(
+			"breakpoint #{id} {description}{}",
+			describe_modifiers(breakpoint)
+		);
+		Ok(())
+	}
+
+	fn describe_break_target(&self, target: &BreakTarget) -> String {
+		match *target {
+			BreakTarget::Index(index) => {
+				let c = &self.interpreter.program[index];
+				format!("at {index} ({}) {}", self.location(c), c.command)
+			}
+			BreakTarget::Step(step) => format!("at step {step}"),
+			BreakTarget::Loop(limit) => format!("when a loop repeats more than {limit} times"),
+			BreakTarget::MemorySize(cells) => format!("when the tape grows past {cells} cells"),
+			BreakTarget::Output(ref text) => {
+				let text: String = text.iter().map(|&b| escape_byte(b)).collect();
+				format!("when the output ends with \"{text}\"")
+			}
+		}
+	}
+}
+
+/// Prints `name value` pairs with the values lined up
+fn print_aligned(rows: &[(&str, String)]) {
+	let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+	for (name, value) in rows {
+		println!("{name:width$}  {value}");
+	}
+}
+
+/// Range of at most `width` items out of `len` with `center` roughly in the middle
+fn centered_window(center: usize, width: usize, len: usize) -> (usize, usize) {
+	let width = width.max(1);
+	let start = (center + 1)
+		.saturating_sub(width.div_ceil(2))
+		.min(len.saturating_sub(width));
+	(start, (start + width).min(len))
+}
+
+/// Parses the source files in order into one program, or formats every error
+fn parse_sources(sources: &[SourceFile]) -> Result<Vec<DebugCommand>, String> {
+	let texts: Vec<&str> = sources.iter().map(|file| file.text.as_str()).collect();
+	parse_files(&texts).map_err(|errors| format_parse_errors(sources, &errors))
+}
+
+/// Every parse error, each followed by its source line with a caret under the problem
+fn format_parse_errors(sources: &[SourceFile], errors: &[ParseError]) -> String {
+	let mut out = Vec::new();
+	for err in errors {
+		let file = match sources.len() {
+			1 => String::new(),
+			_ => format!("in {} ", sources[err.file].name),
+		};
+		if let ParseErrorKind::Suspicious(_) = err.kind {
+			out.push(format!("warning: {file}{err}").yellow().to_string());
+		} else {
+			out.push(format!("Parser error: {file}{err}").red().to_string());
+		}
+		let line = sources[err.file].line(err.line_number).unwrap_or_default();
+		let indent: String = line
+			.chars()
+			.take(err.column - 1)
+			.map(|c| if c == '\t' { '\t' } else { ' ' })
+			.collect();
+		let margin = " ".repeat(err.line_number.to_string().len());
+		out.push(format!("{} | {line}", err.line_number));
+		out.push(format!("{margin} | {indent}{}", "^".cyan()));
+	}
+	out.join("\n")
+}
+
+/// Prints the `--strict` warnings about the sources all together, and returns whether they may run
+fn check_strict(sources: &[SourceFile], strict: Strict) -> bool {
+	if strict == Strict::Off {
+		return true;
+	}
+	let warnings: Vec<_> = sources
+		.iter()
+		.enumerate()
+		.flat_map(|(index, source)| strict_warnings(&source.text, index))
+		.collect();
+	if warnings.is_empty() {
+		return true;
+	}
+	// on stderr, so they do not mix with the output of --run
+	eprintln!(
+		"{}",
+		format!(
+			"--strict: {} characters look like they may be mistyped commands",
+			warnings.len()
+		)
+		.yellow()
+	);
+	eprintln!("{}", format_parse_errors(sources, &warnings));
+	strict != Strict::Error
+}
+
+/// A bracket colored by its nesting depth, cycling through a few colors
+fn rainbow(command: &Command, depth: usize) -> String {
+	const COLORS: [AnsiColors; 6] = [
+		AnsiColors::Yellow,
+		AnsiColors::Magenta,
+		AnsiColors::Blue,
+		AnsiColors::Green,
+		AnsiColors::Cyan,
+		AnsiColors::Red,
+	];
+	command.color(COLORS[depth % COLORS.len()]).to_string()
+}
+
+/// Parses a whole number, allowing `_` or `,` between the digits like in `1_000_000`, or hex like `0x100`
+fn parse_number<T: TryFrom<u64>>(text: &str) -> Option<T> {
+	let (digits, radix) = match text.strip_prefix("0x") {
+		Some(hex) => (hex, 16),
+		None => (text, 10),
+	};
+	if digits.is_empty() || digits.starts_with(['_', ',']) || digits.ends_with(['_', ',']) {
+		return None;
+	}
+	let value = u64::from_str_radix(&digits.replace(['_', ','], ""), radix).ok()?;
+	value.try_into().ok()
+}
+
+/// A numeric argument of `command`, with the same error for every command
+fn number_arg<T: TryFrom<u64>>(command: &str, text: &str) -> Result<T, String> {
+	parse_number(text).ok_or_else(|| format!("{command}: expected a whole number, got '{text}'"))
+}
+
+/// A count argument, where zero would do nothing and is most likely a mistake
+fn count_arg(command: &str, text: &str) -> Result<usize, String> {
+	match number_arg(command, text) {
+		Ok(0) | Err(_) => Err(format!(
+			"{command}: expected a positive integer, got '{text}'"
+		)),
+		count => count,
+	}
+}
+
+/// `text` without its last `words` words
+fn without_last_words(text: &str, words: usize) -> &str {
+	let mut text = text.trim_end();
+	for _ in 0..words {
+		text = text
+			.rsplit_once(char::is_whitespace)
+			.map_or("", |(rest, _)| rest.trim_end());
+	}
+	text
+}
+
+/// What is left of a command line after its first `words` words, keeping the spacing inside it
+fn rest_of_line(line: &str, words: usize) -> &str {
+	let mut rest = line.trim();
+	for _ in 0..words {
+		rest = rest
+			.trim_start()
+			.split_once(char::is_whitespace)
+			.map_or("", |(_, rest)| rest);
+	}
+	rest.trim()
+}
+
+/// A count with commas between groups of three digits, like 1,204,934
+fn separated(count: usize) -> String {
+	let digits = count.to_string();
+	let mut out = String::new();
+	for (i, digit) in digits.chars().enumerate() {
+		if i > 0 && (digits.len() - i).is_multiple_of(3) {
+			out.push(',');
+		}
+		out.push(digit);
+	}
+	out
+}
+
+impl MemView {
+	fn parse(text: &str) -> Option<Self> {
+		match text {
+			"dec" => Some(Self::Dec),
+			"hex" => Some(Self::Hex),
+			"ascii" => Some(Self::Ascii),
+			"mixed" => Some(Self::Mixed),
+			_ => None,
+		}
+	}
+
+	fn format(self, cell: u32, size: CellSize) -> String {
+		let printable = match char::from_u32(cell) {
+			Some(c) if c.is_ascii_graphic() || c == ' ' => c,
+			_ => '.',
+		};
+		match self {
+			MemView::Dec => cell.to_string(),
+			MemView::Hex => format!("{cell:0width$X}", width = size.bits() as usize / 4),
+			MemView::Ascii => printable.to_string(),
+			MemView::Mixed => format!("{cell} {printable}"),
+		}
+	}
+}
+
+impl OutputView {
+	fn parse(text: &str) -> Option<Self> {
+		match text {
+			"raw" => Some(Self::Raw),
+			"escaped" => Some(Self::Escaped),
+			"hex" => Some(Self::Hex),
+			_ => None,
+		}
+	}
+
+	fn format(self, bytes: &[u8]) -> String {
+		match self {
+			OutputView::Raw => String::from_utf8_lossy(bytes).into_owned(),
+			OutputView::Escaped => String::from_utf8_lossy(bytes)
+				.chars()
+				.map(|c| match c {
+					'\n' => "⏎\n".to_string(),
+					c if c.is_control() => format!("\\x{:02x}", c as u32),
+					c => c.to_string(),
+				})
+				.collect(),
+			OutputView::Hex => bytes
+				.chunks(16)
+				.enumerate()
+				.map(|(row, chunk)| {
+					let hex: Vec<_> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+					let text: String = chunk
+						.iter()
+						.map(|&b| {
+							if b.is_ascii_graphic() || b == b' ' {
+								b as char
+							} else {
+								'.'
+							}
+						})
+						.collect();
+					format!("{:08x}  {:<47}  |{text}|", row * 16, hex.join(" "))
+				})
+				.collect::<Vec<_>>()
+				.join("\n"),
+		}
+	}
+}
+
+/// The program laid out like its source, with instructions colored by how often they ran,
+/// followed by the most executed locations
+fn format_profile(program: &[DebugCommand], counts: &[u64], sources: &[SourceFile]) -> String {
+	let max = counts.iter().copied().max().unwrap_or(0).max(1);
+	let mut out = colored_source(program, counts, |text, count| match count * 3 / max {
+		_ if count == 0 => text.dimmed().to_string(),
+		0 => text.green().to_string(),
+		1 => text.yellow().to_string(),
+		_ => text.red().to_string(),
+	});
+
+	let mut hottest: Vec<_> = program.iter().zip(counts).filter(|(_, &n)| n > 0).collect();
+	hottest.sort_by(|a, b| b.1.cmp(a.1));
+	out.push_str("location  instruction  count\n");
+	for (c, count) in hottest.into_iter().take(10) {
+		let location = location(c, sources);
+		out.push_str(&format!(
+			"{location:<9} {:<12} {count}\n",
+			c.command.to_string()
+		));
+	}
+	out
+}
+
+/// The program laid out like its source, with instructions that never ran dimmed,
+/// followed by how many ran and the lines that were never reached
+fn format_coverage(program: &[DebugCommand], counts: &[u64], sources: &[SourceFile]) -> String {
+	let mut out = colored_source(program, counts, |text, count| match count {
+		0 => text.dimmed().to_string(),
+		_ => text.to_string(),
+	});
+	let total = program.len() - 1;
+	let executed = counts[..total].iter().filter(|&&n| n > 0).count();
+	out.push_str(&format!(
+		"{executed}/{total} instructions executed ({}%)\n",
+		(executed * 100).checked_div(total).unwrap_or(100)
+	));
+
+	// lines where no instruction ran, with lines that have no instructions not breaking a range
+	let mut line_reached: BTreeMap<(usize, usize), bool> = BTreeMap::new();
+	for (c, &count) in program[..total].iter().zip(counts) {
+		*line_reached.entry((c.file, c.line_number)).or_default() |= count > 0;
+	}
+	let mut ranges: Vec<(usize, usize, usize)> = Vec::new();
+	let mut in_range = false;
+	for (&(file, line), &reached) in &line_reached {
+		match (reached, in_range, ranges.last_mut()) {
+			(false, true, Some(range)) if range.0 == file => range.2 = line,
+			(false, _, _) => ranges.push((file, line, line)),
+			_ => (),
+		}
+		in_range = !reached;
+	}
+	if !ranges.is_empty() {
+		let ranges: Vec<_> = ranges
+			.iter()
+			.map(|&(file, start, end)| {
+				let file = match sources.len() {
+					1 => String::new(),
+					_ => format!("{}:", sources[file].name),
+				};
+				match start == end {
+					true => format!("{file}{start}"),
+					false => format!("{file}{start}-{end}"),
+				}
+			})
+			.collect();
+		out.push_str(&format!("never reached: lines {}\n", ranges.join(", ")));
+	}
+	out
+}
+
+/// Instruction counts in the program and how often each kind ran, followed by memory, input and output use
+/// The row of `stats` an instruction is counted in
+fn stats_kind(command: &Command) -> &'static str {
+	match command {
+		Command::Inc => "+",
+		Command::Dec => "-",
+		Command::Right => ">",
+		Command::Left => "<",
+		Command::Read => ",",
+		Command::Write => ".",
+		Command::BeginLoop(_) => "[",
+		Command::EndLoop(_) => "]",
+		Command::Clear => "[-]",
+		Command::Break => "!",
+		Command::Assert(_) => "{=N}",
+		Command::End => "",
+	}
+}
+
+fn format_stats(interpreter: &BFInterpreter) -> String {
+	let program = &interpreter.program[..interpreter.program.len() - 1];
+	let mut depth = 0usize;
+	let mut deepest = 0;
+	for c in program {
+		match c.command {
+			Command::BeginLoop(_) => {
+				depth += 1;
+				deepest = deepest.max(depth);
+			}
+			Command::EndLoop(_) => depth -= 1,
+			_ => (),
+		}
+	}
+	let mut out = format!(
+		"program: {} instructions, deepest loop nesting {deepest}\n",
+		program.len()
+	);
+	out.push_str("instruction  in program   executed\n");
+	let mut total_executed = 0;
+	for kind in ["+", "-", ">", "<", ",", ".", "[", "]", "[-]", "!", "{=N}"] {
+		let (count, executed) = program
+			.iter()
+			.zip(interpreter.coverage())
+			.filter(|(c, _)| stats_kind(&c.command) == kind)
+			.fold((0, 0), |(count, executed), (_, &n)| {
+				(count + 1, executed + n)
+			});
+		if count == 0 {
+			continue;
+		}
+		total_executed += executed;
+		out.push_str(&format!("{kind:<12} {count:>10} {executed:>10}\n"));
+	}
+	out.push_str(&format!(
+		"{:<12} {:>10} {total_executed:>10}\n",
+		"total",
+		program.len()
+	));
+	let reads = program
+		.iter()
+		.zip(interpreter.coverage())
+		.filter(|(c, _)| matches!(c.command, Command::Read))
+		.map(|(_, &n)| n)
+		.sum::<u64>();
+	out.push_str(&format!(
+		"steps: {}, memory: {} cells (at most {}), reads: {reads} ({} input bytes used), output: {} bytes\n",
+		interpreter.steps,
+		interpreter.memory.len(),
+		interpreter.max_memory_len(),
+		interpreter.input_ptr,
+		interpreter.output.len()
+	));
+	out
+}
+
+/// The program laid out like its source, each instruction colored by its count
+fn colored_source(
+	program: &[DebugCommand],
+	counts: &[u64],
+	color: impl Fn(&str, u64) -> String,
+) -> String {
+	let mut out = String::new();
+	let (mut file, mut line, mut column) = (0, 1, 1);
+	for (c, &count) in program.iter().zip(counts) {
+		if let Command::End = c.command {
+			break;
+		}
+		if c.file != file {
+			// each file starts on a new line
+			out.push('\n');
+			(file, line, column) = (c.file, 1, 1);
+		}
+		while line < c.line_number {
+			out.push('\n');
+			line += 1;
+			column = 1;
+		}
+		while column < c.column {
+			out.push(' ');
+			column += 1;
+		}
+		let text = c.command.to_string();
+		column += text.len();
+		out.push_str(&color(&text, count));
+	}
+	out.push('\n');
+	out
+}
+
+/// A byte as printable text, with `\n`, `\t`, `\r` and other non-printable bytes escaped
+fn escape_byte(byte: u8) -> String {
+	match byte {
+		b'\n' => "\\n".into(),
+		b'\t' => "\\t".into(),
+		b'\r' => "\\r".into(),
+		b'\\' => "\\\\".into(),
+		b' ' => " ".into(),
+		b if b.is_ascii_graphic() => (b as char).to_string(),
+		b => format!("\\x{b:02x}"),
+	}
+}
+
+/// Bytes as text that `unescape` turns back into them, also escaping `;`, `"` and a leading
+/// space so the text survives as the end of a command
+fn escape(bytes: &[u8]) -> String {
+	bytes
+		.iter()
+		.enumerate()
+		.map(|(i, &byte)| match byte {
+			b';' | b'"' => format!("\\x{byte:02x}"),
+			b' ' if i == 0 => "\\x20".into(),
+			byte => escape_byte(byte),
+		})
+		.collect()
+}
+
+/// How a breakpoint stops, for showing after its target
+fn describe_modifiers(breakpoint: &Breakpoint) -> String {
+	let mut out = String::new();
+	if breakpoint.log {
+		out.push_str(" (log)");
+	}
+	if breakpoint.once {
+		out.push_str(" (once)");
+	}
+	if breakpoint.ignore > 0 {
+		out.push_str(&format!(" (ignoring the next {})", breakpoint.ignore));
+	}
+	out
+}
+
+/// A byte written like `parse_value` takes values, `\xNN`, or `"text"`, for `break output` and `watch outchar`
+fn parse_output_bytes(command: &str, text: &str) -> Result<Vec<u8>, String> {
+	if let Some(quoted) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+		return unescape(quoted);
+	}
+	if text.starts_with('\\') {
+		return match unescape(text)?[..] {
+			[byte] => Ok(vec![byte]),
+			_ => Err(format!(
+				"{command}: expected a byte or \"text\", got '{text}'"
+			)),
+		};
+	}
+	let value = parse_value(text).map_err(|err| format!("{command}: {err}"))?;
+	let byte = u8::try_from(value)
+		.map_err(|_| format!("{command}: {value} is not a byte, the output is made of bytes"))?;
+	Ok(vec![byte])
+}
+
+/// Turns `\n`, `\t`, `\r`, `\0`, `\\` and `\xNN` escapes into the bytes they stand for
+fn unescape(text: &str) -> Result<Vec<u8>, String> {
+	let mut out = Vec::new();
+	let mut chars = text.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			let mut buf = [0; 4];
+			out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+			continue;
+		}
+		match chars.next() {
+			Some('n') => out.push(b'\n'),
+			Some('t') => out.push(b'\t'),
+			Some('r') => out.push(b'\r'),
+			Some('0') => out.push(0),
+			Some('\\') => out.push(b'\\'),
+			Some('x') => {
+				let digits: String = chars.by_ref().take(2).collect();
+				let byte = u8::from_str_radix(&digits, 16).map_err(|_| {
+					format!("\\x must be followed by two hex digits, not {digits:?}")
+				})?;
+				out.push(byte);
+			}
+			Some(other) => return Err(format!("unknown escape \\{other}")),
+			None => return Err("input ends with a lone \\".into()),
+		}
+	}
+	Ok(out)
+}
+
+/// Formats a byte count with a binary unit
+fn format_size(bytes: usize) -> String {
+	match bytes {
+		0..1024 => format!("{bytes} B"),
+		1024..1048576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+		_ => format!("{:.1} MiB", bytes as f64 / 1048576.0),
+	}
 }
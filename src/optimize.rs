@@ -0,0 +1,416 @@
+//! A faster representation of the program, used by `run` when nothing needs to observe individual instructions.
+
+use crate::{
+	link_brackets, BFInterpreter, Command, DebugCommand, RunRecord, State, UndoRecord,
+	INTERRUPT_CHECK_INTERVAL,
+};
+
+#[derive(Debug)]
+pub(crate) struct FastProgram {
+	pub(crate) ops: Vec<FastOp>,
+	/// The op that starts at each program index, if any
+	op_at: Vec<Option<usize>>,
+	pub(crate) transfers: Vec<Transfer>,
+}
+
+/// A loop like `[->+>++<<]` that adds multiples of the current cell to other cells and then clears it
+#[derive(Debug)]
+pub(crate) struct Transfer {
+	/// Offset from the loop cell and what is added there per iteration
+	pub(crate) targets: Vec<(isize, u32)>,
+	/// The furthest the pointer goes to either side while running the body
+	pub(crate) min_offset: isize,
+	pub(crate) max_offset: isize,
+	/// Offset, position in the body and amount of the last instruction writing each cell
+	last_writes: Vec<(isize, usize, u32)>,
+}
+
+#[derive(Debug)]
+pub(crate) struct FastOp {
+	pub(crate) op: Op,
+	/// Index of the first instruction this op was made from
+	pub(crate) source_index: usize,
+	/// Number of instructions folded into this op
+	len: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Op {
+	/// Added with wrapping at 32 bits, then masked down to the cell size
+	Add(u32),
+	Move(isize),
+	Clear,
+	/// Jump to the matching `JumpIfNotZero` when the cell is zero
+	JumpIfZero(usize),
+	/// Jump to the matching `JumpIfZero` when the cell is not zero
+	JumpIfNotZero(usize),
+	/// A whole loop run at once, see `transfers`
+	Transfer(usize),
+	/// Anything else, executed by the regular interpreter
+	Single,
+}
+
+/// Replaces `[-]` and `[+]` loops with a single `Clear`
+pub fn optimize_clear_loops(program: Vec<DebugCommand>) -> Vec<DebugCommand> {
+	let mut out: Vec<DebugCommand> = Vec::with_capacity(program.len());
+	for c in program {
+		out.push(c);
+		if let [.., start, body, end] = &out[..] {
+			if matches!(
+				(start.command, body.command, end.command),
+				(
+					Command::BeginLoop(_),
+					Command::Inc | Command::Dec,
+					Command::EndLoop(_)
+				)
+			) {
+				out.truncate(out.len() - 2);
+				out.last_mut().unwrap().command = Command::Clear;
+			}
+		}
+	}
+	link_brackets(&mut out);
+	out
+}
+
+impl FastProgram {
+	/// Folds repeated instructions together, and turns transfer loops into single ops if `transfer_loops` is set
+	pub(crate) fn compile(program: &[DebugCommand], transfer_loops: bool) -> Self {
+		let mut ops: Vec<FastOp> = Vec::new();
+		let mut op_at = vec![None; program.len()];
+		let mut transfers = Vec::new();
+		let mut loop_starts = Vec::new();
+		let mut skip_to = 0;
+		for (index, c) in program.iter().enumerate() {
+			if index < skip_to {
+				continue;
+			}
+			if let (Command::BeginLoop(end), true) = (c.command, transfer_loops) {
+				if let Some(transfer) = Transfer::detect(&program[index + 1..end]) {
+					op_at[index] = Some(ops.len());
+					ops.push(FastOp {
+						op: Op::Transfer(transfers.len()),
+						source_index: index,
+						len: end - index + 1,
+					});
+					transfers.push(transfer);
+					skip_to = end + 1;
+					continue;
+				}
+			}
+			let op = match c.command {
+				Command::Inc => Op::Add(1),
+				Command::Dec => Op::Add(u32::MAX),
+				Command::Right => Op::Move(1),
+				Command::Left => Op::Move(-1),
+				Command::Clear => Op::Clear,
+				Command::BeginLoop(_) => {
+					loop_starts.push(ops.len());
+					Op::JumpIfZero(usize::MAX)
+				}
+				Command::EndLoop(_) => {
+					let start = loop_starts.pop().unwrap();
+					ops[start].op = Op::JumpIfZero(ops.len());
+					Op::JumpIfNotZero(start)
+				}
+				_ => Op::Single,
+			};
+			if let (Some(last), true) = (ops.last_mut(), index > 0) {
+				if same_kind(&program[index - 1].command, &c.command) {
+					match (&mut last.op, op) {
+						(Op::Add(total), Op::Add(n)) => *total = total.wrapping_add(n),
+						(Op::Move(total), Op::Move(n)) => *total += n,
+						_ => unreachable!(),
+					}
+					last.len += 1;
+					continue;
+				}
+			}
+			op_at[index] = Some(ops.len());
+			ops.push(FastOp {
+				op,
+				source_index: index,
+				len: 1,
+			});
+		}
+		Self {
+			ops,
+			op_at,
+			transfers,
+		}
+	}
+}
+
+impl Transfer {
+	/// Checks that a loop body only adds and moves, returns to where it started
+	/// and takes exactly one from the loop cell per iteration
+	fn detect(body: &[DebugCommand]) -> Option<Self> {
+		let mut offset = 0isize;
+		let (mut min_offset, mut max_offset) = (0, 0);
+		let mut added: Vec<(isize, u32)> = Vec::new();
+		let mut last_writes: Vec<(isize, usize, u32)> = Vec::new();
+		for (position, c) in body.iter().enumerate() {
+			let amount = match c.command {
+				Command::Inc => 1,
+				Command::Dec => u32::MAX,
+				Command::Right | Command::Left => {
+					offset += if let Command::Right = c.command {
+						1
+					} else {
+						-1
+					};
+					min_offset = min_offset.min(offset);
+					max_offset = max_offset.max(offset);
+					continue;
+				}
+				_ => return None,
+			};
+			match added.iter_mut().find(|(o, _)| *o == offset) {
+				Some((_, total)) => *total = total.wrapping_add(amount),
+				None => added.push((offset, amount)),
+			}
+			last_writes.retain(|&(o, _, _)| o != offset);
+			last_writes.push((offset, position, amount));
+		}
+		if offset != 0 {
+			return None;
+		}
+		let (own, targets): (Vec<_>, Vec<_>) = added.into_iter().partition(|&(o, _)| o == 0);
+		if own != [(0, u32::MAX)] {
+			return None;
+		}
+		Some(Self {
+			targets: targets.into_iter().filter(|&(_, n)| n != 0).collect(),
+			min_offset,
+			max_offset,
+			last_writes,
+		})
+	}
+}
+
+/// Whether two instructions in a row can be folded into one op
+fn same_kind(a: &Command, b: &Command) -> bool {
+	matches!(
+		(a, b),
+		(Command::Inc, Command::Inc)
+			| (Command::Dec, Command::Dec)
+			| (Command::Right, Command::Right)
+			| (Command::Left, Command::Left)
+	)
+}
+
+impl BFInterpreter {
+	/// Runs like `run`, but with repeated instructions folded together.
+	/// The history gets a single record for the whole run instead of one per instruction
+	pub(crate) fn run_fast(&mut self) {
+		let fast = self
+			.fast_program
+			.take()
+			.unwrap_or_else(|| FastProgram::compile(&self.program, self.transfer_loops));
+		let before = (self.history_limit > 0).then(|| RunRecord {
+			snapshot: self.snapshot(),
+			input_exhausted_at: self.input_exhausted_at,
+			max_memory: self.max_memory,
+			last_wrap_step: self.last_wrap_step,
+		});
+		let history_limit = std::mem::take(&mut self.history_limit);
+		// finish a partially stepped through op the slow way first
+		while self.state == State::Running && fast.op_at[self.program_ptr].is_none() {
+			self.step_internal();
+		}
+		// how often each op ran, added to the coverage of its instructions at the end
+		let mut op_counts = vec![0u64; fast.ops.len()];
+		if self.state == State::Running {
+			let mut i = fast.op_at[self.program_ptr].unwrap();
+			let mut ops_run = 0usize;
+			loop {
+				let op_index = i;
+				let op = &fast.ops[i];
+				if self.max_steps.is_some_and(|max| self.steps + op.len >= max) {
+					// close to the limit, go one instruction at a time to stop exactly on it
+					self.program_ptr = op.source_index;
+					while self.state == State::Running {
+						self.step_internal();
+					}
+					break;
+				}
+				ops_run += 1;
+				if ops_run.is_multiple_of(INTERRUPT_CHECK_INTERVAL) {
+					self.check_interrupt();
+					if self.state != State::Running {
+						self.program_ptr = op.source_index;
+						break;
+					}
+				}
+				match op.op {
+					Op::Add(n) => {
+						self.memory[self.mem_ptr] =
+							self.memory[self.mem_ptr].wrapping_add(n) & self.cell_size.max();
+						let last = op.source_index + op.len - 1;
+						let amount = match self.program[last].command {
+							Command::Inc => 1,
+							_ => u32::MAX,
+						};
+						let old =
+							self.memory[self.mem_ptr].wrapping_sub(amount) & self.cell_size.max();
+						self.record_fast_write(self.mem_ptr, self.steps + op.len, last, old);
+					}
+					Op::Clear => {
+						let old = self.memory[self.mem_ptr];
+						self.memory[self.mem_ptr] = 0;
+						self.record_fast_write(self.mem_ptr, self.steps + 1, op.source_index, old);
+					}
+					Op::Move(n)
+						if (n < 0 && self.mem_ptr < n.unsigned_abs())
+							|| self.tape_size.is_some_and(|size| {
+								self.mem_ptr.wrapping_add_signed(n) >= size
+							}) || self
+							.memory_limit
+							.is_some_and(|limit| self.mem_ptr.wrapping_add_signed(n) >= limit) =>
+					{
+						// leaves the tape at some point, let the regular interpreter handle the edge
+						self.program_ptr = op.source_index;
+						self.step(op.len);
+						if self.state != State::Running {
+							break;
+						}
+						i = fast.op_at[self.program_ptr].unwrap();
+						continue;
+					}
+					Op::Move(n) => {
+						self.mem_ptr = self.mem_ptr.wrapping_add_signed(n);
+						if self.mem_ptr >= self.memory.len() {
+							self.memory.resize(self.mem_ptr + 1);
+							self.max_memory = self.max_memory.max(self.memory.len());
+						}
+					}
+					Op::JumpIfZero(end) => {
+						if self.memory[self.mem_ptr] == 0 {
+							i = end;
+						} else {
+							self.enter_loop(op.source_index);
+						}
+					}
+					Op::JumpIfNotZero(start) => {
+						let loop_start = fast.ops[start].source_index;
+						if self.memory[self.mem_ptr] != 0 {
+							i = start;
+							self.repeat_loop(loop_start);
+						} else {
+							self.exit_loop(loop_start);
+						}
+					}
+					Op::Transfer(t) => {
+						let transfer = &fast.transfers[t];
+						let iterations = self.memory[self.mem_ptr] as usize;
+						let steps = 1 + iterations * (op.len - 1);
+						let fits = self.mem_ptr >= transfer.min_offset.unsigned_abs()
+							&& self.tape_size.is_none_or(|size| {
+								self.mem_ptr + (transfer.max_offset as usize) < size
+							}) && self.memory_limit.is_none_or(|limit| {
+							self.mem_ptr + (transfer.max_offset as usize) < limit
+						});
+						if iterations > 0
+							&& (!fits
+								|| self.max_steps.is_some_and(|max| self.steps + steps >= max))
+						{
+							// let the regular interpreter handle the edge of the tape or the step limit
+							self.program_ptr = op.source_index;
+							while self.state == State::Running
+								&& self.program_ptr < op.source_index + op.len
+							{
+								self.step_internal();
+							}
+							if self.state != State::Running {
+								break;
+							}
+							i = fast.op_at[self.program_ptr].unwrap();
+							continue;
+						}
+						if iterations > 0 {
+							let reached = self.mem_ptr + transfer.max_offset as usize + 1;
+							if reached > self.memory.len() {
+								self.memory.resize(reached);
+								self.max_memory = self.max_memory.max(reached);
+							}
+							for &(offset, amount) in &transfer.targets {
+								let cell =
+									&mut self.memory[self.mem_ptr.wrapping_add_signed(offset)];
+								*cell = cell.wrapping_add(amount.wrapping_mul(iterations as u32))
+									& self.cell_size.max();
+							}
+							self.memory[self.mem_ptr] = 0;
+							// the last iteration starts after the `[` and all earlier iterations
+							let last_iteration = self.steps + 1 + (iterations - 1) * (op.len - 1);
+							for &(offset, position, amount) in &transfer.last_writes {
+								let cell = self.mem_ptr.wrapping_add_signed(offset);
+								let old =
+									self.memory[cell].wrapping_sub(amount) & self.cell_size.max();
+								self.record_fast_write(
+									cell,
+									last_iteration + position + 1,
+									op.source_index + 1 + position,
+									old,
+								);
+							}
+						}
+						self.steps += steps;
+						self.coverage[op.source_index] += 1;
+						for counter in
+							&mut self.coverage[op.source_index + 1..op.source_index + op.len]
+						{
+							*counter += iterations as u64;
+						}
+						i += 1;
+						continue;
+					}
+					Op::Single => {
+						self.program_ptr = op.source_index;
+						self.step_internal();
+						if self.state != State::Running {
+							break;
+						}
+						i = fast.op_at[self.program_ptr].unwrap();
+						continue;
+					}
+				}
+				self.steps += op.len;
+				op_counts[op_index] += 1;
+				i += 1;
+			}
+		}
+		for (op, &count) in fast.ops.iter().zip(&op_counts) {
+			for counter in &mut self.coverage[op.source_index..op.source_index + op.len] {
+				*counter += count;
+			}
+		}
+		self.fast_program = Some(fast);
+		self.history_limit = history_limit;
+		if let Some(before) = before.filter(|b| b.snapshot.steps != self.steps) {
+			self.push_history(UndoRecord::Run(Box::new(before)));
+		}
+	}
+
+	/// Puts back the state from before a `run` through the folded program
+	pub(crate) fn undo_run(&mut self, run: RunRecord) {
+		let RunRecord {
+			snapshot,
+			input_exhausted_at,
+			max_memory,
+			last_wrap_step,
+		} = run;
+		self.memory = snapshot.memory;
+		self.mem_ptr = snapshot.mem_ptr;
+		self.program_ptr = snapshot.program_ptr;
+		self.output = snapshot.output;
+		self.input_ptr = snapshot.input_ptr;
+		self.send_eof = snapshot.send_eof;
+		self.loop_stack = snapshot.loop_stack;
+		self.steps = snapshot.steps;
+		self.state = snapshot.state;
+		self.stop_reason = None;
+		self.input_exhausted_at = input_exhausted_at;
+		self.max_memory = max_memory;
+		self.last_wrap_step = last_wrap_step;
+	}
+}
@@ -0,0 +1,242 @@
+//! Command line arguments.
+
+use brainfuck::{CellSize, EofBehavior, Language, TapeMode};
+
+use crate::{count_arg, number_arg, style::ColorChoice, unescape};
+
+pub const USAGE: &str =
+	"usage: brainfuck <source_file>... [input_file | -i <input> | --input <file>] [options]";
+
+/// Shown by `--help`, the README has the longer explanations
+pub const HELP: &str = "\
+Options:
+  -i <input>                 Use a string as the input, with \\n, \\xNN and other escapes
+  --input <file>             Same as giving the input file as the second argument, needed
+                             to give more than one source file
+  --no-optimize              Keep clear loops as loops, to step through them
+  --no-asserts               Leave out the {=N} asserts in the source
+  -x <script>                Run the debugger commands in a file before the prompt
+  --run                      Run without the debugger, writing the output to stdout
+  --bench <n>                Time n runs of the program and show the speed
+  --emit c|rust <file>       Translate the program into a C or Rust file instead of running it
+  --eof=0|255|unchanged      What a read does once the input is exhausted
+  --numeric-io               , reads a decimal number and . writes one, instead of bytes
+  --cell-size 8|16|32        Number of bits in a memory cell
+  --tape-size <n>            Give the tape a fixed number of cells
+  --tape-mode error|wrap     Whether moving off a fixed tape stops or wraps around
+  --mem-init <file>          Start with the bytes of a file in the first cells
+  --max-steps <n>            Stop once n steps have run
+  --max-memory <n>           Stop instead of growing the tape past n cells
+  --loop-detect              Stop when the program gets back to a state it was in before
+  --strict[=warn|error]      Warn about characters that may be mistyped commands, or refuse to run
+  --ignore-breaks            Skip ! in the source instead of stopping, the default with --run
+  --stop-at-breaks           Stop at ! in the source even with --run
+  --profile                  Count how many times each instruction runs
+  --expect <file>            Compare the output against a file when the program ends
+  --session <file>           Continue a session saved with save-session
+  --tui                      Use a full screen interface instead of the prompt
+  --json                     Print the state as JSON at every prompt, for other tools
+  --dap                      Serve the Debug Adapter Protocol on stdin/stdout
+  --dap-port <port>          Serve the Debug Adapter Protocol on a TCP port
+  --color=auto|always|never  Whether the output is colored
+  --no-color                 Same as --color=never
+  --help                     Show this help
+  --version                  Show the version";
+
+#[derive(Debug, Default)]
+pub struct Options {
+	/// Parsed in order into one program
+	pub source_files: Vec<String>,
+	pub input: Input,
+	pub eof_behavior: EofBehavior,
+	/// Read and write decimal numbers instead of bytes
+	pub numeric_io: bool,
+	pub run_only: bool,
+	/// Time this many runs of the program instead of debugging it
+	pub bench: Option<usize>,
+	/// Write the program as source in another language instead of debugging it
+	pub emit: Option<(Language, String)>,
+	pub optimize: bool,
+	/// Keep the `{=N}` asserts in the source
+	pub asserts: bool,
+	pub script: Option<String>,
+	pub session_file: Option<String>,
+	pub profile: bool,
+	pub max_steps: Option<usize>,
+	pub max_memory: Option<usize>,
+	pub loop_detect: bool,
+	pub strict: Strict,
+	pub cell_size: CellSize,
+	pub tape_size: Option<usize>,
+	pub tape_mode: TapeMode,
+	/// File whose bytes the tape starts with
+	pub mem_init: Option<String>,
+	/// File with the output the program should produce
+	pub expect: Option<String>,
+	pub json: bool,
+	/// Serve the Debug Adapter Protocol instead of showing the prompt
+	pub dap: bool,
+	pub dap_port: Option<u16>,
+	/// Full screen interface instead of the prompt
+	pub tui: bool,
+	pub color: ColorChoice,
+	/// Whether `!` in the source is skipped, by default only with `--run`
+	pub ignore_breaks: Option<bool>,
+	pub help: bool,
+	pub version: bool,
+}
+
+/// What `--strict` does about characters that may be mistyped commands
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Strict {
+	/// Everything that is not a command is a comment
+	#[default]
+	Off,
+	Warn,
+	/// Warn and refuse to run the program
+	Error,
+}
+
+/// Where the program input comes from
+#[derive(Debug, Default)]
+pub enum Input {
+	#[default]
+	None,
+	File(String),
+	Inline(Vec<u8>),
+}
+
+impl Options {
+	pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+		let mut options = Self {
+			optimize: true,
+			asserts: true,
+			..Self::default()
+		};
+		let mut positional = Vec::new();
+		let mut input_flags = 0;
+		while let Some(arg) = args.next() {
+			let mut value = |name: &str, what: &str| {
+				args.next().ok_or_else(|| format!("{name} requires {what}"))
+			};
+			match arg.as_str() {
+				"-h" | "--help" => options.help = true,
+				"-V" | "--version" => options.version = true,
+				"--run" => options.run_only = true,
+				"--bench" => {
+					let value = value("--bench", "a number of runs")?;
+					options.bench = Some(count_arg("--bench", &value)?);
+				}
+				"--emit" => {
+					let language = value("--emit", "c or rust and a file")?;
+					let language =
+						Language::parse(&language).ok_or("--emit must be one of c or rust")?;
+					options.emit = Some((language, value("--emit", "a file")?));
+				}
+				"--profile" => options.profile = true,
+				"--numeric-io" => options.numeric_io = true,
+				"--loop-detect" => options.loop_detect = true,
+				"--strict" => options.strict = Strict::Warn,
+				"--json" => options.json = true,
+				"--tui" if cfg!(unix) => options.tui = true,
+				"--tui" => return Err("--tui is only supported on unix terminals".into()),
+				"--dap" => options.dap = true,
+				"--dap-port" => {
+					let value = value("--dap-port", "a port number")?;
+					options.dap = true;
+					options.dap_port = Some(
+						value
+							.parse()
+							.map_err(|_| "--dap-port must be a valid port number")?,
+					);
+				}
+				"--no-optimize" => options.optimize = false,
+				"--no-asserts" => options.asserts = false,
+				"--no-color" => options.color = ColorChoice::Never,
+				"--ignore-breaks" => options.ignore_breaks = Some(true),
+				"--stop-at-breaks" => options.ignore_breaks = Some(false),
+				"-x" => options.script = Some(value("-x", "a script file")?),
+				"--mem-init" => options.mem_init = Some(value("--mem-init", "a file")?),
+				"--expect" => options.expect = Some(value("--expect", "a file")?),
+				"--session" => options.session_file = Some(value("--session", "a session file")?),
+				"-i" => {
+					options.input = Input::Inline(unescape(&value("-i", "an input string")?)?);
+					input_flags += 1;
+				}
+				"--max-steps" => {
+					let value = value("--max-steps", "a step count")?;
+					options.max_steps = Some(number_arg("--max-steps", &value)?);
+				}
+				"--max-memory" => {
+					let value = value("--max-memory", "a number of cells")?;
+					options.max_memory = Some(number_arg("--max-memory", &value)?);
+				}
+				"--cell-size" => {
+					let value = value("--cell-size", "8, 16 or 32")?;
+					options.cell_size =
+						CellSize::parse(&value).ok_or("--cell-size must be one of 8, 16 or 32")?;
+				}
+				"--tape-size" => {
+					let value = value("--tape-size", "a number of cells")?;
+					options.tape_size = Some(number_arg("--tape-size", &value)?);
+				}
+				"--tape-mode" => {
+					let value = value("--tape-mode", "error or wrap")?;
+					options.tape_mode = TapeMode::parse(&value)
+						.ok_or("--tape-mode must be one of error or wrap")?;
+				}
+				"--input" | "--input-file" => {
+					options.input = Input::File(value(&arg, "a file")?);
+					input_flags += 1;
+				}
+				_ => {
+					if let Some(mode) = arg.strip_prefix("--eof=") {
+						options.eof_behavior = EofBehavior::parse(mode)
+							.ok_or("--eof must be one of 0, 255 or unchanged")?;
+					} else if let Some(mode) = arg.strip_prefix("--strict=") {
+						options.strict = match mode {
+							"warn" => Strict::Warn,
+							"error" => Strict::Error,
+							_ => return Err("--strict must be one of warn or error".into()),
+						};
+					} else if let Some(when) = arg.strip_prefix("--color=") {
+						options.color = ColorChoice::parse(when)
+							.ok_or("--color must be one of always, never or auto")?;
+					} else if arg.starts_with('-') && arg.len() > 1 {
+						return Err(format!("unknown option {arg}"));
+					} else {
+						positional.push(arg);
+					}
+				}
+			}
+		}
+		if options.help || options.version {
+			return Ok(options);
+		}
+		// without an input flag, a second file is the input like it always was
+		if input_flags == 0 && positional.len() == 2 {
+			options.input = Input::File(positional.pop().unwrap_or_default());
+			input_flags += 1;
+		} else if input_flags == 0 && positional.len() > 2 {
+			return Err(
+				"give the input with -i or --input when there are several source files, -i '' for none"
+					.into(),
+			);
+		}
+		options.source_files = positional;
+		if options.tape_mode == TapeMode::Wrap && options.tape_size.is_none() {
+			return Err("--tape-mode wrap needs a --tape-size".into());
+		}
+		if input_flags > 1 {
+			return Err(
+				"only one of -i, --input-file or an input file argument can be given".into(),
+			);
+		}
+		if options.session_file.is_some() && (!options.source_files.is_empty() || input_flags > 0) {
+			return Err(
+				"--session already names the source file and input, no others can be given".into(),
+			);
+		}
+		Ok(options)
+	}
+}
@@ -0,0 +1,27 @@
+//! Counting how often each instruction is executed.
+
+use crate::BFInterpreter;
+
+impl BFInterpreter {
+	/// Starts counting executions per instruction, keeping any earlier counts
+	pub fn start_profile(&mut self) {
+		if self.profile.is_none() {
+			self.profile = Some(vec![0; self.program.len()]);
+		}
+	}
+
+	pub fn stop_profile(&mut self) {
+		self.profile = None;
+	}
+
+	pub fn reset_profile(&mut self) {
+		if let Some(counts) = &mut self.profile {
+			counts.fill(0);
+		}
+	}
+
+	/// Execution count of every instruction, if profiling is on
+	pub fn profile(&self) -> Option<&[u64]> {
+		self.profile.as_deref()
+	}
+}
@@ -0,0 +1,91 @@
+//! Saving the whole debugger state to a file so it can be picked up again later.
+
+use std::{collections::BTreeMap, fs};
+
+use brainfuck::{
+	program_hash, Breakpoint, CellSize, EofBehavior, MemoryWatcher, Snapshot, TapeMode,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::Debugger;
+
+/// Bumped whenever the file layout changes
+const SESSION_VERSION: u32 = 5;
+
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+	version: u32,
+	/// Absolute paths of the program sources, in order
+	pub source_paths: Vec<String>,
+	/// Whether the program was loaded with clear loops optimized
+	pub optimize: bool,
+	program_hash: u64,
+	pub input: Vec<u8>,
+	pub eof_behavior: EofBehavior,
+	#[serde(default)]
+	pub numeric_io: bool,
+	pub cell_size: CellSize,
+	pub tape_size: Option<usize>,
+	pub tape_mode: TapeMode,
+	state: Snapshot,
+	watchers: Vec<MemoryWatcher>,
+	breakpoints: Vec<Breakpoint>,
+	labels: BTreeMap<usize, String>,
+}
+
+impl Session {
+	pub fn capture(debugger: &Debugger) -> Self {
+		let interpreter = &debugger.interpreter;
+		Self {
+			version: SESSION_VERSION,
+			source_paths: debugger.sources.iter().map(|f| f.path.clone()).collect(),
+			optimize: debugger.optimize,
+			program_hash: program_hash(&interpreter.program),
+			input: interpreter.input.clone(),
+			eof_behavior: interpreter.eof_behavior,
+			numeric_io: interpreter.numeric_io,
+			cell_size: interpreter.cell_size(),
+			tape_size: interpreter.tape_size(),
+			tape_mode: interpreter.tape_mode(),
+			state: interpreter.snapshot(),
+			watchers: interpreter.watchers.clone(),
+			breakpoints: interpreter.breakpoints.clone(),
+			labels: debugger.labels.clone(),
+		}
+	}
+
+	pub fn save(&self, path: &str) -> Result<(), String> {
+		let text = serde_json::to_string(self).map_err(|err| err.to_string())?;
+		fs::write(path, text).map_err(|err| format!("could not write {path}: {err}"))
+	}
+
+	pub fn load(path: &str) -> Result<Self, String> {
+		let text =
+			fs::read_to_string(path).map_err(|err| format!("could not read {path}: {err}"))?;
+		let version = serde_json::from_str::<serde_json::Value>(&text)
+			.map_err(|err| format!("{path} is not a session file: {err}"))?
+			.get("version")
+			.and_then(|v| v.as_u64());
+		if version != Some(SESSION_VERSION as u64) {
+			return Err(format!(
+				"{path} has an unsupported session version {version:?}, expected {SESSION_VERSION}"
+			));
+		}
+		serde_json::from_str(&text).map_err(|err| format!("{path} is not a session file: {err}"))
+	}
+
+	/// Puts the saved state into a debugger that has the same program loaded
+	pub fn apply(self, debugger: &mut Debugger) -> Result<(), String> {
+		let interpreter = &mut debugger.interpreter;
+		if program_hash(&interpreter.program) != self.program_hash {
+			return Err(format!(
+				"{} has changed since the session was saved",
+				self.source_paths.join(" ")
+			));
+		}
+		interpreter.restore(&self.state);
+		interpreter.set_debug_points(self.watchers, self.breakpoints);
+		debugger.labels = self.labels;
+		Ok(())
+	}
+}
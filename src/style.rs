@@ -0,0 +1,127 @@
+//! Colors in the output. Everything is colored through `Paint`, so colors can be turned off in one place.
+
+use std::{
+	env,
+	io::{stdout, IsTerminal},
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+use owo_colors::{AnsiColors, Style, Styled};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// When to color the output, from `--color`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+	/// Only on a terminal, and not when `NO_COLOR` is set
+	#[default]
+	Auto,
+	Always,
+	Never,
+}
+
+impl ColorChoice {
+	pub fn parse(text: &str) -> Option<Self> {
+		match text {
+			"auto" => Some(Self::Auto),
+			"always" => Some(Self::Always),
+			"never" => Some(Self::Never),
+			_ => None,
+		}
+	}
+}
+
+pub fn init(choice: ColorChoice) {
+	let enabled = match choice {
+		ColorChoice::Always => true,
+		ColorChoice::Never => false,
+		ColorChoice::Auto => {
+			env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && stdout().is_terminal()
+		}
+	};
+	ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether colors are used, when a marker has to be shown some other way without them
+pub fn enabled() -> bool {
+	ENABLED.load(Ordering::Relaxed)
+}
+
+/// The colors used by the debugger, leaving the text plain when colors are off
+pub trait Paint {
+	fn paint(&self, style: Style) -> Styled<&Self> {
+		if enabled() {
+			style.style(self)
+		} else {
+			Style::new().style(self)
+		}
+	}
+
+	fn red(&self) -> Styled<&Self> {
+		self.paint(Style::new().red())
+	}
+
+	fn green(&self) -> Styled<&Self> {
+		self.paint(Style::new().green())
+	}
+
+	fn yellow(&self) -> Styled<&Self> {
+		self.paint(Style::new().yellow())
+	}
+
+	fn blue(&self) -> Styled<&Self> {
+		self.paint(Style::new().blue())
+	}
+
+	fn magenta(&self) -> Styled<&Self> {
+		self.paint(Style::new().magenta())
+	}
+
+	fn cyan(&self) -> Styled<&Self> {
+		self.paint(Style::new().cyan())
+	}
+
+	fn color(&self, color: AnsiColors) -> Styled<&Self> {
+		self.paint(Style::new().color(color))
+	}
+
+	fn on_red(&self) -> Styled<&Self> {
+		self.paint(Style::new().on_red())
+	}
+
+	fn on_yellow(&self) -> Styled<&Self> {
+		self.paint(Style::new().on_yellow())
+	}
+
+	fn on_magenta(&self) -> Styled<&Self> {
+		self.paint(Style::new().on_magenta())
+	}
+
+	fn on_cyan(&self) -> Styled<&Self> {
+		self.paint(Style::new().on_cyan())
+	}
+
+	fn dimmed(&self) -> Styled<&Self> {
+		self.paint(Style::new().dimmed())
+	}
+
+	fn bold(&self) -> Styled<&Self> {
+		self.paint(Style::new().bold())
+	}
+
+	/// Not a color, so it is kept even when colors are off
+	fn reversed(&self) -> Styled<&Self> {
+		Style::new().reversed().style(self)
+	}
+
+	/// A marker for the full screen interface, which has no room for a caret line. Reversed without colors
+	fn highlight(&self, color: AnsiColors) -> Styled<&Self> {
+		if enabled() {
+			Style::new().on_color(color).style(self)
+		} else {
+			self.reversed()
+		}
+	}
+}
+
+impl<T: ?Sized> Paint for T {}
@@ -0,0 +1,191 @@
+//! The memory tape, stored in chunks that are only allocated once something is written to them.
+//! Other per-cell data is stored the same way, so a far away cell does not need room for every cell before it.
+
+use std::ops::{Index, IndexMut};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+const CHUNK_BITS: usize = 12;
+const CHUNK_SIZE: usize = 1 << CHUNK_BITS;
+
+/// Returned for cells in chunks that were never written
+static ZERO: u32 = 0;
+
+/// Values by index that start out as `T::default()`, with a chunk allocated the first time something in it is written
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Chunks<T> {
+	chunks: Vec<Option<Box<[T; CHUNK_SIZE]>>>,
+}
+
+#[cold]
+fn new_chunk<T: Copy + Default>() -> Box<[T; CHUNK_SIZE]> {
+	Box::new([T::default(); CHUNK_SIZE])
+}
+
+impl<T> Default for Chunks<T> {
+	fn default() -> Self {
+		Self { chunks: Vec::new() }
+	}
+}
+
+impl<T: Copy + Default> Chunks<T> {
+	/// `None` when nothing in the chunk of `index` has been written
+	#[inline]
+	pub(crate) fn get(&self, index: usize) -> Option<&T> {
+		let chunk = self.chunks.get(index >> CHUNK_BITS)?.as_ref()?;
+		Some(&chunk[index & (CHUNK_SIZE - 1)])
+	}
+
+	#[inline]
+	pub(crate) fn get_mut(&mut self, index: usize) -> &mut T {
+		let chunk_index = index >> CHUNK_BITS;
+		if chunk_index >= self.chunks.len() {
+			self.chunks.resize(chunk_index + 1, None);
+		}
+		let chunk = self.chunks[chunk_index].get_or_insert_with(new_chunk);
+		&mut chunk[index & (CHUNK_SIZE - 1)]
+	}
+
+	/// Drops every chunk past the one holding index `len - 1`
+	fn truncate(&mut self, len: usize) {
+		self.chunks.truncate(len.div_ceil(CHUNK_SIZE));
+	}
+
+	/// Every value in an allocated chunk
+	pub(crate) fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+		self.chunks
+			.iter_mut()
+			.flatten()
+			.flat_map(|chunk| chunk.iter_mut())
+	}
+
+	pub(crate) fn clear(&mut self) {
+		self.chunks.clear();
+	}
+
+	fn heap_size(&self) -> usize {
+		let allocated = self.chunks.iter().flatten().count();
+		self.chunks.len() * size_of::<usize>() + allocated * CHUNK_SIZE * size_of::<T>()
+	}
+}
+
+/// Zero-initialized cells, where a program that walks far to the right
+/// only uses memory for the parts of the tape it actually writes.
+/// Writing past `len` panics like it would for a `Vec`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tape {
+	cells: Chunks<u32>,
+	len: usize,
+}
+
+impl Tape {
+	/// A tape of `len` cells, all zero
+	pub fn new(len: usize) -> Self {
+		let mut tape = Self::default();
+		tape.resize(len);
+		tape
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	pub fn get(&self, index: usize) -> Option<u32> {
+		(index < self.len).then(|| self[index])
+	}
+
+	pub fn push(&mut self, value: u32) {
+		self.resize(self.len + 1);
+		if value != 0 {
+			let last = self.len - 1;
+			self[last] = value;
+		}
+	}
+
+	/// Grows the tape with zeros, or cuts it off at `len`
+	pub fn resize(&mut self, len: usize) {
+		if len < self.len {
+			// cells past the end are kept zero, so growing again does not bring old values back
+			if let Some(Some(chunk)) = self.cells.chunks.get_mut(len >> CHUNK_BITS) {
+				let end = (self.len - (len & !(CHUNK_SIZE - 1))).min(CHUNK_SIZE);
+				chunk[len & (CHUNK_SIZE - 1)..end].fill(0);
+			}
+			self.cells.truncate(len);
+		}
+		self.len = len;
+	}
+
+	pub fn truncate(&mut self, len: usize) {
+		if len < self.len {
+			self.resize(len);
+		}
+	}
+
+	/// The cells from `start` up to but not including `end`
+	pub fn range(&self, start: usize, end: usize) -> impl Iterator<Item = u32> + '_ {
+		(start..end.min(self.len)).map(|index| self[index])
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+		self.range(0, self.len)
+	}
+
+	/// Applies `f` to every cell that is in an allocated chunk, the others stay zero
+	pub fn map_written(&mut self, f: impl Fn(u32) -> u32) {
+		for cell in self.cells.values_mut() {
+			*cell = f(*cell);
+		}
+	}
+
+	/// Approximate number of bytes used to store the tape
+	pub fn heap_size(&self) -> usize {
+		self.cells.heap_size()
+	}
+}
+
+impl Index<usize> for Tape {
+	type Output = u32;
+
+	#[inline]
+	fn index(&self, index: usize) -> &u32 {
+		debug_assert!(index < self.len, "cell {index} is past the end of the tape");
+		self.cells.get(index).unwrap_or(&ZERO)
+	}
+}
+
+impl IndexMut<usize> for Tape {
+	#[inline]
+	fn index_mut(&mut self, index: usize) -> &mut u32 {
+		assert!(index < self.len, "cell {index} is past the end of the tape");
+		self.cells.get_mut(index)
+	}
+}
+
+impl From<Vec<u32>> for Tape {
+	fn from(cells: Vec<u32>) -> Self {
+		let mut tape = Self::new(cells.len());
+		for (index, value) in cells.into_iter().enumerate() {
+			if value != 0 {
+				tape[index] = value;
+			}
+		}
+		tape
+	}
+}
+
+/// Stored as a plain list of cells, like the tape used to be
+impl Serialize for Tape {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_seq(self.iter())
+	}
+}
+
+impl<'de> Deserialize<'de> for Tape {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		Vec::deserialize(deserializer).map(Self::from)
+	}
+}
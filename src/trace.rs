@@ -0,0 +1,110 @@
+//! Logging every executed instruction to a file.
+
+use std::{
+	collections::VecDeque,
+	fs::File,
+	io::{self, BufWriter, Seek, Write},
+};
+
+use crate::{BFInterpreter, DebugCommand};
+
+#[derive(Debug)]
+pub(crate) struct Trace {
+	file: BufWriter<File>,
+	/// When set, only the last `limit` entries are kept and the file is rewritten with them on every flush
+	ring: Option<Ring>,
+}
+
+#[derive(Debug)]
+struct Ring {
+	limit: usize,
+	entries: VecDeque<TraceEntry>,
+}
+
+#[derive(Debug)]
+struct TraceEntry {
+	step: usize,
+	index: usize,
+	mem_ptr: usize,
+	cell: u32,
+}
+
+impl TraceEntry {
+	fn write(&self, out: &mut impl Write, program: &[DebugCommand]) -> io::Result<()> {
+		let c = &program[self.index];
+		writeln!(
+			out,
+			"{} {} {}:{} {} {} {}",
+			self.step, self.index, c.line_number, c.column, c.command, self.mem_ptr, self.cell
+		)
+	}
+}
+
+impl Trace {
+	fn flush(&mut self, program: &[DebugCommand]) -> io::Result<()> {
+		if let Some(ring) = &self.ring {
+			self.file.rewind()?;
+			self.file.get_ref().set_len(0)?;
+			for entry in &ring.entries {
+				entry.write(&mut self.file, program)?;
+			}
+		}
+		self.file.flush()
+	}
+}
+
+impl BFInterpreter {
+	/// Starts writing a line for each executed instruction to `file`, or only the last `last` of them
+	pub fn start_trace(&mut self, file: File, last: Option<usize>) -> io::Result<()> {
+		self.stop_trace()?;
+		self.trace = Some(Trace {
+			file: BufWriter::new(file),
+			ring: last.map(|limit| Ring {
+				limit,
+				entries: VecDeque::with_capacity(limit),
+			}),
+		});
+		Ok(())
+	}
+
+	pub fn stop_trace(&mut self) -> io::Result<()> {
+		self.flush_trace()?;
+		self.trace = None;
+		Ok(())
+	}
+
+	pub fn is_tracing(&self) -> bool {
+		self.trace.is_some()
+	}
+
+	/// Writes out buffered trace lines, so the file is complete up to the current step
+	pub fn flush_trace(&mut self) -> io::Result<()> {
+		match &mut self.trace {
+			Some(trace) => trace.flush(&self.program),
+			None => Ok(()),
+		}
+	}
+
+	/// Logs the instruction at `index`, which was just executed
+	pub(crate) fn trace_step(&mut self, index: usize) {
+		let Some(trace) = &mut self.trace else {
+			return;
+		};
+		let entry = TraceEntry {
+			step: self.steps,
+			index,
+			mem_ptr: self.mem_ptr,
+			cell: self.memory[self.mem_ptr],
+		};
+		match &mut trace.ring {
+			Some(ring) => {
+				if ring.entries.len() >= ring.limit {
+					ring.entries.pop_front();
+				}
+				ring.entries.push_back(entry);
+			}
+			// write errors also show up when flushing, which is where they get reported
+			None => _ = entry.write(&mut trace.file, &self.program),
+		}
+	}
+}
@@ -0,0 +1,501 @@
+//! A full screen interface with fixed panes, drawn with plain terminal escape codes.
+
+use std::{
+	collections::HashSet,
+	fs::File,
+	io::{stdout, Read, Seek, SeekFrom, Write},
+	mem,
+	ops::ControlFlow,
+	os::fd::AsRawFd,
+	sync::atomic::{self, AtomicBool},
+};
+
+use brainfuck::{BreakTarget, Command, State, StopReason};
+use owo_colors::AnsiColors;
+
+use crate::{execute_command, style::Paint, Debugger, OutputView};
+
+/// Lines of command output kept for the messages pane
+const MESSAGE_HISTORY: usize = 200;
+const HELP: &str =
+	"s/enter step  n next  f finish  r run  b back  : command  up/down scroll memory  q quit";
+
+/// Puts the terminal back the way it was when dropped
+pub(crate) struct Terminal {
+	original: libc::termios,
+}
+
+impl Terminal {
+	/// Switches to a separate screen that reads keys as they are pressed, for `what` that needs it
+	pub(crate) fn enter(what: &str) -> Result<Self, String> {
+		// SAFETY: termios is plain data and the calls only read and write the struct we pass
+		unsafe {
+			if libc::isatty(0) == 0 || libc::isatty(1) == 0 {
+				return Err(format!("{what} needs a terminal"));
+			}
+			let mut original: libc::termios = mem::zeroed();
+			if libc::tcgetattr(0, &mut original) != 0 {
+				return Err("could not read the terminal settings".into());
+			}
+			let mut raw = original;
+			// keep signals on so Ctrl-C still stops a running program
+			raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+			// reads give up after a tenth of a second, so a lone escape can be told apart from a sequence
+			raw.c_cc[libc::VMIN] = 0;
+			raw.c_cc[libc::VTIME] = 1;
+			libc::tcsetattr(0, libc::TCSANOW, &raw);
+			print!("\x1b[?1049h\x1b[?25l");
+			_ = stdout().flush();
+			Ok(Self { original })
+		}
+	}
+
+	fn size() -> (usize, usize) {
+		// SAFETY: winsize is plain data filled in by the ioctl
+		unsafe {
+			let mut size: libc::winsize = mem::zeroed();
+			if libc::ioctl(0, libc::TIOCGWINSZ, &mut size) == 0 && size.ws_col > 0 {
+				(size.ws_col as usize, size.ws_row as usize)
+			} else {
+				(80, 24)
+			}
+		}
+	}
+}
+
+impl Drop for Terminal {
+	fn drop(&mut self) {
+		print!("\x1b[?25h\x1b[?1049l");
+		_ = stdout().flush();
+		// SAFETY: restores the settings read in `enter`
+		unsafe {
+			libc::tcsetattr(0, libc::TCSANOW, &self.original);
+		}
+	}
+}
+
+pub(crate) enum Key {
+	Char(char),
+	Enter,
+	Backspace,
+	Escape,
+	Up,
+	Down,
+	PageUp,
+	PageDown,
+	Home,
+}
+
+/// Waits a moment for a key press
+pub(crate) fn read_key() -> Option<Key> {
+	let byte = read_byte()?;
+	Some(match byte {
+		b'\r' | b'\n' => Key::Enter,
+		0x7f | 0x08 => Key::Backspace,
+		0x1b => match (read_byte(), read_byte()) {
+			(None, _) => Key::Escape,
+			(Some(b'['), Some(b'A')) => Key::Up,
+			(Some(b'['), Some(b'B')) => Key::Down,
+			(Some(b'['), Some(b'H')) => Key::Home,
+			(Some(b'['), Some(code @ (b'5' | b'6'))) => {
+				read_byte();
+				if code == b'5' {
+					Key::PageUp
+				} else {
+					Key::PageDown
+				}
+			}
+			_ => return None,
+		},
+		b if b.is_ascii() && !b.is_ascii_control() => Key::Char(b as char),
+		_ => return None,
+	})
+}
+
+fn read_byte() -> Option<u8> {
+	let mut byte = 0u8;
+	// SAFETY: reads at most one byte into `byte`
+	let read = unsafe { libc::read(0, (&mut byte as *mut u8).cast(), 1) };
+	(read == 1).then_some(byte)
+}
+
+/// What typing currently goes to
+enum Mode {
+	Keys,
+	Command(String),
+	/// The program is waiting for input
+	ProgramInput(String),
+}
+
+/// Collects what commands print while the screen is taken over, by pointing stdout at a file
+struct Capture {
+	file: File,
+	stdout: i32,
+}
+
+impl Capture {
+	fn new() -> Result<Self, String> {
+		let path = std::env::temp_dir().join(format!("bf-debugger-tui-{}", std::process::id()));
+		let file = File::options()
+			.read(true)
+			.write(true)
+			.create(true)
+			.truncate(true)
+			.open(&path)
+			.map_err(|err| format!("could not create {}: {err}", path.display()))?;
+		// the open file keeps working after it is removed
+		_ = std::fs::remove_file(&path);
+		// SAFETY: duplicates the stdout file descriptor so it can be restored
+		let stdout = unsafe { libc::dup(1) };
+		Ok(Self { file, stdout })
+	}
+
+	fn run<T>(&mut self, f: impl FnOnce() -> T) -> (T, String) {
+		_ = self.file.set_len(0);
+		_ = self.file.seek(SeekFrom::Start(0));
+		_ = stdout().flush();
+		// SAFETY: both are open file descriptors
+		unsafe { libc::dup2(self.file.as_raw_fd(), 1) };
+		let result = f();
+		_ = stdout().flush();
+		// SAFETY: puts back the descriptor saved in `new`
+		unsafe { libc::dup2(self.stdout, 1) };
+		let mut text = String::new();
+		_ = self.file.seek(SeekFrom::Start(0));
+		_ = self.file.read_to_string(&mut text);
+		(result, text)
+	}
+}
+
+struct Tui<'a> {
+	debugger: &'a mut Debugger,
+	mode: Mode,
+	messages: Vec<String>,
+	/// First memory row shown, or `None` to keep the pointer in view
+	memory_scroll: Option<usize>,
+	capture: Capture,
+}
+
+pub fn run(debugger: &mut Debugger, interrupt: &AtomicBool) -> Result<(), String> {
+	let _terminal = Terminal::enter("--tui")?;
+	// the output pane shows the output, streaming it would only fill the messages
+	debugger.stream = false;
+	let mut tui = Tui {
+		debugger,
+		mode: Mode::Keys,
+		messages: Vec::new(),
+		memory_scroll: None,
+		capture: Capture::new()?,
+	};
+	let mut dirty = true;
+	loop {
+		if dirty {
+			tui.draw();
+		}
+		let Some(key) = read_key() else {
+			dirty = false;
+			continue;
+		};
+		dirty = true;
+		interrupt.store(false, atomic::Ordering::Relaxed);
+		if tui.handle_key(key).is_break() {
+			return Ok(());
+		}
+	}
+}
+
+impl Tui<'_> {
+	fn handle_key(&mut self, key: Key) -> ControlFlow<()> {
+		match (&mut self.mode, key) {
+			(Mode::Keys, Key::Char('q')) => return ControlFlow::Break(()),
+			(Mode::Keys, Key::Char('s') | Key::Enter) => return self.command("step"),
+			(Mode::Keys, Key::Char('n')) => return self.command("next"),
+			(Mode::Keys, Key::Char('f')) => return self.command("finish"),
+			(Mode::Keys, Key::Char('r')) => return self.command("run"),
+			(Mode::Keys, Key::Char('b')) => return self.command("back"),
+			(Mode::Keys, Key::Char(':')) => self.mode = Mode::Command(String::new()),
+			(Mode::Keys, Key::Up) => self.scroll_memory(-1),
+			(Mode::Keys, Key::Down) => self.scroll_memory(1),
+			(Mode::Keys, Key::PageUp) => self.scroll_memory(-8),
+			(Mode::Keys, Key::PageDown) => self.scroll_memory(8),
+			(Mode::Keys, Key::Home) => self.memory_scroll = None,
+			(Mode::Command(text) | Mode::ProgramInput(text), Key::Char(c)) => text.push(c),
+			(Mode::Command(text) | Mode::ProgramInput(text), Key::Backspace) => {
+				text.pop();
+			}
+			(Mode::Command(_), Key::Escape) => self.mode = Mode::Keys,
+			(Mode::Command(text), Key::Enter) => {
+				let line = mem::take(text);
+				self.mode = Mode::Keys;
+				return self.command(&line);
+			}
+			(Mode::ProgramInput(text), Key::Enter) => {
+				let mut line = mem::take(text);
+				line.push('\n');
+				self.mode = Mode::Keys;
+				self.debugger
+					.shown_memory
+					.clone_from(&self.debugger.interpreter.memory);
+				self.debugger.interpreter.provide_input(line.as_bytes());
+				self.debugger.interpreter.step_once();
+			}
+			_ => (),
+		}
+		ControlFlow::Continue(())
+	}
+
+	/// Runs a command like the prompt would, keeping what it prints for the messages pane
+	fn command(&mut self, line: &str) -> ControlFlow<()> {
+		let debugger = &mut *self.debugger;
+		debugger
+			.shown_memory
+			.clone_from(&debugger.interpreter.memory);
+		let (result, printed) = self.capture.run(|| execute_command(debugger, line));
+		self.debugger.flush_trace();
+		self.messages.extend(printed.lines().map(String::from));
+		match result {
+			Ok(ControlFlow::Break(())) => return ControlFlow::Break(()),
+			Ok(ControlFlow::Continue(())) => (),
+			Err(err) => self.messages.push(err.red().to_string()),
+		}
+		let extra = self.messages.len().saturating_sub(MESSAGE_HISTORY);
+		self.messages.drain(..extra);
+		if self.debugger.interpreter.state == State::WaitingForInput {
+			self.mode = Mode::ProgramInput(String::new());
+		}
+		ControlFlow::Continue(())
+	}
+
+	fn scroll_memory(&mut self, rows: isize) {
+		let (_, _, pointer_row) = self.memory_layout(Terminal::size().0);
+		let row = self.memory_scroll.unwrap_or(pointer_row);
+		self.memory_scroll = Some(row.saturating_add_signed(rows));
+	}
+
+	/// Cell width, cells per row and the row of the memory pointer
+	fn memory_layout(&self, width: usize) -> (usize, usize, usize) {
+		let size = self.debugger.interpreter.cell_size();
+		let cell_width = self.debugger.mem_view.format(size.max(), size).len().max(3);
+		let per_row = (width.saturating_sub(8) / (cell_width + 1)).max(1);
+		(
+			cell_width,
+			per_row,
+			self.debugger.interpreter.mem_ptr / per_row,
+		)
+	}
+
+	fn draw(&self) {
+		let (width, height) = Terminal::size();
+		let interpreter = &self.debugger.interpreter;
+		let fixed = 5;
+		let rest = height.saturating_sub(fixed).max(4);
+		let memory_rows = (rest / 4).clamp(1, 8);
+		let output_rows = (rest / 5).max(1);
+		let message_rows = (rest / 5).max(1);
+		let program_rows = rest
+			.saturating_sub(memory_rows + output_rows + message_rows)
+			.max(1);
+
+		let mut lines = Vec::with_capacity(height);
+		let stop = match &interpreter.stop_reason {
+			Some(StopReason::Watcher { watcher, .. }) => format!("  watcher #{}", watcher.id),
+			Some(StopReason::Breakpoint(breakpoint)) => format!("  breakpoint #{}", breakpoint.id),
+			Some(StopReason::CodeBreakpoint(_)) => "  !".into(),
+			Some(StopReason::Skipped { .. }) => "  skipped".into(),
+			Some(StopReason::OffTape(_)) => "  off the tape".into(),
+			Some(StopReason::Assertion { actual, .. }) => format!("  assert, cell is {actual}"),
+			Some(StopReason::Cycle { first_step }) => format!("  same as step {first_step}"),
+			None => String::new(),
+		};
+		lines.push(
+			format!(
+				" {:?}{stop}  steps: {}  mem ptr: {}",
+				interpreter.state, interpreter.steps, interpreter.mem_ptr
+			)
+			.reversed()
+			.to_string(),
+		);
+		self.draw_program(&mut lines, width, program_rows);
+		lines.push(pane_title("memory", width));
+		self.draw_memory(&mut lines, width, memory_rows);
+		lines.push(pane_title("output", width));
+		let view = match self.debugger.output_view {
+			OutputView::Raw => OutputView::Escaped,
+			view => view,
+		};
+		push_last(
+			&mut lines,
+			view.format(&interpreter.output).lines(),
+			output_rows,
+		);
+		lines.push(pane_title("messages", width));
+		push_last(
+			&mut lines,
+			self.messages.iter().map(String::as_str),
+			message_rows,
+		);
+		lines.push(match &self.mode {
+			Mode::Keys => HELP.dimmed().to_string(),
+			Mode::Command(text) => format!(":{text}\u{2588}"),
+			Mode::ProgramInput(text) => format!("{}{text}\u{2588}", "program input> ".yellow()),
+		});
+
+		let mut frame = String::from("\x1b[H");
+		for (i, line) in lines.iter().take(height).enumerate() {
+			if i > 0 {
+				frame.push_str("\r\n");
+			}
+			frame.push_str(&fit(line, width));
+			frame.push_str("\x1b[0m\x1b[K");
+		}
+		frame.push_str("\x1b[J");
+		let mut out = stdout();
+		_ = out.write_all(frame.as_bytes());
+		_ = out.flush();
+	}
+
+	/// The source lines around the current instruction, with breakpoints marked in the gutter
+	fn draw_program(&self, lines: &mut Vec<String>, width: usize, rows: usize) {
+		let interpreter = &self.debugger.interpreter;
+		let sources = &self.debugger.sources;
+		let c = &interpreter.program[interpreter.program_ptr];
+		// the end of the program is after the last file
+		let file = match c.command {
+			Command::End => sources.len() - 1,
+			_ => c.file,
+		};
+		let source: Vec<&str> = sources[file].text.lines().collect();
+		// locations in other files are never on screen
+		let location = |index: usize| {
+			let c = &interpreter.program[index];
+			(c.file == file).then_some((c.line_number, c.column))
+		};
+		let current = location(interpreter.program_ptr).unwrap_or((0, 0));
+		let partner = c.command.matching_bracket().and_then(location);
+		let breakpoints: HashSet<_> = interpreter
+			.breakpoints
+			.iter()
+			.filter(|b| !b.disabled)
+			.filter_map(|b| match b.target {
+				BreakTarget::Index(index) => location(index),
+				_ => None,
+			})
+			.collect();
+		let current_line = match current.0 {
+			0 => source.len(),
+			line => line,
+		};
+		let first = current_line
+			.saturating_sub(rows / 2)
+			.min(source.len().saturating_sub(rows))
+			.max(1);
+		let text_width = width.saturating_sub(8);
+		let offset = if current.1 > text_width {
+			current.1 - text_width / 2
+		} else {
+			0
+		};
+		for line_number in first..first + rows {
+			let Some(text) = source.get(line_number - 1) else {
+				lines.push(String::new());
+				continue;
+			};
+			let marker = if breakpoints.iter().any(|&(line, _)| line == line_number) {
+				"●".red().to_string()
+			} else {
+				" ".into()
+			};
+			let mut out = format!("{marker}{line_number:>4} │ ");
+			for (column, c) in text
+				.chars()
+				.enumerate()
+				.skip(offset)
+				.map(|(i, c)| (i + 1, c))
+			{
+				let c = if c == '\t' { ' ' } else { c };
+				if (line_number, column) == current {
+					out.push_str(&c.highlight(AnsiColors::Cyan).to_string());
+				} else if partner == Some((line_number, column)) {
+					out.push_str(&c.on_magenta().to_string());
+				} else if breakpoints.contains(&(line_number, column)) {
+					out.push_str(&c.on_red().to_string());
+				} else {
+					out.push(c);
+				}
+			}
+			lines.push(out);
+		}
+	}
+
+	/// A grid of cells, following the pointer unless scrolled
+	fn draw_memory(&self, lines: &mut Vec<String>, width: usize, rows: usize) {
+		let interpreter = &self.debugger.interpreter;
+		let (cell_width, per_row, pointer_row) = self.memory_layout(width);
+		let last_row = interpreter.memory.len().saturating_sub(1) / per_row;
+		let first = self
+			.memory_scroll
+			.unwrap_or(pointer_row.saturating_sub(rows / 2))
+			.min(last_row.saturating_sub(rows - 1));
+		for row in first..first + rows {
+			let start = row * per_row;
+			if start >= interpreter.memory.len() {
+				lines.push(String::new());
+				continue;
+			}
+			let mut out = format!("{start:>6} │");
+			for (index, cell) in (start..).zip(interpreter.memory.range(start, start + per_row)) {
+				let text = format!(
+					"{:>cell_width$}",
+					self.debugger.mem_view.format(cell, interpreter.cell_size())
+				);
+				out.push(' ');
+				if index == interpreter.mem_ptr {
+					out.push_str(&text.highlight(AnsiColors::Red).to_string());
+				} else if self.debugger.cell_changed(index) {
+					out.push_str(&text.on_yellow().to_string());
+				} else {
+					out.push_str(&text);
+				}
+			}
+			lines.push(out);
+		}
+	}
+}
+
+fn pane_title(name: &str, width: usize) -> String {
+	let rule = "─".repeat(width.saturating_sub(name.len() + 4));
+	format!("── {name} {rule}").dimmed().to_string()
+}
+
+/// Adds the last `rows` lines, padding with empty lines when there are fewer
+fn push_last<'a>(lines: &mut Vec<String>, text: impl Iterator<Item = &'a str>, rows: usize) {
+	let text: Vec<_> = text.collect();
+	let shown = &text[text.len().saturating_sub(rows)..];
+	lines.extend(shown.iter().map(|line| line.to_string()));
+	lines.extend((shown.len()..rows).map(|_| String::new()));
+}
+
+/// Cuts a line down to `width` visible characters, not counting color codes
+fn fit(line: &str, width: usize) -> String {
+	let mut out = String::new();
+	let mut visible = 0;
+	let mut chars = line.chars();
+	while let Some(c) = chars.next() {
+		if c == '\x1b' {
+			out.push(c);
+			for c in chars.by_ref() {
+				out.push(c);
+				if c.is_ascii_alphabetic() {
+					break;
+				}
+			}
+			continue;
+		}
+		if visible == width {
+			break;
+		}
+		visible += 1;
+		out.push(c);
+	}
+	out
+}
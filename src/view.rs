@@ -0,0 +1,116 @@
+//! The state shown at every prompt, collected once and then rendered either for people or as JSON.
+
+use brainfuck::{Breakpoint, EofBehavior, MemoryWatcher, State, StopReason};
+use serde::{Serialize, Serializer};
+
+use crate::{centered_window, compare_output, Debugger};
+
+/// With `--json`, tapes up to this many cells are sent whole instead of just the cells around the pointer
+const JSON_FULL_TAPE_LIMIT: usize = 4096;
+
+#[derive(Serialize)]
+pub struct View<'a> {
+	pub state: State,
+	pub steps: usize,
+	pub max_steps: Option<usize>,
+	pub program_ptr: usize,
+	/// Source location of the current instruction
+	pub file: &'a str,
+	pub line: usize,
+	pub column: usize,
+	pub mem_ptr: usize,
+	/// Index of the first cell in `memory`
+	pub memory_start: usize,
+	pub memory: Vec<u32>,
+	/// Number of cells on the whole tape
+	pub tape_len: usize,
+	pub tape_size: Option<usize>,
+	pub eof_behavior: EofBehavior,
+	pub numeric_io: bool,
+	/// Input bytes read so far
+	pub input_ptr: usize,
+	pub input_len: usize,
+	pub input_exhausted_at: Option<usize>,
+	pub stop_reason: Option<&'a StopReason>,
+	pub watchers: &'a [MemoryWatcher],
+	pub breakpoints: &'a [Breakpoint],
+	pub expect: Option<Expect<'a>>,
+	/// Base64, since the output can be any bytes
+	#[serde(serialize_with = "base64")]
+	pub output: &'a [u8],
+}
+
+/// How the output compares to the `expect` file, once the program has ended
+#[derive(Serialize)]
+pub struct Expect<'a> {
+	pub path: &'a str,
+	/// Where the output differs, or `None` when it matches
+	pub difference: Option<String>,
+}
+
+impl Debugger {
+	pub fn collect_view(&self) -> View<'_> {
+		let interpreter = &self.interpreter;
+		let current = &interpreter.program[interpreter.program_ptr];
+		let tape_len = interpreter.memory.len();
+		let (memory_start, memory_end) = if self.json && tape_len <= JSON_FULL_TAPE_LIMIT {
+			(0, tape_len)
+		} else {
+			centered_window(interpreter.mem_ptr, self.mem_width, tape_len)
+		};
+		let expect = match (&self.expected_output, interpreter.state) {
+			(Some((path, expected)), State::EndOfProgram) => Some(Expect {
+				path,
+				difference: compare_output(expected, &interpreter.output),
+			}),
+			_ => None,
+		};
+		View {
+			state: interpreter.state,
+			steps: interpreter.steps,
+			max_steps: interpreter.max_steps,
+			program_ptr: interpreter.program_ptr,
+			file: &self.sources[current.file].name,
+			line: current.line_number,
+			column: current.column,
+			mem_ptr: interpreter.mem_ptr,
+			memory_start,
+			memory: interpreter.memory.range(memory_start, memory_end).collect(),
+			tape_len,
+			tape_size: interpreter.tape_size(),
+			eof_behavior: interpreter.eof_behavior,
+			numeric_io: interpreter.numeric_io,
+			input_ptr: interpreter.input_ptr,
+			input_len: interpreter.input.len(),
+			input_exhausted_at: interpreter.input_exhausted_at,
+			stop_reason: interpreter.stop_reason.as_ref(),
+			watchers: &interpreter.watchers,
+			breakpoints: &interpreter.breakpoints,
+			expect,
+			output: &interpreter.output,
+		}
+	}
+}
+
+fn base64<S: Serializer>(bytes: &&[u8], serializer: S) -> Result<S::Ok, S::Error> {
+	serializer.serialize_str(&to_base64(bytes))
+}
+
+pub fn to_base64(bytes: &[u8]) -> String {
+	const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+	let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+	for chunk in bytes.chunks(3) {
+		let group = chunk
+			.iter()
+			.enumerate()
+			.fold(0u32, |group, (i, &b)| group | (b as u32) << (16 - 8 * i));
+		for i in 0..4 {
+			if i <= chunk.len() {
+				out.push(ALPHABET[(group >> (18 - 6 * i)) as usize & 63] as char);
+			} else {
+				out.push('=');
+			}
+		}
+	}
+	out
+}
@@ -0,0 +1,124 @@
+//! Remembering which instruction last wrote each memory cell, and every write to recorded cells.
+
+use std::collections::VecDeque;
+
+use crate::BFInterpreter;
+
+/// Writes kept per recorded cell, older ones are dropped
+const RECORDING_LIMIT: usize = 10_000;
+
+/// The most recent write to a cell by the program
+#[derive(Debug, Clone, Copy)]
+pub struct WriteRecord {
+	/// The step count right after the write
+	pub step: usize,
+	/// Program index of the instruction that wrote the cell
+	pub index: usize,
+	pub old: u32,
+	pub new: u32,
+}
+
+/// The writes to one cell since `record` was used on it
+#[derive(Debug, Default)]
+pub(crate) struct Recording {
+	/// False once stopped, keeping what was collected
+	active: bool,
+	writes: VecDeque<WriteRecord>,
+}
+
+impl BFInterpreter {
+	/// The last write to a cell, if write tracking was on at the time
+	pub fn last_write(&self, cell: usize) -> Option<&WriteRecord> {
+		self.last_writes.get(cell)?.as_ref()
+	}
+
+	/// Starts keeping every write to a cell, continuing an earlier recording of it
+	pub fn start_recording(&mut self, cell: usize) {
+		self.recordings.entry(cell).or_default().active = true;
+	}
+
+	/// Stops recording a cell, keeping the writes collected so far
+	pub fn stop_recording(&mut self, cell: usize) -> Result<(), String> {
+		match self.recordings.get_mut(&cell) {
+			Some(recording) if recording.active => {
+				recording.active = false;
+				Ok(())
+			}
+			_ => Err(format!("cell {cell} is not being recorded")),
+		}
+	}
+
+	/// The recorded writes to a cell, oldest first
+	pub fn recorded_writes(&self, cell: usize) -> Option<&VecDeque<WriteRecord>> {
+		self.recordings.get(&cell).map(|r| &r.writes)
+	}
+
+	/// The cells that are being recorded right now
+	pub fn recorded_cells(&self) -> impl Iterator<Item = usize> + '_ {
+		self.recordings
+			.iter()
+			.filter(|(_, r)| r.active)
+			.map(|(&cell, _)| cell)
+	}
+
+	pub(crate) fn is_recording(&self) -> bool {
+		self.recordings.values().any(|r| r.active)
+	}
+
+	/// Called after `+`, `-`, `,` or a clear changed the current cell
+	pub(crate) fn record_write(&mut self, index: usize, old: u32) {
+		let write = WriteRecord {
+			step: self.steps,
+			index,
+			old,
+			new: self.memory[self.mem_ptr],
+		};
+		if let Some(recording) = self.recordings.get_mut(&self.mem_ptr) {
+			if recording.active {
+				if recording.writes.len() == RECORDING_LIMIT {
+					recording.writes.pop_front();
+				}
+				recording.writes.push_back(write);
+			}
+		}
+		if !self.track_writes {
+			return;
+		}
+		*self.last_writes.get_mut(self.mem_ptr) = Some(write);
+	}
+
+	/// Remembers a write made by the folded program, which changes cells without `record_write`
+	pub(crate) fn record_fast_write(&mut self, cell: usize, step: usize, index: usize, old: u32) {
+		if self.track_writes {
+			*self.last_writes.get_mut(cell) = Some(WriteRecord {
+				step,
+				index,
+				old,
+				new: self.memory[cell],
+			});
+		}
+	}
+
+	/// Forgets all writes, when the program starts over at a different step
+	pub(crate) fn clear_writes(&mut self) {
+		self.last_writes.clear();
+		for recording in self.recordings.values_mut() {
+			recording.writes.clear();
+		}
+	}
+
+	/// Drops writes that happened after the current step, once it has been stepped back.
+	/// The writes before them are not remembered, so those cells show no write at all
+	pub(crate) fn forget_later_writes(&mut self) {
+		for record in self.last_writes.values_mut() {
+			if record.is_some_and(|r| r.step > self.steps) {
+				*record = None;
+			}
+		}
+		for recording in self.recordings.values_mut() {
+			while recording.writes.back().is_some_and(|w| w.step > self.steps) {
+				recording.writes.pop_back();
+			}
+		}
+	}
+}
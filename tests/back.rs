@@ -0,0 +1,76 @@
+//! Stepping back must leave the program exactly as it was before the undone instructions.
+
+mod common;
+
+use brainfuck::{BFInterpreter, State, TapeMode};
+use common::{interpreter, memory};
+
+/// Everything `back` is expected to put back, in a comparable form
+fn state(bf: &BFInterpreter) -> String {
+	format!(
+		"{:?}",
+		(
+			memory(bf),
+			bf.mem_ptr,
+			bf.program_ptr,
+			&bf.output,
+			bf.input_ptr,
+			bf.input_exhausted_at,
+			bf.steps,
+			bf.state,
+			&bf.loop_stack,
+			bf.max_memory_len(),
+			bf.last_wrap_step,
+		)
+	)
+}
+
+/// Steps through the whole program, then backs out of it one instruction at a time
+fn check_back(mut bf: BFInterpreter) {
+	let mut states = Vec::new();
+	while bf.state == State::Running {
+		let (before, steps) = (state(&bf), bf.steps);
+		bf.step_once();
+		if bf.steps > steps {
+			states.push(before);
+		}
+	}
+	while let Some(expected) = states.pop() {
+		bf.back(1).unwrap();
+		assert_eq!(
+			state(&bf),
+			expected,
+			"after stepping back to step {}",
+			bf.steps
+		);
+	}
+	assert!(bf.back(1).is_err());
+}
+
+#[test]
+fn back_undoes_every_command() {
+	// `+ - < > , .`, with the second `,` reading past the end of the input
+	check_back(interpreter("+>+>-<<.>,>,.<.", b"a"));
+}
+
+#[test]
+fn back_undoes_loop_entry_repeat_and_exit() {
+	check_back(interpreter("++[>+[-]<-]>[+]+.", b""));
+}
+
+#[test]
+fn back_undoes_moving_around_a_circular_tape() {
+	let mut bf = interpreter("<+>>+<.", b"");
+	bf.set_tape(Some(2), TapeMode::Wrap).unwrap();
+	check_back(bf);
+}
+
+#[test]
+fn back_forgets_how_far_the_tape_grew() {
+	let mut bf = interpreter(">>>+", b"");
+	bf.step(4);
+	assert_eq!(bf.max_memory_len(), 4);
+	bf.back(4).unwrap();
+	assert_eq!((bf.steps, bf.max_memory_len()), (0, 1));
+	assert!(bf.back(1).is_err());
+}
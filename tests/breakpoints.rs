@@ -0,0 +1,37 @@
+//! Breakpoints on program indices, including the ones nothing steps onto.
+
+mod common;
+
+use brainfuck::{BreakTarget, State};
+use common::{interpreter, memory};
+
+#[test]
+fn breakpoint_on_the_first_instruction_stops_before_it() {
+	let mut bf = interpreter("+>+", b"");
+	bf.add_breakpoint(BreakTarget::Index(0), None).unwrap();
+	bf.run();
+	assert_eq!(bf.state, State::BreakPointHit);
+	assert_eq!((bf.program_ptr, bf.steps), (0, 0));
+
+	// continuing goes past it instead of stopping on it again
+	bf.run();
+	assert_eq!(bf.state, State::EndOfProgram);
+	assert_eq!(memory(&bf), [1, 1]);
+
+	bf.reset();
+	bf.run();
+	assert_eq!(bf.state, State::BreakPointHit);
+	assert_eq!(bf.steps, 0);
+}
+
+#[test]
+fn breakpoint_after_goto_stops_before_running() {
+	let mut bf = interpreter("+>+>+", b"");
+	bf.add_breakpoint(BreakTarget::Index(2), None).unwrap();
+	bf.goto(2).unwrap();
+	bf.run();
+	assert_eq!(bf.state, State::BreakPointHit);
+	assert_eq!((bf.program_ptr, bf.steps), (2, 0));
+	bf.run();
+	assert_eq!(bf.state, State::EndOfProgram);
+}
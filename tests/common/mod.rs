@@ -0,0 +1,19 @@
+//! Helpers shared by the integration tests.
+
+// every test file compiles this module on its own and uses only part of it
+#![allow(dead_code)]
+
+use brainfuck::{optimize_clear_loops, parse, BFInterpreter};
+
+pub fn interpreter(code: &str, input: &[u8]) -> BFInterpreter {
+	BFInterpreter::new(parse(code).unwrap(), input.to_vec())
+}
+
+/// Like `interpreter`, with clear loops replaced by `Clear` and no input
+pub fn optimized(code: &str) -> BFInterpreter {
+	BFInterpreter::new(optimize_clear_loops(parse(code).unwrap()), Vec::new())
+}
+
+pub fn memory(bf: &BFInterpreter) -> Vec<u32> {
+	bf.memory.iter().collect()
+}
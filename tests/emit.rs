@@ -0,0 +1,99 @@
+//! Programs emitted as C must print exactly what `--run` prints.
+
+use std::{
+	fs,
+	io::Write,
+	path::Path,
+	process::{Command, Stdio},
+};
+
+const HELLO_WORLD: &str = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+const CAT: &str = ",[.,]";
+const INPUT: &[u8] = b"h\xe9llo, w\xf6rld\n\xff\x01";
+
+fn has_compiler() -> bool {
+	Command::new("cc")
+		.arg("--version")
+		.stdout(Stdio::null())
+		.stderr(Stdio::null())
+		.status()
+		.is_ok_and(|status| status.success())
+}
+
+/// Runs `command` with `input` on stdin and returns what it printed
+fn output(mut command: Command, input: &[u8]) -> Vec<u8> {
+	let mut child = command
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.spawn()
+		.unwrap();
+	child.stdin.take().unwrap().write_all(input).unwrap();
+	let out = child.wait_with_output().unwrap();
+	assert!(
+		out.status.success(),
+		"{command:?} failed with {}",
+		out.status
+	);
+	out.stdout
+}
+
+fn brainfuck(dir: &Path, source: &Path, cell_size: &str) -> Command {
+	let mut command = Command::new(env!("CARGO_BIN_EXE_brainfuck"));
+	command
+		.current_dir(dir)
+		.args(["--color=never", "--cell-size", cell_size])
+		.arg(source);
+	command
+}
+
+fn compare(name: &str, code: &str, input: &[u8]) {
+	for cell_size in ["8", "16", "32"] {
+		let dir =
+			std::env::temp_dir().join(format!("bf-emit-{}-{name}-{cell_size}", std::process::id()));
+		fs::create_dir_all(&dir).unwrap();
+		let source = dir.join("program.b");
+		let input_file = dir.join("input.txt");
+		fs::write(&source, code).unwrap();
+		fs::write(&input_file, input).unwrap();
+
+		let mut emit = brainfuck(&dir, &source, cell_size);
+		emit.args(["--emit", "c", "program.c"]);
+		output(emit, b"");
+		let status = Command::new("cc")
+			.current_dir(&dir)
+			.args(["-O1", "-o", "program", "program.c"])
+			.status()
+			.unwrap();
+		assert!(
+			status.success(),
+			"{name} at {cell_size} bits did not compile"
+		);
+		let native = output(Command::new(dir.join("program")), input);
+
+		let mut run = brainfuck(&dir, &source, cell_size);
+		run.args(["--run", "--input"]).arg(&input_file);
+		let interpreted = output(run, b"");
+
+		assert!(!interpreted.is_empty(), "{name} printed nothing");
+		assert_eq!(native, interpreted, "{name} at {cell_size} bits");
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
+
+#[test]
+fn emitted_hello_world_matches_run() {
+	if !has_compiler() {
+		eprintln!("no C compiler, skipping");
+		return;
+	}
+	compare("hello", HELLO_WORLD, b"");
+}
+
+#[test]
+fn emitted_cat_matches_run() {
+	if !has_compiler() {
+		eprintln!("no C compiler, skipping");
+		return;
+	}
+	compare("cat", CAT, INPUT);
+}
@@ -0,0 +1,106 @@
+//! Stepping semantics of the interpreter, driven through the library API.
+
+mod common;
+
+use brainfuck::{CellSize, EofBehavior, State};
+use common::{interpreter, memory};
+
+#[test]
+fn dec_on_zero_wraps_around() {
+	let mut bf = interpreter("-", b"");
+	bf.step(1);
+	assert_eq!(memory(&bf), [255]);
+	assert_eq!(bf.state, State::Running);
+	bf.step(1);
+	assert_eq!(bf.state, State::EndOfProgram);
+
+	let mut bf = interpreter("-", b"");
+	bf.set_cell_size(CellSize::Bits16);
+	bf.run();
+	assert_eq!(memory(&bf), [65535]);
+}
+
+#[test]
+fn dec_on_zero_stops_without_wrapping() {
+	let mut bf = interpreter("+--", b"");
+	bf.wrap = false;
+	bf.run();
+	assert_eq!(bf.state, State::CellUnderflow);
+	// the `-` that would wrap is not executed
+	assert_eq!((bf.program_ptr, bf.steps), (2, 2));
+	assert_eq!(memory(&bf), [0]);
+}
+
+#[test]
+fn loop_runs_until_its_cell_is_zero() {
+	let mut bf = interpreter("++[>+++<-]>.", b"");
+	// `++`, then `[`, and the first pass through the body up to the `]`
+	bf.step(9);
+	assert_eq!(memory(&bf), [1, 3]);
+	assert_eq!(bf.program_ptr, 9);
+	bf.step(1);
+	assert_eq!(bf.program_ptr, 3);
+	bf.run();
+	assert_eq!(bf.state, State::EndOfProgram);
+	assert_eq!(memory(&bf), [0, 6]);
+	assert_eq!(bf.output, [6]);
+	assert_eq!(bf.steps, 19);
+}
+
+#[test]
+fn leading_left_stops_too_far_left() {
+	let mut bf = interpreter("<+", b"");
+	bf.step(5);
+	assert_eq!(bf.state, State::TooFarLeft);
+	assert_eq!((bf.program_ptr, bf.steps, bf.mem_ptr), (0, 0, 0));
+	assert_eq!(memory(&bf), [0]);
+}
+
+#[test]
+fn read_past_the_input_stores_the_eof_value() {
+	for (behavior, expected) in [
+		(EofBehavior::Zero, [b'a' as u32, 0]),
+		(EofBehavior::Max, [b'a' as u32, 255]),
+		(EofBehavior::Unchanged, [b'a' as u32, 7]),
+	] {
+		let mut bf = interpreter(",>+++++++,", b"a");
+		bf.eof_behavior = behavior;
+		bf.run();
+		assert_eq!(bf.state, State::EndOfProgram, "{behavior}");
+		assert_eq!(memory(&bf), expected, "{behavior}");
+		assert_eq!(bf.input_ptr, 1, "{behavior}");
+		// the first read finds input, the last one does not
+		assert_eq!(bf.input_exhausted_at, Some(10), "{behavior}");
+	}
+}
+
+#[test]
+fn off_tape_error_recovers_with_skip_and_continue() {
+	let mut bf = interpreter("+[-<]+.", b"");
+	bf.run();
+	assert_eq!(bf.state, State::TooFarLeft);
+	// the `<` is left unexecuted, and its location is still known
+	assert_eq!((bf.program_ptr, bf.steps, bf.mem_ptr), (3, 3, 0));
+	let c = bf.program[bf.program_ptr];
+	assert_eq!((c.line_number, c.column), (1, 4));
+
+	// nothing goes on until the error is cleared
+	bf.step(1);
+	bf.step_once();
+	bf.finish();
+	bf.run_until_location(0, 1, Some(7)).unwrap();
+	assert_eq!(bf.state, State::TooFarLeft);
+	assert_eq!((bf.program_ptr, bf.steps), (3, 3));
+
+	bf.skip().unwrap();
+	bf.finish();
+	bf.run_until_location(0, 1, Some(7)).unwrap();
+	assert_eq!((bf.program_ptr, bf.steps), (4, 3));
+	assert_eq!(bf.state, State::TooFarLeft);
+
+	bf.clear_error();
+	bf.run();
+	assert_eq!(bf.state, State::EndOfProgram);
+	assert_eq!(bf.output, [1]);
+	assert_eq!(bf.steps, 6);
+}
@@ -0,0 +1,73 @@
+//! Cell names from `@index:name` annotations must be shown over the cells they name.
+
+use std::{
+	fs,
+	io::Write,
+	process::{Command, Stdio},
+};
+
+const PROGRAM: &str = "@0:count @2:sum @5:big_total\n+>++>+++>>>+\n";
+
+/// Each word of a row, with the byte offset it ends at
+fn word_ends(row: &str) -> Vec<(&str, usize)> {
+	let mut ends = Vec::new();
+	let mut start = None;
+	for (i, c) in row.char_indices().chain([(row.len(), ' ')]) {
+		match (c == ' ', start) {
+			(true, Some(s)) => {
+				ends.push((&row[s..i], i));
+				start = None;
+			}
+			(false, None) => start = Some(i),
+			_ => (),
+		}
+	}
+	ends
+}
+
+#[test]
+fn annotated_names_are_shown_over_their_cells() {
+	let dir = std::env::temp_dir().join(format!("bf-labels-{}", std::process::id()));
+	fs::create_dir_all(&dir).unwrap();
+	let source = dir.join("program.b");
+	fs::write(&source, PROGRAM).unwrap();
+	let mut child = Command::new(env!("CARGO_BIN_EXE_brainfuck"))
+		.arg("--color=never")
+		.arg(&source)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.spawn()
+		.unwrap();
+	child
+		.stdin
+		.take()
+		.unwrap()
+		.write_all(b"run\nquit\n")
+		.unwrap();
+	let out = child.wait_with_output().unwrap();
+	fs::remove_dir_all(&dir).unwrap();
+	let out = String::from_utf8(out.stdout).unwrap();
+
+	// the memory shown after the run, with all six cells
+	let lines: Vec<&str> = out.lines().collect();
+	let row = lines.iter().rposition(|l| l.starts_with("lbl:")).unwrap();
+	let (labels, memory, indices) = (lines[row], lines[row + 1], lines[row + 2]);
+	let names = word_ends(labels);
+	let values = word_ends(memory);
+	let cells = word_ends(indices);
+	assert_eq!(
+		names.iter().map(|&(name, _)| name).collect::<Vec<_>>(),
+		["lbl:", "count", "sum", "big_total"]
+	);
+	for ((name, end), cell) in names[1..].iter().zip(["0", "2", "5"]) {
+		let (_, cell_end) = cells.iter().find(|&&(index, _)| index == cell).unwrap();
+		assert_eq!(
+			end, cell_end,
+			"{name} is not over cell {cell}:\n{labels}\n{indices}"
+		);
+	}
+	assert_eq!(
+		values.iter().map(|&(value, _)| value).collect::<Vec<_>>(),
+		["mem:", "1", "2", "3", "0", "0", "1"]
+	);
+}
@@ -0,0 +1,46 @@
+//! Clear loops run as a single `Clear` must leave the same results as the loops they replace.
+
+mod common;
+
+use brainfuck::{Comparison, State, StopReason, WatchCondition};
+use common::{interpreter, memory, optimized};
+
+#[test]
+fn clear_loops_match_the_unoptimized_program() {
+	let programs = [
+		"+++++[-].",
+		"+++++[+].",
+		"++>+++++[-]<[-]>+.<.",
+		"-[-]>--[+]+.>.<<.",
+		"++++[>++++++<-]>[-]+.",
+	];
+	for code in programs {
+		let mut plain = interpreter(code, b"");
+		let mut optimized = optimized(code);
+		plain.run();
+		optimized.run();
+		assert_eq!(optimized.state, State::EndOfProgram, "{code}");
+		assert_eq!(optimized.state, plain.state, "{code}");
+		assert_eq!(optimized.output, plain.output, "{code}");
+		assert_eq!(memory(&optimized), memory(&plain), "{code}");
+		assert_eq!(optimized.mem_ptr, plain.mem_ptr, "{code}");
+	}
+}
+
+#[test]
+fn watch_for_zero_fires_through_clear() {
+	for code in ["+++[-]>", "+++[+]>"] {
+		let mut bf = optimized(code);
+		let id = bf
+			.add_watch(0, WatchCondition::Compare(Comparison::Equal, 0))
+			.unwrap();
+		bf.run();
+		assert_eq!(bf.state, State::StoppedOnMemoryValue, "{code}");
+		let Some(StopReason::Watcher { watcher, value }) = &bf.stop_reason else {
+			panic!("{code} stopped without the watcher: {:?}", bf.stop_reason);
+		};
+		assert_eq!((watcher.id, *value), (id, 0), "{code}");
+		// stopped right after the clear
+		assert_eq!(bf.program_ptr, 4, "{code}");
+	}
+}
@@ -0,0 +1,131 @@
+//! Turning source text into commands, and what is reported about the text on the way.
+
+mod common;
+
+use brainfuck::{
+	parse, parse_files, parse_value, strict_warnings, CellSize, ParseError, ParseErrorKind,
+};
+use common::interpreter;
+
+fn error(kind: ParseErrorKind, file: usize, line_number: usize, column: usize) -> ParseError {
+	ParseError {
+		kind,
+		file,
+		line_number,
+		column,
+	}
+}
+
+fn suspicious(c: char, file: usize, line_number: usize, column: usize) -> ParseError {
+	error(ParseErrorKind::Suspicious(c), file, line_number, column)
+}
+
+#[test]
+fn strict_warns_about_digits_and_underscores_but_not_annotations() {
+	let source = "add 2 +{=1}\n@0:cell_2 x_y; café\n";
+	assert_eq!(
+		strict_warnings(source, 3),
+		[
+			suspicious('2', 3, 1, 5),
+			suspicious('_', 3, 2, 12),
+			suspicious(';', 3, 2, 14),
+		]
+	);
+}
+
+#[test]
+fn unmatched_brackets_are_all_reported_in_order() {
+	use ParseErrorKind::{UnclosedOpen, UnmatchedClose};
+	let cases = [
+		("[[", vec![(UnclosedOpen, 1, 1), (UnclosedOpen, 1, 2)]),
+		("]]", vec![(UnmatchedClose, 1, 1), (UnmatchedClose, 1, 2)]),
+		("[[\n+]", vec![(UnclosedOpen, 1, 1)]),
+		(
+			"+]\n[[-]\n]]",
+			vec![(UnmatchedClose, 1, 2), (UnmatchedClose, 3, 2)],
+		),
+		// the unclosed `[` is found last, but reported in source order
+		(
+			"[\n ]]\n [",
+			vec![(UnmatchedClose, 2, 3), (UnclosedOpen, 3, 2)],
+		),
+		(
+			"[ ]]\n\n  [[]",
+			vec![(UnmatchedClose, 1, 4), (UnclosedOpen, 3, 3)],
+		),
+	];
+	for (code, expected) in cases {
+		let expected: Vec<_> = expected
+			.into_iter()
+			.map(|(kind, line, column)| error(kind, 0, line, column))
+			.collect();
+		assert_eq!(parse(code).unwrap_err(), expected, "{code:?}");
+	}
+}
+
+#[test]
+fn brackets_match_across_files_and_errors_name_their_file() {
+	assert!(parse_files(&["+[", "-]"]).is_ok());
+	assert_eq!(
+		parse_files(&["[\n[", "]", "]\n]"]).unwrap_err(),
+		[error(ParseErrorKind::UnmatchedClose, 2, 2, 1)]
+	);
+	assert_eq!(
+		parse_files(&["]", "[["]).unwrap_err(),
+		[
+			error(ParseErrorKind::UnmatchedClose, 0, 1, 1),
+			error(ParseErrorKind::UnclosedOpen, 1, 1, 1),
+			error(ParseErrorKind::UnclosedOpen, 1, 1, 2),
+		]
+	);
+}
+
+#[test]
+fn values_parse_as_numbers_hex_and_characters() {
+	let cases: [(&str, Result<u32, &str>); 16] = [
+		("65", Ok(65)),
+		("1_000", Ok(1000)),
+		("0x41", Ok(0x41)),
+		("0xff_ff", Ok(0xffff)),
+		("'A'", Ok(65)),
+		("'\\n'", Ok(10)),
+		("'\\0'", Ok(0)),
+		("'\\''", Ok(39)),
+		("'\\x7f'", Ok(127)),
+		("4294967295", Ok(u32::MAX)),
+		("'ab'", Err("'ab' is more than one character")),
+		("''", Err("'' has no character in it")),
+		("'\\q'", Err("unknown escape in '\\q'")),
+		(
+			"0x",
+			Err("'0x' is not a number, a 0x hex number or a 'c' character"),
+		),
+		(
+			"_1",
+			Err("'_1' is not a number, a 0x hex number or a 'c' character"),
+		),
+		("4294967296", Err("4294967296 is too large for a cell")),
+	];
+	for (text, expected) in cases {
+		assert_eq!(parse_value(text), expected.map_err(String::from), "{text}");
+	}
+}
+
+#[test]
+fn values_must_fit_the_cell_size() {
+	let cases = [
+		(CellSize::Bits8, "255", "256"),
+		(CellSize::Bits8, "0xff", "0x100"),
+		(CellSize::Bits16, "65535", "65536"),
+		(CellSize::Bits16, "0xffff", "0x1_0000"),
+		(CellSize::Bits32, "0xffff_ffff", "0x1_0000_0000"),
+	];
+	for (size, largest, too_large) in cases {
+		let mut bf = interpreter("", b"");
+		bf.set_cell_size(size);
+		let value = parse_value(largest).unwrap();
+		assert_eq!(bf.set_cell(0, value), Ok(()), "{largest} at {size:?}");
+		let rejected = parse_value(too_large).and_then(|value| bf.set_cell(0, value));
+		assert!(rejected.is_err(), "{too_large} at {size:?}");
+	}
+}
@@ -0,0 +1,109 @@
+//! Transfer loops run in one go by `run` must end up where stepping through them does.
+
+mod common;
+
+use brainfuck::{BFInterpreter, CellSize, State};
+use common::{interpreter, memory};
+
+const HELLO_WORLD: &str = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+
+/// What a program left behind: state, memory pointer, memory and output
+type Outcome = (State, usize, Vec<u32>, Vec<u8>);
+
+fn sized(code: &str, size: CellSize) -> BFInterpreter {
+	let mut bf = interpreter(code, b"");
+	bf.set_cell_size(size);
+	bf
+}
+
+fn outcome(bf: BFInterpreter) -> Outcome {
+	(bf.state, bf.mem_ptr, memory(&bf), bf.output)
+}
+
+/// Runs on the fast path, with or without transfer loops
+fn run(code: &str, size: CellSize, transfer_loops: bool) -> Outcome {
+	let mut bf = sized(code, size);
+	bf.set_history_limit(0);
+	bf.track_writes = false;
+	bf.transfer_loops = transfer_loops;
+	bf.run();
+	outcome(bf)
+}
+
+fn step_through(code: &str, size: CellSize) -> Outcome {
+	let mut bf = sized(code, size);
+	while bf.state == State::Running {
+		bf.step_once();
+	}
+	outcome(bf)
+}
+
+#[test]
+fn transfer_loops_match_stepping() {
+	let programs = [
+		("hello world", HELLO_WORLD, CellSize::Bits8),
+		("multiply", "+++++[->+>++<<]>.>.", CellSize::Bits8),
+		(
+			"negative offset",
+			">>+++++++[-<<+++>+>]<.<.",
+			CellSize::Bits8,
+		),
+		("wrapped 16 bit cell", "-[->+++<]>.", CellSize::Bits16),
+	];
+	for (name, code, size) in programs {
+		let stepped = step_through(code, size);
+		assert_eq!(stepped.0, State::EndOfProgram, "{name}");
+		assert_eq!(run(code, size, true), stepped, "{name} with transfer loops");
+		assert_eq!(
+			run(code, size, false),
+			stepped,
+			"{name} without transfer loops"
+		);
+	}
+}
+
+#[test]
+fn wrapped_16_bit_transfer_keeps_the_high_bits() {
+	let (_, _, memory, _) = run("-[->+++<]", CellSize::Bits16, true);
+	assert_eq!(memory, [0, 65533]);
+}
+
+#[test]
+fn run_in_a_default_session_takes_the_fast_path() {
+	let programs = [HELLO_WORLD, "+++++[->+>++<<]>.>.", "++[->+-<]>[-]+"];
+	for code in programs {
+		let mut stepped = interpreter(code, b"");
+		while stepped.state == State::Running {
+			stepped.step_once();
+		}
+		let mut bf = interpreter(code, b"");
+		bf.run();
+		assert_eq!(bf.steps, stepped.steps, "{code}");
+		assert_eq!(
+			(bf.state, bf.mem_ptr, memory(&bf), &bf.output),
+			(
+				stepped.state,
+				stepped.mem_ptr,
+				memory(&stepped),
+				&stepped.output
+			),
+			"{code}"
+		);
+		for cell in 0..bf.memory.len() {
+			assert_eq!(
+				format!("{:?}", bf.last_write(cell)),
+				format!("{:?}", stepped.last_write(cell)),
+				"{code} cell {cell}"
+			);
+		}
+		// the whole run is a single history entry, which only the fast path records
+		bf.back(1).unwrap();
+		assert_eq!(
+			(bf.steps, bf.program_ptr, bf.state),
+			(0, 0, State::Running),
+			"{code}"
+		);
+		assert!(bf.output.is_empty() && memory(&bf) == [0], "{code}");
+		assert!(bf.back(1).is_err(), "{code}");
+	}
+}
@@ -0,0 +1,99 @@
+//! Memory watchers, checked against every way a cell can change.
+
+mod common;
+
+use brainfuck::{BFInterpreter, Comparison, State, StopReason, WatchCondition};
+use common::{interpreter, memory, optimized};
+
+/// The id and value of the watcher the program stopped on
+fn fired(bf: &BFInterpreter) -> Option<(usize, u32)> {
+	match &bf.stop_reason {
+		Some(StopReason::Watcher { watcher, value }) => Some((watcher.id, *value)),
+		_ => None,
+	}
+}
+
+#[test]
+fn watch_triggers_through_read() {
+	let mut bf = interpreter(">>,>", b"A");
+	let id = bf
+		.add_watch(2, WatchCondition::Compare(Comparison::Equal, b'A' as u32))
+		.unwrap();
+	bf.run();
+	assert_eq!(bf.state, State::StoppedOnMemoryValue);
+	assert_eq!(fired(&bf), Some((id, b'A' as u32)));
+	// stopped right after the `,`
+	assert_eq!(bf.program_ptr, 3);
+}
+
+#[test]
+fn watch_ignores_other_cells_under_the_pointer() {
+	// cell 0 goes up to 3, and the pointer comes back to cell 1 after every change
+	let mut bf = interpreter("><+><+><+>", b"");
+	bf.add_watch(1, WatchCondition::Changed { last: 0 })
+		.unwrap();
+	bf.add_watch(1, WatchCondition::Compare(Comparison::Equal, 3))
+		.unwrap();
+	bf.run();
+	assert_eq!(bf.state, State::EndOfProgram);
+	assert_eq!(fired(&bf), None);
+	assert_eq!(memory(&bf), [3, 0]);
+	assert_eq!(bf.mem_ptr, 1);
+}
+
+#[test]
+fn watch_triggers_on_input() {
+	let mut bf = interpreter(",>,", b"xy");
+	let id = bf
+		.add_watch(0, WatchCondition::Changed { last: 0 })
+		.unwrap();
+	bf.run();
+	assert_eq!(bf.state, State::StoppedOnMemoryValue);
+	assert_eq!(fired(&bf), Some((id, b'x' as u32)));
+	assert_eq!(bf.input_ptr, 1);
+}
+
+#[test]
+fn watch_triggers_on_optimized_clear_loop() {
+	let mut bf = optimized("+++++[-]+");
+	let id = bf
+		.add_watch(0, WatchCondition::Compare(Comparison::Equal, 0))
+		.unwrap();
+	bf.run();
+	assert_eq!(bf.state, State::StoppedOnMemoryValue);
+	assert_eq!(fired(&bf), Some((id, 0)));
+	// stopped right after the clear, before the last `+`
+	assert_eq!(bf.program_ptr, 6);
+}
+
+#[test]
+fn watch_triggers_on_manual_edit() {
+	let mut bf = interpreter("+", b"");
+	let id = bf
+		.add_watch(3, WatchCondition::Compare(Comparison::Equal, 10))
+		.unwrap();
+	bf.set_cell(3, 10).unwrap();
+	assert_eq!(bf.state, State::StoppedOnMemoryValue);
+	assert_eq!(fired(&bf), Some((id, 10)));
+}
+
+#[test]
+fn change_watch_compares_against_the_memory_after_back() {
+	let mut bf = interpreter("+,", b"");
+	let id = bf
+		.add_watch(0, WatchCondition::Changed { last: 0 })
+		.unwrap();
+	bf.step_once();
+	assert_eq!(fired(&bf), Some((id, 1)));
+	bf.back(1).unwrap();
+	// the same change happens again, and is seen again
+	bf.step_once();
+	assert_eq!(fired(&bf), Some((id, 1)));
+
+	// the `,` reads EOF into the cell that is 0 again after `back`, which is no change
+	bf.back(1).unwrap();
+	bf.goto(1).unwrap();
+	bf.run();
+	assert_eq!(bf.state, State::EndOfProgram);
+	assert_eq!(fired(&bf), None);
+}